@@ -0,0 +1,361 @@
+//! Bounding volume hierarchy to speed up the search for the nearest hit of a ray.
+//! The tree is build with the surface area heuristic and stored flat in a vector.
+//! Nodes are in depth first order, so the first child of a node is always the next node in the vector.
+
+use crate::geometry::{Aabb, Location};
+use crate::scene::{Hit, Hittable, Ray};
+use std::sync::Arc;
+
+/// Number of buckets used to evaluate the split positions
+const SAH_BUCKETS: usize = 12;
+/// Maximum number of primitives in a leaf
+const MAX_LEAF_SIZE: usize = 4;
+/// Cost of traversing a node relativ to the intersection with a primitive
+const TRAVERSAL_COST: f64 = 0.125;
+/// Depth after which the primitives are split in the middle instead with the heuristic.
+/// This limits the depth of the tree, so that a fixed size stack can be used for the traversal.
+const MAX_SAH_DEPTH: usize = 32;
+
+#[derive(Debug, Copy, Clone)]
+struct Node {
+    /// Bounding box of everything below the node
+    bounds: Aabb,
+    /// Leaf: index of the first primitive. Inner node: index of the second child.
+    offset: usize,
+    /// Number of primitives in the leaf. Zero for inner nodes.
+    count: usize,
+    /// Axis the node is split along. Used to visit the nearer child first.
+    axis: usize,
+}
+
+/// Flattened bvh over primitives that are identified by their index.
+/// Can be used for any kind of primitive, see `Bvh` for a hierarchy of objects.
+#[derive(Debug, Clone)]
+pub struct BvhTree {
+    nodes: Vec<Node>,
+    /// Primitive indices in the order they are referenced by the leaves
+    indices: Vec<usize>,
+}
+
+/// Helper data about a primitive during the build
+#[derive(Copy, Clone)]
+struct BuildPrimitive {
+    index: usize,
+    bounds: Aabb,
+    centroid: Location,
+}
+
+impl BvhTree {
+    /// Builds the tree for primitives with the given bounding boxes
+    pub fn new(bounds: &[Aabb]) -> Self {
+        let mut primitives: Vec<BuildPrimitive> = bounds
+            .iter()
+            .enumerate()
+            .map(|(index, bounds)| BuildPrimitive {
+                index,
+                bounds: *bounds,
+                centroid: bounds.centroid(),
+            })
+            .collect();
+        let mut tree = Self {
+            nodes: Vec::with_capacity(2 * bounds.len()),
+            indices: Vec::with_capacity(bounds.len()),
+        };
+        if !primitives.is_empty() {
+            tree.build(&mut primitives, 0);
+        }
+        tree
+    }
+
+    /// Recursively builds the nodes for the given primitives. Returns the index of the created node.
+    fn build(&mut self, primitives: &mut [BuildPrimitive], depth: usize) -> usize {
+        let bounds = primitives
+            .iter()
+            .skip(1)
+            .fold(primitives[0].bounds, |bounds, p| bounds.union(&p.bounds));
+        let node_index = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            offset: self.indices.len(),
+            count: primitives.len(),
+            axis: 0,
+        });
+
+        let split = if primitives.len() <= 1 {
+            None
+        } else if depth >= MAX_SAH_DEPTH {
+            Self::median_split(primitives, &bounds)
+        } else {
+            Self::find_split(primitives, &bounds)
+        };
+
+        match split {
+            Some((axis, mid)) => {
+                self.build(&mut primitives[..mid], depth + 1);
+                let second = self.build(&mut primitives[mid..], depth + 1);
+                let node = &mut self.nodes[node_index];
+                node.offset = second;
+                node.count = 0;
+                node.axis = axis;
+            }
+            None => {
+                self.indices.extend(primitives.iter().map(|p| p.index));
+            }
+        }
+        node_index
+    }
+
+    /// Searches the best split with the surface area heuristic and partitions the primitives.
+    /// Returns the split axis and the number of primitives in the first half, or None if a leaf is cheaper.
+    fn find_split(primitives: &mut [BuildPrimitive], bounds: &Aabb) -> Option<(usize, usize)> {
+        let centroid_bounds = primitives.iter().fold(
+            Aabb::new(primitives[0].centroid, primitives[0].centroid),
+            |b, p| b.include(p.centroid),
+        );
+        let axis = centroid_bounds.longest_axis();
+        let c_min = centroid_bounds.min.as_slice()[axis];
+        let c_max = centroid_bounds.max.as_slice()[axis];
+
+        if c_max - c_min <= 0.0 {
+            // All centroids are at the same spot. Only split if the leaf would get too large
+            if primitives.len() <= MAX_LEAF_SIZE {
+                return None;
+            }
+            let mid = primitives.len() / 2;
+            return Some((axis, mid));
+        }
+
+        let bucket_of = |p: &BuildPrimitive| -> usize {
+            let b = ((p.centroid.as_slice()[axis] - c_min) / (c_max - c_min) * SAH_BUCKETS as f64)
+                as usize;
+            b.min(SAH_BUCKETS - 1)
+        };
+
+        // Sort the primitives into buckets
+        let mut counts = [0usize; SAH_BUCKETS];
+        let mut boxes: [Option<Aabb>; SAH_BUCKETS] = [None; SAH_BUCKETS];
+        for p in primitives.iter() {
+            let b = bucket_of(p);
+            counts[b] += 1;
+            boxes[b] = Some(boxes[b].map_or(p.bounds, |bounds| bounds.union(&p.bounds)));
+        }
+
+        // Evaluate the cost for splitting after each bucket
+        let mut best: Option<(f64, usize)> = None;
+        for split in 0..SAH_BUCKETS - 1 {
+            let (count_a, box_a) = Self::merge_buckets(&counts[..=split], &boxes[..=split]);
+            let (count_b, box_b) = Self::merge_buckets(&counts[split + 1..], &boxes[split + 1..]);
+            if count_a == 0 || count_b == 0 {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (count_a as f64 * box_a.map_or(0.0, |b| b.surface_area())
+                    + count_b as f64 * box_b.map_or(0.0, |b| b.surface_area()))
+                    / bounds.surface_area().max(f64::MIN_POSITIVE);
+            if best.is_none_or(|(best_cost, _)| cost < best_cost) {
+                best = Some((cost, split));
+            }
+        }
+
+        let (cost, split) = best?;
+        // A leaf costs one intersection per primitive
+        if primitives.len() <= MAX_LEAF_SIZE && cost >= primitives.len() as f64 {
+            return None;
+        }
+
+        // Partition the primitives
+        let mut mid = 0;
+        for i in 0..primitives.len() {
+            if bucket_of(&primitives[i]) <= split {
+                primitives.swap(i, mid);
+                mid += 1;
+            }
+        }
+        Some((axis, mid))
+    }
+
+    /// Splits the primitives in two halves along the longest axis
+    fn median_split(primitives: &mut [BuildPrimitive], bounds: &Aabb) -> Option<(usize, usize)> {
+        if primitives.len() <= MAX_LEAF_SIZE {
+            return None;
+        }
+        let axis = bounds.longest_axis();
+        let mid = primitives.len() / 2;
+        primitives.select_nth_unstable_by(mid, |a, b| {
+            a.centroid.as_slice()[axis]
+                .partial_cmp(&b.centroid.as_slice()[axis])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        Some((axis, mid))
+    }
+
+    /// Sums up the counts and bounding boxes of a range of buckets
+    fn merge_buckets(counts: &[usize], boxes: &[Option<Aabb>]) -> (usize, Option<Aabb>) {
+        let count = counts.iter().sum();
+        let bounds = boxes.iter().flatten().fold(None, |acc: Option<Aabb>, b| {
+            Some(acc.map_or(*b, |acc| acc.union(b)))
+        });
+        (count, bounds)
+    }
+
+    /// Bounding box of all primitives in the tree
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+
+    /// Searches the nearest hit of the ray. `hit_primitive` is called with the index of every
+    /// primitive whose bounding boxes are hit by the ray and the current maximum distance.
+    pub fn get_hits<'a, F>(
+        &self,
+        ray: &Ray,
+        min_distance: f64,
+        max_distance: f64,
+        mut hit_primitive: F,
+    ) -> Option<Hit<'a>>
+    where
+        F: FnMut(usize, f64) -> Option<Hit<'a>>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let origin = ray.origin.as_slice();
+        let direction = ray.direction.as_slice();
+        let inv_direction = [1.0 / direction[0], 1.0 / direction[1], 1.0 / direction[2]];
+
+        let mut nearest: Option<Hit<'a>> = None;
+        let mut max_distance = max_distance;
+        let mut stack = [0usize; 64];
+        let mut stack_size = 1;
+
+        while stack_size > 0 {
+            stack_size -= 1;
+            let node_index = stack[stack_size];
+            let node = &self.nodes[node_index];
+            if node
+                .bounds
+                .hit(&origin, &inv_direction, min_distance, max_distance)
+                .is_none()
+            {
+                continue;
+            }
+            if node.count > 0 {
+                for index in &self.indices[node.offset..node.offset + node.count] {
+                    if let Some(hit) = hit_primitive(*index, max_distance) {
+                        if hit.distance < max_distance {
+                            max_distance = hit.distance;
+                            nearest = Some(hit);
+                        }
+                    }
+                }
+            } else {
+                // Visit the nearer child first. The stack is processed from the back.
+                let (first, second) = (node_index + 1, node.offset);
+                if direction[node.axis] < 0.0 {
+                    stack[stack_size] = first;
+                    stack[stack_size + 1] = second;
+                } else {
+                    stack[stack_size] = second;
+                    stack[stack_size + 1] = first;
+                }
+                stack_size += 2;
+            }
+        }
+        nearest
+    }
+}
+
+/// Bvh over objects. Used by the world, but can be used as an object itself as well.
+pub struct Bvh {
+    tree: BvhTree,
+    objects: Vec<Arc<dyn Hittable + Send + Sync>>,
+}
+
+impl Bvh {
+    /// Builds the hierarchy. All objects need a bounding box.
+    ///
+    /// # Panics
+    /// Panics if an object has no bounding box.
+    pub fn new(objects: Vec<Arc<dyn Hittable + Send + Sync>>) -> Self {
+        let bounds: Vec<Aabb> = objects
+            .iter()
+            .map(|object| {
+                object
+                    .bounding_box()
+                    .expect("Objects in a bvh need a bounding box")
+            })
+            .collect();
+        Self {
+            tree: BvhTree::new(&bounds),
+            objects,
+        }
+    }
+}
+
+impl Hittable for Bvh {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        self.tree
+            .get_hits(ray, min_distance, max_distance, |index, max_distance| {
+                self.objects[index].get_hits(ray, min_distance, max_distance)
+            })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.tree.bounding_box()
+    }
+}
+
+mod test {
+
+    #[test]
+    fn same_hits_as_brute_force() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::objects::Sphere;
+        use crate::scene::{Hittable, Ray};
+        use rand::prelude::*;
+        use std::sync::Arc;
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let material = Arc::new(Lambertian::new(Color::white()));
+        let mut objects: Vec<Arc<dyn Hittable + Send + Sync>> = Vec::new();
+        for _ in 0..200 {
+            objects.push(Arc::new(Sphere {
+                origin: Location::new(
+                    rng.gen_range(-10.0, 10.0),
+                    rng.gen_range(-10.0, 10.0),
+                    rng.gen_range(-10.0, 10.0),
+                ),
+                radius: rng.gen_range(0.1, 1.0),
+                material: material.clone(),
+            }));
+        }
+        let bvh = super::Bvh::new(objects.clone());
+
+        for _ in 0..1000 {
+            let ray = Ray {
+                origin: Location::new(
+                    rng.gen_range(-12.0, 12.0),
+                    rng.gen_range(-12.0, 12.0),
+                    rng.gen_range(-12.0, 12.0),
+                ),
+                direction: Direction::new(
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                    rng.gen_range(-1.0, 1.0),
+                )
+                .norm(),
+                time: 0.0,
+            };
+            let expected = objects
+                .iter()
+                .filter_map(|o| o.get_hits(&ray, 0.001, f64::MAX))
+                .map(|hit| hit.distance)
+                .fold(None, |acc: Option<f64>, d| {
+                    Some(acc.map_or(d, |a| a.min(d)))
+                });
+            let actual = bvh.get_hits(&ray, 0.001, f64::MAX).map(|hit| hit.distance);
+            assert_eq!(expected, actual);
+        }
+    }
+}
@@ -1,97 +1,141 @@
-//! In this module the camera ist implemented. The main goal is to provide an iterator that returns all the rays from the camera
+//! In this module the cameras are implemented. A camera creates the rays for the points of the image.
 
 use crate::geometry::Direction;
 use crate::geometry::Location;
+use crate::sampling::rng;
 use crate::scene::Ray;
-use rand::prelude::*;
+use rand::Rng;
 
-// Definitins only for calculation
-/// Height of the Viewport. Is used for some Calculations internaly.
-/// Can't be changed, and is not needed.
-const VIEWPORT_HEIGHT: f64 = 2.0;
+mod orthographic;
+mod panoramic;
+mod perspective;
+mod stereo;
 
-#[derive(Debug, Copy, Clone)]
-pub struct Camera {
-    /// Location of the camera
-    origin: Location,
-    /// Direction in whicht the camera points
-    direction: Direction,
-    /// Aspect Ration of the final image (Width/Height). For Exampel: 4/3, 16/9, 1/1
-    aspect_ratio: f64,
-    /// Dimensions of the viewport in pixels (Width, Height)
-    viewport_size: (u32, u32),
-    /// Focal Length (Distance of the viewport from the origin)
-    focal_length: f64,
-    /// Position of the upper Left corener of the Viewport
-    viewport_top_left: Location,
-    /// Horizontal Direction of the viewport
-    viewport_horizontal: Direction,
-    /// Vertical Direction of the viewport
-    viewport_vertical: Direction,
-    /// Random number generator for anti alysing
-    rng: rand::rngs::ThreadRng,
+pub use orthographic::OrthographicCamera;
+pub use panoramic::{EquirectangularCamera, FisheyeCamera};
+pub use perspective::PerspectiveCamera;
+pub use stereo::{StereoCamera, StereoLayout};
+
+/// All cameras must implement this trait
+pub trait Camera: std::fmt::Debug {
+    /// Returns the ray through a point of the image.
+    /// `s` goes from 0.0 on the left to 1.0 on the right and `t` from 0.0 at the top to 1.0 at the bottom.
+    /// Points of the image that are not covered by the camera, like the corners of a fisheye image, have no ray.
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray>;
 }
 
-impl Camera {
-    /// Create a new Camera Object. After Createion the rays can be requested by calling the next() method.
-    pub fn new(
-        origin: Location,
-        direction: Direction,
-        width: u32,
-        height: u32,
-        focal_length: f64,
-    ) -> Self {
-        // Calculate data from the inputs
-        let aspect_ratio = width as f64 / height as f64;
-        // Calculate the horizontal direction of the viewport
-        let vert_rotation = direction.y().atan2(direction.x());
-        let horizontal_angle = vert_rotation - std::f64::consts::FRAC_PI_2;
-        let viewport_horizontal =
-            Direction::new(horizontal_angle.cos(), horizontal_angle.sin(), 0.0);
-        // The Vertical Direction of is the cross product of the origin and the horizontal direction
-        let viewport_vertical = viewport_horizontal.cross(direction).norm();
-        // From this values we can calculate the top left corner of the viewport. The Imaginaray viewport is 2 unit heigth and 2*aspect_ratio width
-        let viewport_top_left = Location::origin()
-            + direction.norm() * focal_length
-            + viewport_vertical * 0.5 * VIEWPORT_HEIGHT
-            - viewport_horizontal * 0.5 * VIEWPORT_HEIGHT * aspect_ratio;
+/// Time interval in which the shutter of a camera is open. The rays get a random time in this interval.
+#[derive(Debug, Copy, Clone, Default)]
+struct Shutter {
+    open: f64,
+    close: f64,
+}
 
-        // Create the Struct
-        Camera {
-            origin,
-            direction,
-            aspect_ratio,
-            focal_length,
-            viewport_horizontal,
-            viewport_vertical,
-            viewport_top_left,
-            viewport_size: (width, height),
-            rng: thread_rng(),
+impl Shutter {
+    /// Returns a random point in time while the shutter is open
+    fn sample(&self) -> f64 {
+        if self.close > self.open {
+            rng().gen_range(self.open, self.close)
+        } else {
+            self.open
         }
     }
+}
 
-    pub fn get_ray(&mut self, u: u32, v: u32) -> Result<Ray, String> {
-        // Create random offset in pixel
-        let u_r: f64 = self.rng.gen();
-        let v_r: f64 = self.rng.gen();
-        // Check if the target pixel is within the viewport
-        if u < self.viewport_size.0 && v < self.viewport_size.1 {
-            let viewport_target = self.viewport_top_left
-                + self.viewport_horizontal
-                    * (self.aspect_ratio / self.viewport_size.0 as f64
-                        * (u as f64 + u_r)
-                        * VIEWPORT_HEIGHT)
-                - self.viewport_vertical
-                    * (1.0 / self.viewport_size.1 as f64 * (v as f64 + v_r) * VIEWPORT_HEIGHT);
+/// Orientation of a camera
+#[derive(Debug, Copy, Clone)]
+struct ViewBasis {
+    /// Unit vector pointing to the right side of the image
+    horizontal: Direction,
+    /// Unit vector pointing to the top of the image
+    vertical: Direction,
+    /// Unit vector in viewing direction
+    forward: Direction,
+}
 
-            // Construct the ray
-            let ray = Ray {
-                direction: (viewport_target - Location::origin()).norm(),
-                origin: self.origin,
+impl ViewBasis {
+    /// Orientation of a camera at `from` looking at `to`.
+    /// `up` selects the rotation around the viewing direction and does not need to be perpendicular to it.
+    ///
+    /// If `up` is parallel to the viewing direction, another axis is choosen as up,
+    /// so the camera can look straight up or down.
+    fn look_at(from: Location, to: Location, up: Direction) -> Self {
+        let forward = (to - from).norm();
+        let mut horizontal = forward.cross(up);
+        if horizontal.length() <= 1e-9 * up.length() {
+            // Use the axis that is the least aligned with the viewing direction as up
+            let [x, y, z] = forward.as_slice();
+            let fallback = if x.abs() <= y.abs() && x.abs() <= z.abs() {
+                Direction::new(1.0, 0.0, 0.0)
+            } else if y.abs() <= z.abs() {
+                Direction::new(0.0, 1.0, 0.0)
+            } else {
+                Direction::new(0.0, 0.0, 1.0)
             };
-            Ok(ray)
-        } else {
-            Err("Pixel out of Viewport".into())
+            horizontal = forward.cross(fallback);
+        }
+        let horizontal = horizontal.norm();
+        let vertical = horizontal.cross(forward);
+        Self {
+            horizontal,
+            vertical,
+            forward,
+        }
+    }
+
+    /// Rotates the basis counter clockwise around the viewing direction
+    fn rolled(self, roll_degrees: f64) -> Self {
+        let (sin, cos) = roll_degrees.to_radians().sin_cos();
+        Self {
+            horizontal: self.horizontal * cos + self.vertical * sin,
+            vertical: self.vertical * cos - self.horizontal * sin,
+            forward: self.forward,
+        }
+    }
+}
+
+mod test {
+
+    #[test]
+    fn shutter_times() {
+        use super::{
+            Camera, EquirectangularCamera, FisheyeCamera, OrthographicCamera, PerspectiveCamera,
+            Shutter,
+        };
+        use crate::geometry::{Direction, Location};
+
+        let shutter = Shutter {
+            open: 0.25,
+            close: 0.75,
+        };
+        let times: Vec<f64> = (0..1000).map(|_| shutter.sample()).collect();
+        assert!(times.iter().all(|time| (0.25..=0.75).contains(time)));
+        // The times spread over the whole interval
+        assert!(times.iter().any(|time| *time < 0.3) && times.iter().any(|time| *time > 0.7));
+        // A closed interval is a single point in time
+        let instant = Shutter {
+            open: 2.0,
+            close: 2.0,
+        };
+        assert_eq!(instant.sample(), 2.0);
+
+        // The rays of every camera carry a time in the interval
+        let (from, to, up) = (
+            Location::origin(),
+            Location::new(1.0, 0.0, 0.0),
+            Direction::new(0.0, 0.0, 1.0),
+        );
+        let cameras: Vec<Box<dyn Camera>> = vec![
+            Box::new(PerspectiveCamera::look_at(from, to, up, 90.0, 1.0).with_shutter(1.0, 2.0)),
+            Box::new(OrthographicCamera::look_at(from, to, up, 2.0, 1.0).with_shutter(1.0, 2.0)),
+            Box::new(EquirectangularCamera::look_at(from, to, up).with_shutter(1.0, 2.0)),
+            Box::new(FisheyeCamera::look_at(from, to, up, 180.0, 1.0).with_shutter(1.0, 2.0)),
+        ];
+        for camera in &cameras {
+            for _ in 0..100 {
+                let ray = camera.get_ray(0.5, 0.5).unwrap();
+                assert!((1.0..=2.0).contains(&ray.time), "{:?}", camera);
+            }
         }
     }
 }
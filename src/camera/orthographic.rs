@@ -0,0 +1,93 @@
+//! Camera with a parallel projection.
+
+use super::{Camera, Shutter, ViewBasis};
+use crate::geometry::Direction;
+use crate::geometry::Location;
+use crate::scene::Ray;
+
+/// Camera with a parallel projection. All rays have the same direction and start on the image plane,
+/// so the size of objects does not depend on their distance.
+#[derive(Debug, Copy, Clone)]
+pub struct OrthographicCamera {
+    /// Center of the image plane
+    origin: Location,
+    /// Orientation of the camera
+    basis: ViewBasis,
+    /// Half of the width of the image plane
+    half_width: f64,
+    /// Half of the height of the image plane
+    half_height: f64,
+    /// Time interval of the rays
+    shutter: Shutter,
+}
+
+impl OrthographicCamera {
+    /// Creates a camera with the center of the image at `from` looking at `to`.
+    /// `up` selects the rotation around the viewing direction and does not need to be perpendicular to it.
+    /// `view_height` is the height of the visible area in world units and `aspect` the ratio of width to height of the image.
+    pub fn look_at(
+        from: Location,
+        to: Location,
+        up: Direction,
+        view_height: f64,
+        aspect: f64,
+    ) -> Self {
+        Self {
+            origin: from,
+            basis: ViewBasis::look_at(from, to, up),
+            half_width: view_height * aspect / 2.0,
+            half_height: view_height / 2.0,
+            shutter: Shutter::default(),
+        }
+    }
+
+    /// Rotates the camera counter clockwise around the viewing direction. The image rotates clockwise.
+    pub fn with_roll(mut self, roll_degrees: f64) -> Self {
+        self.basis = self.basis.rolled(roll_degrees);
+        self
+    }
+
+    /// Opens the shutter from `open` to `close`. Moving objects are blurred along their path in this interval.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = Shutter { open, close };
+        self
+    }
+}
+
+impl Camera for OrthographicCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let basis = &self.basis;
+        Some(Ray {
+            origin: self.origin
+                + basis.horizontal * ((2.0 * s - 1.0) * self.half_width)
+                + basis.vertical * ((1.0 - 2.0 * t) * self.half_height),
+            direction: basis.forward,
+            time: self.shutter.sample(),
+        })
+    }
+}
+
+mod test {
+
+    #[test]
+    fn parallel_rays() {
+        use crate::camera::Camera;
+        use crate::geometry::{Direction, Location};
+
+        let cam = super::OrthographicCamera::look_at(
+            Location::new(-5.0, 0.0, 0.0),
+            Location::origin(),
+            Direction::new(0.0, 0.0, 1.0),
+            2.0,
+            2.0,
+        );
+        let center = cam.get_ray(0.5, 0.5).unwrap();
+        assert!((center.origin - Location::new(-5.0, 0.0, 0.0)).length() < 1e-12);
+
+        // Top left corner is one unit up and two units to the left, which is +y
+        let corner = cam.get_ray(0.0, 0.0).unwrap();
+        assert!((corner.origin - Location::new(-5.0, 2.0, 1.0)).length() < 1e-12);
+        assert!((corner.direction - center.direction).length() < 1e-12);
+        assert!((corner.direction - Direction::new(1.0, 0.0, 0.0)).length() < 1e-12);
+    }
+}
@@ -0,0 +1,185 @@
+//! Cameras with a wide field of view that can not be projected on a plane.
+
+use super::{Camera, Shutter, ViewBasis};
+use crate::geometry::Direction;
+use crate::geometry::Location;
+use crate::scene::Ray;
+use std::f64::consts::PI;
+
+/// Camera that maps all directions to the image. The horizontal axis of the image is the longitude
+/// and the vertical axis the latitude, so the image should be twice as wide as high.
+#[derive(Debug, Copy, Clone)]
+pub struct EquirectangularCamera {
+    /// Location of the camera
+    origin: Location,
+    /// Orientation of the camera. The viewing direction is in the center of the image.
+    basis: ViewBasis,
+    /// Time interval of the rays
+    shutter: Shutter,
+}
+
+impl EquirectangularCamera {
+    /// Creates a camera at `from` with `to` in the center of the image.
+    /// `up` is the direction of the top edge of the image.
+    pub fn look_at(from: Location, to: Location, up: Direction) -> Self {
+        Self {
+            origin: from,
+            basis: ViewBasis::look_at(from, to, up),
+            shutter: Shutter::default(),
+        }
+    }
+
+    /// Rotates the camera counter clockwise around the viewing direction. The image rotates clockwise.
+    pub fn with_roll(mut self, roll_degrees: f64) -> Self {
+        self.basis = self.basis.rolled(roll_degrees);
+        self
+    }
+
+    /// Opens the shutter from `open` to `close`. Moving objects are blurred along their path in this interval.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = Shutter { open, close };
+        self
+    }
+}
+
+impl Camera for EquirectangularCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let longitude = (2.0 * s - 1.0) * PI;
+        let latitude = (0.5 - t) * PI;
+        let basis = &self.basis;
+        let direction = basis.forward * (latitude.cos() * longitude.cos())
+            + basis.horizontal * (latitude.cos() * longitude.sin())
+            + basis.vertical * latitude.sin();
+        Some(Ray {
+            origin: self.origin,
+            direction: direction.norm(),
+            time: self.shutter.sample(),
+        })
+    }
+}
+
+/// Fisheye camera with an equidistant projection. The angle to the viewing direction grows linearly with the
+/// distance to the center of the image. The image circle touches the top and the bottom of the image,
+/// points outside of the circle are not covered.
+#[derive(Debug, Copy, Clone)]
+pub struct FisheyeCamera {
+    /// Location of the camera
+    origin: Location,
+    /// Orientation of the camera
+    basis: ViewBasis,
+    /// Half of the field of view in radians
+    half_fov: f64,
+    /// Ratio of width to height of the image
+    aspect: f64,
+    /// Time interval of the rays
+    shutter: Shutter,
+}
+
+impl FisheyeCamera {
+    /// Creates a camera at `from` looking at `to`.
+    /// `up` selects the rotation around the viewing direction and does not need to be perpendicular to it.
+    /// `fov_degrees` is the field of view across the image circle, up to 360 degrees.
+    /// `aspect` is the ratio of width to height of the image.
+    pub fn look_at(
+        from: Location,
+        to: Location,
+        up: Direction,
+        fov_degrees: f64,
+        aspect: f64,
+    ) -> Self {
+        Self {
+            origin: from,
+            basis: ViewBasis::look_at(from, to, up),
+            half_fov: fov_degrees.to_radians() / 2.0,
+            aspect,
+            shutter: Shutter::default(),
+        }
+    }
+
+    /// Rotates the camera counter clockwise around the viewing direction. The image rotates clockwise.
+    pub fn with_roll(mut self, roll_degrees: f64) -> Self {
+        self.basis = self.basis.rolled(roll_degrees);
+        self
+    }
+
+    /// Opens the shutter from `open` to `close`. Moving objects are blurred along their path in this interval.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = Shutter { open, close };
+        self
+    }
+}
+
+impl Camera for FisheyeCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        // Position in the image circle with radius one
+        let x = (2.0 * s - 1.0) * self.aspect;
+        let y = 1.0 - 2.0 * t;
+        let radius = x.hypot(y);
+        if radius > 1.0 {
+            return None;
+        }
+
+        let theta = radius * self.half_fov;
+        let (cos_phi, sin_phi) = if radius > 0.0 {
+            (x / radius, y / radius)
+        } else {
+            (1.0, 0.0)
+        };
+        let basis = &self.basis;
+        let direction = basis.forward * theta.cos()
+            + basis.horizontal * (theta.sin() * cos_phi)
+            + basis.vertical * (theta.sin() * sin_phi);
+        Some(Ray {
+            origin: self.origin,
+            direction: direction.norm(),
+            time: self.shutter.sample(),
+        })
+    }
+}
+
+mod test {
+
+    #[test]
+    fn equirectangular_directions() {
+        use crate::camera::Camera;
+        use crate::geometry::{Direction, Location};
+
+        let cam = super::EquirectangularCamera::look_at(
+            Location::origin(),
+            Location::new(1.0, 0.0, 0.0),
+            Direction::new(0.0, 0.0, 1.0),
+        );
+        let check = |s: f64, t: f64, expected: Direction| {
+            let ray = cam.get_ray(s, t).unwrap();
+            assert!((ray.direction - expected).length() < 1e-12);
+        };
+        check(0.5, 0.5, Direction::new(1.0, 0.0, 0.0));
+        // Right is -y, behind the camera on the edges and up on top
+        check(0.75, 0.5, Direction::new(0.0, -1.0, 0.0));
+        check(0.0, 0.5, Direction::new(-1.0, 0.0, 0.0));
+        check(0.3, 0.0, Direction::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn fisheye_image_circle() {
+        use crate::camera::Camera;
+        use crate::geometry::{Direction, Location};
+
+        let cam = super::FisheyeCamera::look_at(
+            Location::origin(),
+            Location::new(1.0, 0.0, 0.0),
+            Direction::new(0.0, 0.0, 1.0),
+            180.0,
+            2.0,
+        );
+        let ray = cam.get_ray(0.5, 0.5).unwrap();
+        assert!((ray.direction - Direction::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        // The top of the circle is 90° up, the middle between center and edge 45°
+        let ray = cam.get_ray(0.5, 0.0).unwrap();
+        assert!((ray.direction - Direction::new(0.0, 0.0, 1.0)).length() < 1e-12);
+        let ray = cam.get_ray(0.625, 0.5).unwrap();
+        assert!((ray.direction - Direction::new(1.0, -1.0, 0.0).norm()).length() < 1e-12);
+        // The corners are outside of the circle
+        assert!(cam.get_ray(0.0, 0.0).is_none());
+    }
+}
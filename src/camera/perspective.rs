@@ -0,0 +1,225 @@
+//! Pinhole and thin lens camera with a perspective projection.
+
+use super::{Camera, Shutter, ViewBasis};
+use crate::geometry::Direction;
+use crate::geometry::Location;
+use crate::sampling::{random_in_polygon, random_in_unit_disk};
+use crate::scene::Ray;
+
+/// Camera with a perspective projection. Objects further away look smaller.
+#[derive(Debug, Copy, Clone)]
+pub struct PerspectiveCamera {
+    /// Location of the camera
+    origin: Location,
+    /// Orientation of the camera
+    basis: ViewBasis,
+    /// Half of the width of the image plane at a distance of one
+    half_width: f64,
+    /// Half of the height of the image plane at a distance of one
+    half_height: f64,
+    /// Horizontal offset of the center of the image plane at a distance of one
+    shift: f64,
+    /// Radius of the lens. A radius of zero is a pinhole camera where everything is in focus.
+    aperture: f64,
+    /// Distance from the camera to the plane that is in focus
+    focus_distance: f64,
+    /// Number of blades of the aperture. With less than three blades the aperture is round.
+    blades: u32,
+    /// Rotation of the blades in radians
+    blade_rotation: f64,
+    /// Time interval of the rays
+    shutter: Shutter,
+}
+
+impl PerspectiveCamera {
+    /// Create a new camera looking in `direction`, with the z axis up.
+    /// The image plane is 2 units high at `focal_length` in front of the camera.
+    pub fn new(
+        origin: Location,
+        direction: Direction,
+        width: u32,
+        height: u32,
+        focal_length: f64,
+    ) -> Self {
+        let vfov = 2.0 * (1.0 / focal_length).atan();
+        Self::look_at(
+            origin,
+            origin + direction,
+            Direction::new(0.0, 0.0, 1.0),
+            vfov.to_degrees(),
+            width as f64 / height as f64,
+        )
+    }
+
+    /// Creates a camera at `from` looking at `to`.
+    /// `up` selects the rotation around the viewing direction and does not need to be perpendicular to it.
+    /// `vfov_degrees` is the vertical field of view and `aspect` the ratio of width to height of the image.
+    ///
+    /// If `up` is parallel to the viewing direction, another axis is choosen as up,
+    /// so the camera can look straight up or down.
+    pub fn look_at(
+        from: Location,
+        to: Location,
+        up: Direction,
+        vfov_degrees: f64,
+        aspect: f64,
+    ) -> Self {
+        let half_height = (vfov_degrees.to_radians() / 2.0).tan();
+        Self {
+            origin: from,
+            basis: ViewBasis::look_at(from, to, up),
+            half_width: half_height * aspect,
+            half_height,
+            shift: 0.0,
+            aperture: 0.0,
+            focus_distance: 1.0,
+            blades: 0,
+            blade_rotation: 0.0,
+            shutter: Shutter::default(),
+        }
+    }
+
+    /// Rotates the camera counter clockwise around the viewing direction. The image rotates clockwise.
+    pub fn with_roll(mut self, roll_degrees: f64) -> Self {
+        self.basis = self.basis.rolled(roll_degrees);
+        self
+    }
+
+    /// Opens the shutter from `open` to `close`. Moving objects are blurred along their path in this interval.
+    pub fn with_shutter(mut self, open: f64, close: f64) -> Self {
+        self.shutter = Shutter { open, close };
+        self
+    }
+
+    /// Sets the radius of the lens and the distance at which objects are in focus.
+    /// Objects in front or behind the focus distance are blurred.
+    pub fn with_aperture(mut self, aperture: f64, focus_distance: f64) -> Self {
+        self.aperture = aperture;
+        self.focus_distance = focus_distance;
+        self
+    }
+
+    /// Gives the aperture the shape of a regular polygon with `blades` corners, which shows in the blur of small highlights.
+    /// `rotation_degrees` rotates the polygon around the viewing direction.
+    pub fn with_blades(mut self, blades: u32, rotation_degrees: f64) -> Self {
+        self.blades = blades;
+        self.blade_rotation = rotation_degrees.to_radians();
+        self
+    }
+
+    /// Moves the camera by `offset` to the right and shifts the image plane, so that the point in the center of the
+    /// image at distance `convergence` stays in the center. Used for the eyes of a stereo camera.
+    pub(super) fn offset_eye(mut self, offset: f64, convergence: f64) -> Self {
+        self.origin = self.origin + self.basis.horizontal * offset;
+        self.shift -= offset / convergence;
+        self
+    }
+}
+
+impl Camera for PerspectiveCamera {
+    fn get_ray(&self, s: f64, t: f64) -> Option<Ray> {
+        let basis = &self.basis;
+        let direction = basis.forward
+            + basis.horizontal * ((2.0 * s - 1.0) * self.half_width + self.shift)
+            + basis.vertical * ((1.0 - 2.0 * t) * self.half_height);
+        if self.aperture <= 0.0 {
+            return Some(Ray {
+                origin: self.origin,
+                direction: direction.norm(),
+                time: self.shutter.sample(),
+            });
+        }
+
+        // Start the ray at a random point of the lens and aim at the point in focus
+        let (x, y) = if self.blades >= 3 {
+            random_in_polygon(self.blades, self.blade_rotation)
+        } else {
+            random_in_unit_disk()
+        };
+        let lens_offset =
+            basis.horizontal * (x * self.aperture) + basis.vertical * (y * self.aperture);
+        let focus_point = self.origin + direction * self.focus_distance;
+        let origin = self.origin + lens_offset;
+        Some(Ray {
+            origin,
+            direction: (focus_point - origin).norm(),
+            time: self.shutter.sample(),
+        })
+    }
+}
+
+mod test {
+
+    #[test]
+    fn look_at_basis() {
+        use crate::camera::Camera;
+        use crate::geometry::{Direction, Location};
+
+        let up = Direction::new(0.0, 0.0, 1.0);
+        let cam = super::PerspectiveCamera::look_at(
+            Location::origin(),
+            Location::new(2.0, 0.0, 0.0),
+            up,
+            90.0,
+            1.0,
+        );
+        // Center of the image is the viewing direction
+        let ray = cam.get_ray(0.5, 0.5).unwrap();
+        assert!((ray.direction - Direction::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        // Top left corner is 45° up and 45° to the left, which is +y
+        let ray = cam.get_ray(0.0, 0.0).unwrap();
+        assert!((ray.direction - Direction::new(1.0, 1.0, 1.0).norm()).length() < 1e-12);
+
+        // Rolled by 90° the top of the image points to the left
+        let rolled = cam.with_roll(90.0);
+        let ray = rolled.get_ray(0.5, 0.0).unwrap();
+        assert!((ray.direction - Direction::new(1.0, 1.0, 0.0).norm()).length() < 1e-12);
+    }
+
+    #[test]
+    fn focus_plane() {
+        use crate::camera::Camera;
+        use crate::geometry::{Direction, Location};
+
+        let cam = super::PerspectiveCamera::look_at(
+            Location::origin(),
+            Location::new(1.0, 0.0, 0.0),
+            Direction::new(0.0, 0.0, 1.0),
+            60.0,
+            1.0,
+        )
+        .with_aperture(0.5, 4.0)
+        .with_blades(6, 0.0);
+        let pinhole = cam.with_aperture(0.0, 4.0).get_ray(0.2, 0.7).unwrap();
+        let expected = pinhole.at(4.0 / pinhole.direction.x());
+        // All rays through a point of the image meet in the focus plane
+        for _ in 0..100 {
+            let ray = cam.get_ray(0.2, 0.7).unwrap();
+            assert!(ray.origin.x().abs() < 1e-12);
+            assert!(ray.origin.y().hypot(ray.origin.z()) <= 0.5 + 1e-12);
+            let point = ray.at(4.0 / ray.direction.x());
+            assert!((point - expected).length() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn look_straight_down() {
+        use crate::camera::Camera;
+        use crate::geometry::{Direction, Location};
+
+        let cam = super::PerspectiveCamera::look_at(
+            Location::new(0.0, 0.0, 5.0),
+            Location::origin(),
+            Direction::new(0.0, 0.0, 1.0),
+            60.0,
+            1.5,
+        );
+        for (s, t) in &[(0.5, 0.5), (0.0, 0.0), (1.0, 1.0)] {
+            let ray = cam.get_ray(*s, *t).unwrap();
+            assert!(ray.direction.x().is_finite() && ray.direction.y().is_finite());
+            assert!(ray.direction.z() < 0.0);
+        }
+        let ray = cam.get_ray(0.5, 0.5).unwrap();
+        assert!((ray.direction - Direction::new(0.0, 0.0, -1.0)).length() < 1e-12);
+    }
+}
@@ -0,0 +1,131 @@
+//! Stereo camera that renders an image for each eye.
+
+use super::PerspectiveCamera;
+use serde::Deserialize;
+
+/// Two perspective cameras next to each other. The image planes are shifted instead of rotating the cameras
+/// inwards, so both images are free of vertical parallax.
+#[derive(Debug, Copy, Clone)]
+pub struct StereoCamera {
+    left: PerspectiveCamera,
+    right: PerspectiveCamera,
+}
+
+impl StereoCamera {
+    /// Creates a stereo camera with the eyes `interocular` apart around the position of `camera`.
+    /// Objects at a distance of `convergence` appear at the same place in both images,
+    /// which puts them on the screen. Nearer objects appear in front of the screen.
+    pub fn new(camera: PerspectiveCamera, interocular: f64, convergence: f64) -> Self {
+        Self {
+            left: camera.offset_eye(-interocular / 2.0, convergence),
+            right: camera.offset_eye(interocular / 2.0, convergence),
+        }
+    }
+
+    /// Camera of the left eye
+    pub fn left(&self) -> &PerspectiveCamera {
+        &self.left
+    }
+
+    /// Camera of the right eye
+    pub fn right(&self) -> &PerspectiveCamera {
+        &self.right
+    }
+}
+
+/// How the images of both eyes are combined in one image
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StereoLayout {
+    /// Left eye on the left, right eye on the right half of the image
+    #[default]
+    SideBySide,
+    /// Left eye on top, right eye on the bottom half of the image
+    OverUnder,
+    /// Red channel from the left eye, green and blue from the right eye, for red/cyan glasses
+    Anaglyph,
+}
+
+impl StereoLayout {
+    /// Combines the images of the eyes, which have to be of the same size.
+    pub fn combine(self, left: &image::RgbImage, right: &image::RgbImage) -> image::RgbImage {
+        assert_eq!(left.dimensions(), right.dimensions());
+        let (width, height) = left.dimensions();
+        match self {
+            StereoLayout::SideBySide => image::RgbImage::from_fn(width * 2, height, |x, y| {
+                if x < width {
+                    *left.get_pixel(x, y)
+                } else {
+                    *right.get_pixel(x - width, y)
+                }
+            }),
+            StereoLayout::OverUnder => image::RgbImage::from_fn(width, height * 2, |x, y| {
+                if y < height {
+                    *left.get_pixel(x, y)
+                } else {
+                    *right.get_pixel(x, y - height)
+                }
+            }),
+            StereoLayout::Anaglyph => image::RgbImage::from_fn(width, height, |x, y| {
+                let l = left.get_pixel(x, y).0;
+                let r = right.get_pixel(x, y).0;
+                image::Rgb([l[0], r[1], r[2]])
+            }),
+        }
+    }
+}
+
+mod test {
+
+    #[test]
+    fn eyes_converge() {
+        use crate::camera::{Camera, PerspectiveCamera};
+        use crate::geometry::{Direction, Location};
+
+        let camera = PerspectiveCamera::look_at(
+            Location::origin(),
+            Location::new(1.0, 0.0, 0.0),
+            Direction::new(0.0, 0.0, 1.0),
+            60.0,
+            1.5,
+        );
+        let stereo = super::StereoCamera::new(camera, 0.2, 5.0);
+        let left = stereo.left().get_ray(0.5, 0.5).unwrap();
+        let right = stereo.right().get_ray(0.5, 0.5).unwrap();
+        // The left eye is at +y
+        assert!((left.origin - Location::new(0.0, 0.1, 0.0)).length() < 1e-12);
+        assert!((right.origin - Location::new(0.0, -0.1, 0.0)).length() < 1e-12);
+        // The centers of both images meet at the convergence distance
+        let convergence = Location::new(5.0, 0.0, 0.0);
+        assert!((left.at(5.0 / left.direction.x()) - convergence).length() < 1e-12);
+        assert!((right.at(5.0 / right.direction.x()) - convergence).length() < 1e-12);
+        // No vertical parallax
+        let left = stereo.left().get_ray(0.1, 0.2).unwrap();
+        let right = stereo.right().get_ray(0.1, 0.2).unwrap();
+        assert!(
+            (left.direction.z() / left.direction.x() - right.direction.z() / right.direction.x())
+                .abs()
+                < 1e-12
+        );
+    }
+
+    #[test]
+    fn combine_layouts() {
+        use super::StereoLayout;
+
+        let left = image::RgbImage::from_pixel(2, 1, image::Rgb([200, 10, 20]));
+        let right = image::RgbImage::from_pixel(2, 1, image::Rgb([30, 40, 50]));
+
+        let image = StereoLayout::SideBySide.combine(&left, &right);
+        assert_eq!(image.dimensions(), (4, 1));
+        assert_eq!(image.get_pixel(1, 0), left.get_pixel(0, 0));
+        assert_eq!(image.get_pixel(2, 0), right.get_pixel(0, 0));
+
+        let image = StereoLayout::OverUnder.combine(&left, &right);
+        assert_eq!(image.dimensions(), (2, 2));
+        assert_eq!(image.get_pixel(0, 1), right.get_pixel(0, 0));
+
+        let image = StereoLayout::Anaglyph.combine(&left, &right);
+        assert_eq!(image.get_pixel(0, 0).0, [200, 40, 50]);
+    }
+}
@@ -26,6 +26,11 @@ impl Color {
         self.data[2] = b;
     }
 
+    /// Returns true if all components are zero
+    pub fn is_black(&self) -> bool {
+        self.data.iter().all(|c| *c == 0.0)
+    }
+
     pub fn new(r: f64, g: f64, b: f64) -> Self {
         Self { data: [r, g, b] }
     }
@@ -103,6 +108,21 @@ impl std::ops::Mul<f64> for Color {
     }
 }
 
+impl std::ops::Mul for Color {
+    type Output = Color;
+
+    /// Component wise multiplication, used to attenuate a color by a surface color.
+    fn mul(self, other: Self) -> Self {
+        Self {
+            data: [
+                self.data[0] * other.data[0],
+                self.data[1] * other.data[1],
+                self.data[2] * other.data[2],
+            ],
+        }
+    }
+}
+
 impl std::ops::Div<f64> for Color {
     type Output = Color;
 
@@ -151,6 +171,17 @@ mod test {
         assert_eq!(c3.b(), 6.0);
     }
 
+    #[test]
+    fn mul_color() {
+        let c1 = super::Color::new(0.0, 1.0, 2.0);
+        let c2 = super::Color::new(3.0, 4.0, 5.0);
+
+        let c3 = c1 * c2;
+        assert_eq!(c3.r(), 0.0);
+        assert_eq!(c3.g(), 4.0);
+        assert_eq!(c3.b(), 10.0);
+    }
+
     #[test]
     fn div_f64() {
         let c1 = super::Color::new(0.0, 1.0, 2.0);
@@ -33,7 +33,7 @@ impl Direction {
     /// Cross Product of two directions. Returns also a direction.
     pub fn cross(self, other: Self) -> Self {
         let x = self.y() * other.z() - self.z() * other.y();
-        let y = self.x() * other.z() - self.z() * other.x();
+        let y = self.z() * other.x() - self.x() * other.z();
         let z = self.x() * other.y() - self.y() * other.x();
 
         Self::new(x, y, z)
@@ -58,6 +58,21 @@ impl Direction {
     pub fn invert(self) -> Self {
         self * (-1.0)
     }
+
+    /// Reflects the direction on a surface with the given normal. The normal has to be normalized.
+    pub fn reflect(self, normal: Self) -> Self {
+        self - normal * 2.0 * self.dot(normal)
+    }
+
+    /// Refracts the normalized direction on a surface with the given normal according to snell's law.
+    /// The normal has to point against the direction. `ratio` is the quotient of the refraction
+    /// index on the incoming side and the refraction index on the outgoing side.
+    pub fn refract(self, normal: Self, ratio: f64) -> Self {
+        let cos_theta = self.invert().dot(normal).min(1.0);
+        let out_perpendicular = (self + normal * cos_theta) * ratio;
+        let out_parallel = normal * -(1.0 - out_perpendicular.length().powi(2)).abs().sqrt();
+        out_perpendicular + out_parallel
+    }
 }
 
 impl std::ops::Add for Direction {
@@ -147,6 +162,11 @@ impl Location {
     pub fn origin() -> Self {
         Self::new(0.0, 0.0, 0.0)
     }
+
+    /// Returns the location coordinates as a slice.
+    pub fn as_slice(&self) -> [f64; 3] {
+        self.data
+    }
 }
 
 impl std::ops::Sub for Location {
@@ -188,6 +208,483 @@ impl std::ops::Sub<Direction> for Location {
         }
     }
 }
+/// Axis aligned bounding box. Used to quickly reject rays that can not hit an object.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    /// Corner with the smallest coordinates
+    pub min: Location,
+    /// Corner with the largest coordinates
+    pub max: Location,
+}
+
+impl Aabb {
+    /// Creates a new box spanned by two corners. The corners can be given in any order.
+    pub fn new(a: Location, b: Location) -> Self {
+        Self {
+            min: Location::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Location::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+    }
+
+    /// Creates the smallest box containing both boxes
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            min: Location::new(
+                self.min.x().min(other.min.x()),
+                self.min.y().min(other.min.y()),
+                self.min.z().min(other.min.z()),
+            ),
+            max: Location::new(
+                self.max.x().max(other.max.x()),
+                self.max.y().max(other.max.y()),
+                self.max.z().max(other.max.z()),
+            ),
+        }
+    }
+
+    /// Creates the box covered by both boxes. None if they do not overlap.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let min = Location::new(
+            self.min.x().max(other.min.x()),
+            self.min.y().max(other.min.y()),
+            self.min.z().max(other.min.z()),
+        );
+        let max = Location::new(
+            self.max.x().min(other.max.x()),
+            self.max.y().min(other.max.y()),
+            self.max.z().min(other.max.z()),
+        );
+        if min.x() <= max.x() && min.y() <= max.y() && min.z() <= max.z() {
+            Some(Self { min, max })
+        } else {
+            None
+        }
+    }
+
+    /// Grows the box to contain the given location
+    pub fn include(&self, location: Location) -> Self {
+        self.union(&Self {
+            min: location,
+            max: location,
+        })
+    }
+
+    /// Center of the box
+    pub fn centroid(&self) -> Location {
+        self.min + (self.max - self.min) * 0.5
+    }
+
+    /// Surface area of the box. Used as cost estimation for building a bvh.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// Index of the axis with the largest extent (0: x, 1: y, 2: z)
+    pub fn longest_axis(&self) -> usize {
+        let d = (self.max - self.min).as_slice();
+        if d[0] >= d[1] && d[0] >= d[2] {
+            0
+        } else if d[1] >= d[2] {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Checks if a ray hits the box between `min_distance` and `max_distance`.
+    /// `origin` and `inv_direction` are the origin of the ray and the component wise inverse of its direction.
+    /// Returns the distance at which the ray enters the box.
+    pub fn hit(
+        &self,
+        origin: &[f64; 3],
+        inv_direction: &[f64; 3],
+        min_distance: f64,
+        max_distance: f64,
+    ) -> Option<f64> {
+        self.clip(origin, inv_direction, min_distance, max_distance)
+            .map(|(enter, _)| enter)
+    }
+
+    /// Like `hit`, but returns the distances at which the ray enters and leaves the box
+    pub fn clip(
+        &self,
+        origin: &[f64; 3],
+        inv_direction: &[f64; 3],
+        min_distance: f64,
+        max_distance: f64,
+    ) -> Option<(f64, f64)> {
+        let mut t_min = min_distance;
+        let mut t_max = max_distance;
+        for axis in 0..3 {
+            let t0 = (self.min.data[axis] - origin[axis]) * inv_direction[axis];
+            let t1 = (self.max.data[axis] - origin[axis]) * inv_direction[axis];
+            let (t0, t1) = if t0 <= t1 { (t0, t1) } else { (t1, t0) };
+            // max and min ignore NaN values which occur if the origin is on a slab of an axis parallel ray
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return None;
+            }
+        }
+        Some((t_min, t_max))
+    }
+}
+
+/// Quaternion of unit length that describes a rotation
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quaternion {
+    w: f64,
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+impl Quaternion {
+    /// Creates a quaternion from its components and normalizes it
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Self {
+        Self { w, x, y, z }.norm()
+    }
+
+    /// Rotation by zero degrees
+    pub fn identity() -> Self {
+        Self {
+            w: 1.0,
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+
+    /// Counter clockwise rotation around the axis, seen from the tip of the axis
+    pub fn from_axis_angle(axis: Direction, angle_degrees: f64) -> Self {
+        let axis = axis.norm();
+        let (sin, cos) = (angle_degrees.to_radians() / 2.0).sin_cos();
+        Self::new(cos, axis.x() * sin, axis.y() * sin, axis.z() * sin)
+    }
+
+    /// Rotation around the x axis, then the y axis and then the z axis
+    pub fn from_euler(x_degrees: f64, y_degrees: f64, z_degrees: f64) -> Self {
+        Self::from_axis_angle(Direction::new(0.0, 0.0, 1.0), z_degrees)
+            * Self::from_axis_angle(Direction::new(0.0, 1.0, 0.0), y_degrees)
+            * Self::from_axis_angle(Direction::new(1.0, 0.0, 0.0), x_degrees)
+    }
+
+    /// Scales the quaternion to length one
+    pub fn norm(self) -> Self {
+        let length = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        Self {
+            w: self.w / length,
+            x: self.x / length,
+            y: self.y / length,
+            z: self.z / length,
+        }
+    }
+
+    /// Rotation in the opposite direction
+    pub fn conjugate(self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    /// Rotates the direction
+    pub fn rotate(self, direction: Direction) -> Direction {
+        let q = Direction::new(self.x, self.y, self.z);
+        let t = q.cross(direction) * 2.0;
+        direction + t * self.w + q.cross(t)
+    }
+
+    /// Spherical linear interpolation between two rotations. `t` goes from 0.0 (self) to 1.0 (other).
+    pub fn slerp(self, other: Self, t: f64) -> Self {
+        let mut dot = self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z;
+        // Take the shorter way
+        let other = if dot < 0.0 {
+            dot = -dot;
+            Self {
+                w: -other.w,
+                x: -other.x,
+                y: -other.y,
+                z: -other.z,
+            }
+        } else {
+            other
+        };
+        let (a, b) = if dot > 0.9995 {
+            // Nearly the same rotation, linear interpolation is accurate enough
+            (1.0 - t, t)
+        } else {
+            let theta = dot.acos();
+            let sin = theta.sin();
+            (((1.0 - t) * theta).sin() / sin, (t * theta).sin() / sin)
+        };
+        Self::new(
+            self.w * a + other.w * b,
+            self.x * a + other.x * b,
+            self.y * a + other.y * b,
+            self.z * a + other.z * b,
+        )
+    }
+}
+
+impl std::ops::Mul for Quaternion {
+    type Output = Quaternion;
+
+    /// Combines two rotations. The rotation on the right side is applied first.
+    fn mul(self, other: Self) -> Self {
+        Self {
+            w: self.w * other.w - self.x * other.x - self.y * other.y - self.z * other.z,
+            x: self.w * other.x + self.x * other.w + self.y * other.z - self.z * other.y,
+            y: self.w * other.y - self.x * other.z + self.y * other.w + self.z * other.x,
+            z: self.w * other.z + self.x * other.y - self.y * other.x + self.z * other.w,
+        }
+    }
+}
+
+/// Rows of a 4x4 matrix
+type Matrix = [[f64; 4]; 4];
+
+/// Affine transformation of the space. Holds the matrix and its inverse, so points can be transformed
+/// in both directions without inverting the matrix again.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    matrix: Matrix,
+    inverse: Matrix,
+}
+
+impl Transform {
+    /// Transformation that changes nothing
+    pub fn identity() -> Self {
+        let matrix = [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        Self {
+            matrix,
+            inverse: matrix,
+        }
+    }
+
+    /// Moves everything by the offset
+    pub fn translation(offset: Direction) -> Self {
+        let [x, y, z] = offset.as_slice();
+        Self {
+            matrix: [
+                [1.0, 0.0, 0.0, x],
+                [0.0, 1.0, 0.0, y],
+                [0.0, 0.0, 1.0, z],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            inverse: [
+                [1.0, 0.0, 0.0, -x],
+                [0.0, 1.0, 0.0, -y],
+                [0.0, 0.0, 1.0, -z],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Scales along the axes. A factor of zero makes the transformation singular and is not allowed.
+    pub fn scaling(x: f64, y: f64, z: f64) -> Self {
+        Self {
+            matrix: [
+                [x, 0.0, 0.0, 0.0],
+                [0.0, y, 0.0, 0.0],
+                [0.0, 0.0, z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            inverse: [
+                [1.0 / x, 0.0, 0.0, 0.0],
+                [0.0, 1.0 / y, 0.0, 0.0],
+                [0.0, 0.0, 1.0 / z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Rotates around the origin
+    pub fn rotation(rotation: Quaternion) -> Self {
+        let x = rotation.rotate(Direction::new(1.0, 0.0, 0.0)).as_slice();
+        let y = rotation.rotate(Direction::new(0.0, 1.0, 0.0)).as_slice();
+        let z = rotation.rotate(Direction::new(0.0, 0.0, 1.0)).as_slice();
+        let matrix = [
+            [x[0], y[0], z[0], 0.0],
+            [x[1], y[1], z[1], 0.0],
+            [x[2], y[2], z[2], 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        // The inverse of a rotation matrix is its transpose
+        let mut inverse = matrix;
+        for (row, inverse_row) in inverse.iter_mut().enumerate().take(3) {
+            for (col, value) in inverse_row.iter_mut().enumerate().take(3) {
+                *value = matrix[col][row];
+            }
+        }
+        Self { matrix, inverse }
+    }
+
+    /// Scales, then rotates and then translates
+    pub fn from_parts(translation: Direction, rotation: Quaternion, scale: Direction) -> Self {
+        Self::translation(translation)
+            * Self::rotation(rotation)
+            * Self::scaling(scale.x(), scale.y(), scale.z())
+    }
+
+    /// Transformation in the opposite direction
+    pub fn inverse(&self) -> Self {
+        Self {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// Transforms a location
+    pub fn location(&self, location: Location) -> Location {
+        let [x, y, z] = apply(&self.matrix, location.data, 1.0);
+        Location::new(x, y, z)
+    }
+
+    /// Transforms a direction. The direction is not normalized afterwards.
+    pub fn direction(&self, direction: Direction) -> Direction {
+        let [x, y, z] = apply(&self.matrix, direction.data, 0.0);
+        Direction::new(x, y, z)
+    }
+
+    /// Transforms the normal of a surface, so that it stays perpendicular to the surface.
+    /// The normal is not normalized afterwards.
+    pub fn normal(&self, normal: Direction) -> Direction {
+        // Multiply with the transposed inverse
+        let m = &self.inverse;
+        let [x, y, z] = normal.data;
+        Direction::new(
+            m[0][0] * x + m[1][0] * y + m[2][0] * z,
+            m[0][1] * x + m[1][1] * y + m[2][1] * z,
+            m[0][2] * x + m[1][2] * y + m[2][2] * z,
+        )
+    }
+
+    /// Transforms a location with the inverse transformation
+    pub fn inverse_location(&self, location: Location) -> Location {
+        let [x, y, z] = apply(&self.inverse, location.data, 1.0);
+        Location::new(x, y, z)
+    }
+
+    /// Transforms a direction with the inverse transformation
+    pub fn inverse_direction(&self, direction: Direction) -> Direction {
+        let [x, y, z] = apply(&self.inverse, direction.data, 0.0);
+        Direction::new(x, y, z)
+    }
+
+    /// Factor by which the transformation scales volumes. Negative if the transformation mirrors.
+    pub fn determinant(&self) -> f64 {
+        let m = &self.matrix;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Bounding box of the transformed box
+    pub fn aabb(&self, aabb: &Aabb) -> Aabb {
+        let corners = [aabb.min.data, aabb.max.data];
+        let first = self.location(aabb.min);
+        (0..8).fold(Aabb::new(first, first), |bounds, i| {
+            let corner = Location::new(
+                corners[i & 1][0],
+                corners[(i >> 1) & 1][1],
+                corners[(i >> 2) & 1][2],
+            );
+            bounds.include(self.location(corner))
+        })
+    }
+}
+
+impl std::ops::Mul for Transform {
+    type Output = Transform;
+
+    /// Combines two transformations. The transformation on the right side is applied first.
+    fn mul(self, other: Self) -> Self {
+        Self {
+            matrix: multiply(&self.matrix, &other.matrix),
+            inverse: multiply(&other.inverse, &self.inverse),
+        }
+    }
+}
+
+/// Translation, rotation and scale of an object, applied like `Transform::from_parts`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Pose {
+    pub translation: Direction,
+    pub rotation: Quaternion,
+    pub scale: Direction,
+}
+
+impl Pose {
+    pub fn new(translation: Direction, rotation: Quaternion, scale: Direction) -> Self {
+        Self {
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform::from_parts(self.translation, self.rotation, self.scale)
+    }
+}
+
+/// Transformation that changes from the `start` pose at `start_time` to the `end` pose at `end_time`.
+/// Translation and scale change linearly, the rotation with constant speed. Before and after this interval the
+/// poses rest.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Motion {
+    pub start: Pose,
+    pub end: Pose,
+    pub start_time: f64,
+    pub end_time: f64,
+}
+
+impl Motion {
+    /// Transformation at the given time
+    pub fn at(&self, time: f64) -> Transform {
+        let duration = self.end_time - self.start_time;
+        let t = if duration > 0.0 {
+            ((time - self.start_time) / duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let lerp = |a: Direction, b: Direction| a * (1.0 - t) + b * t;
+        Transform::from_parts(
+            lerp(self.start.translation, self.end.translation),
+            self.start.rotation.slerp(self.end.rotation, t),
+            lerp(self.start.scale, self.end.scale),
+        )
+    }
+}
+
+/// Multiplies the matrix with the vector (x, y, z, w) and returns the first three components
+fn apply(m: &Matrix, v: [f64; 3], w: f64) -> [f64; 3] {
+    let mut result = [0.0; 3];
+    for (row, value) in result.iter_mut().enumerate() {
+        *value = m[row][0] * v[0] + m[row][1] * v[1] + m[row][2] * v[2] + m[row][3] * w;
+    }
+    result
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut result = [[0.0; 4]; 4];
+    for (row, result_row) in result.iter_mut().enumerate() {
+        for (col, value) in result_row.iter_mut().enumerate() {
+            *value = (0..4).map(|i| a[row][i] * b[i][col]).sum();
+        }
+    }
+    result
+}
+
 mod test {
 
     #[test]
@@ -216,6 +713,16 @@ mod test {
         assert_eq!(c3.x(), 0.0);
         assert_eq!(c3.y(), 0.0);
         assert_eq!(c3.z(), 1.0);
+
+        let c4 = c2.cross(c3);
+        assert_eq!(c4.x(), 1.0);
+        assert_eq!(c4.y(), 0.0);
+        assert_eq!(c4.z(), 0.0);
+
+        let c5 = c3.cross(c1);
+        assert_eq!(c5.x(), 0.0);
+        assert_eq!(c5.y(), 1.0);
+        assert_eq!(c5.z(), 0.0);
     }
 
     #[test]
@@ -259,4 +766,110 @@ mod test {
         assert_eq!(c3.y(), 1.0 / 3.0);
         assert_eq!(c3.z(), 2.0 / 3.0);
     }
+
+    #[test]
+    fn aabb_hit() {
+        let aabb = super::Aabb::new(
+            super::Location::new(1.0, 1.0, 1.0),
+            super::Location::new(-1.0, -1.0, -1.0),
+        );
+        assert_eq!(aabb.surface_area(), 24.0);
+
+        // Ray along the x axis
+        let inv = [1.0, f64::INFINITY, f64::INFINITY];
+        assert_eq!(aabb.hit(&[-5.0, 0.0, 0.0], &inv, 0.0, f64::MAX), Some(4.0));
+        assert_eq!(aabb.hit(&[-5.0, 0.0, 0.0], &inv, 0.0, 3.0), None);
+        assert_eq!(aabb.hit(&[-5.0, 2.0, 0.0], &inv, 0.0, f64::MAX), None);
+        // Starting inside the box
+        assert_eq!(aabb.hit(&[0.0, 0.0, 0.0], &inv, 0.0, f64::MAX), Some(0.0));
+    }
+
+    #[test]
+    fn reflect() {
+        let c1 = super::Direction::new(1.0, 0.0, -1.0);
+        let n = super::Direction::new(0.0, 0.0, 1.0);
+
+        let c3 = c1.reflect(n);
+        assert_eq!(c3.x(), 1.0);
+        assert_eq!(c3.y(), 0.0);
+        assert_eq!(c3.z(), 1.0);
+    }
+
+    #[test]
+    fn refract() {
+        // Without a change in the refraction index the direction stays the same
+        let c1 = super::Direction::new(1.0, 0.0, -1.0).norm();
+        let n = super::Direction::new(0.0, 0.0, 1.0);
+
+        let c3 = c1.refract(n, 1.0);
+        assert!((c3 - c1).length() < 1e-12);
+
+        // Entering a denser medium bends the ray towards the normal
+        let c3 = c1.refract(n, 1.0 / 1.5);
+        assert!((c3.length() - 1.0).abs() < 1e-12);
+        assert!(c3.x() < c1.x());
+        assert!(c3.z() < c1.z());
+    }
+
+    #[test]
+    fn quaternion_rotation() {
+        let q = super::Quaternion::from_axis_angle(super::Direction::new(0.0, 0.0, 1.0), 90.0);
+        let d = q.rotate(super::Direction::new(1.0, 0.0, 0.0));
+        assert!((d - super::Direction::new(0.0, 1.0, 0.0)).length() < 1e-12);
+        // Combined rotations apply the right one first
+        let q2 = super::Quaternion::from_axis_angle(super::Direction::new(1.0, 0.0, 0.0), 90.0);
+        let d = (q2 * q).rotate(super::Direction::new(1.0, 0.0, 0.0));
+        assert!((d - super::Direction::new(0.0, 0.0, 1.0)).length() < 1e-12);
+        // Halfway between no rotation and 90 degrees
+        let half = super::Quaternion::identity().slerp(q, 0.5);
+        let d = half.rotate(super::Direction::new(1.0, 0.0, 0.0));
+        assert!((d - super::Direction::new(1.0, 1.0, 0.0).norm()).length() < 1e-12);
+    }
+
+    #[test]
+    fn transform_inverse() {
+        let t = super::Transform::from_parts(
+            super::Direction::new(1.0, 2.0, 3.0),
+            super::Quaternion::from_euler(30.0, 45.0, 60.0),
+            super::Direction::new(2.0, 0.5, 3.0),
+        );
+        let p = super::Location::new(-1.0, 4.0, 0.5);
+        let back = t.inverse_location(t.location(p));
+        assert!((back - p).length() < 1e-12);
+        assert!((t.determinant() - 3.0).abs() < 1e-12);
+
+        // Normals stay perpendicular to transformed tangents
+        let tangent = super::Direction::new(1.0, 1.0, 0.0);
+        let normal = super::Direction::new(1.0, -1.0, 0.0);
+        assert!(t.direction(tangent).dot(t.normal(normal)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn motion() {
+        let z = super::Direction::new(0.0, 0.0, 1.0);
+        let motion = super::Motion {
+            start: super::Pose::new(
+                super::Direction::new(0.0, 0.0, 0.0),
+                super::Quaternion::identity(),
+                super::Direction::new(1.0, 1.0, 1.0),
+            ),
+            end: super::Pose::new(
+                super::Direction::new(4.0, 0.0, 0.0),
+                super::Quaternion::from_axis_angle(z, 90.0),
+                super::Direction::new(3.0, 3.0, 3.0),
+            ),
+            start_time: 1.0,
+            end_time: 2.0,
+        };
+        let p = super::Location::new(1.0, 0.0, 0.0);
+        // Halfway the rotation is 45 degrees, the scale 2 and the offset 2
+        let half = motion.at(1.5).location(p);
+        let expected = super::Location::new(2.0 + 2f64.sqrt(), 2f64.sqrt(), 0.0);
+        assert!((half - expected).length() < 1e-12);
+        // Rests before the start and after the end
+        assert_eq!(motion.at(0.0), motion.start.transform());
+        assert!(
+            (motion.at(3.0).location(p) - super::Location::new(4.0, 3.0, 0.0)).length() < 1e-12
+        );
+    }
 }
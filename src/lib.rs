@@ -0,0 +1,15 @@
+//! A small raytracer following the raytracer in one weekend series.
+//! The library holds everything needed to describe and render a scene, the binary drives the rendering.
+
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod geometry;
+pub mod material;
+pub mod obj;
+pub mod sampling;
+pub mod scene;
+pub mod scene_file;
+pub mod texture;
+pub mod threadpool;
+pub mod voxel;
@@ -1,123 +1,263 @@
-mod camera;
-mod color;
-mod geometry;
-mod scene;
-mod threadpool;
-use color::Color;
-use geometry::{Direction, Location};
-use scene::World;
+use clap::{Parser, ValueEnum};
+use rand::Rng;
+use raytracer::camera::{Camera, StereoLayout};
+use raytracer::color::Color;
+use raytracer::sampling;
+use raytracer::scene::{RenderMode, World};
+use raytracer::scene_file;
+use raytracer::threadpool;
+use std::sync::Arc;
 
-const ANTI_ALIASING: u32 = 1000;
+/// Renders a scene described in a toml file
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Options {
+    /// Scene file to render
+    #[arg(default_value = "scenes/sample.toml")]
+    scene: String,
+    /// Path of the rendered image. The format is choosen by the extension.
+    #[arg(short, long, default_value = "test.png")]
+    output: String,
+    /// Size of the image in pixel, for example 800x600. Overrides the scene file.
+    #[arg(short, long, value_parser = parse_resolution)]
+    resolution: Option<(u32, u32)>,
+    /// Number of rays per pixel. Overrides the scene file.
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    samples: Option<u32>,
+    /// Maximum number of bounces of a ray. Overrides the scene file.
+    #[arg(short = 'd', long)]
+    max_depth: Option<u32>,
+    /// Number of render threads. Defaults to the number of cores.
+    #[arg(short = 'j', long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: Option<usize>,
+    /// Seed for the random numbers. The same seed renders the same image.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// What is shown in the image
+    #[arg(short, long, value_enum, default_value_t = Mode::Shaded)]
+    mode: Mode,
+    /// How the eyes of a stereo camera are combined. Overrides the scene file.
+    #[arg(long, value_enum)]
+    stereo_layout: Option<Layout>,
+}
+
+/// Render modes selectable on the command line
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum Mode {
+    /// Path traced image
+    Shaded,
+    /// Direction of the normals
+    Normal,
+}
+
+impl From<Mode> for RenderMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::Shaded => RenderMode::Shaded,
+            Mode::Normal => RenderMode::Normal,
+        }
+    }
+}
+
+/// Stereo layouts selectable on the command line
+#[derive(Debug, Copy, Clone, ValueEnum)]
+enum Layout {
+    /// Left eye on the left, right eye on the right half
+    SideBySide,
+    /// Left eye on top, right eye on the bottom half
+    OverUnder,
+    /// Red/cyan anaglyph
+    Anaglyph,
+}
+
+impl From<Layout> for StereoLayout {
+    fn from(layout: Layout) -> Self {
+        match layout {
+            Layout::SideBySide => StereoLayout::SideBySide,
+            Layout::OverUnder => StereoLayout::OverUnder,
+            Layout::Anaglyph => StereoLayout::Anaglyph,
+        }
+    }
+}
+
+/// Parses a resolution in the form WIDTHxHEIGHT
+fn parse_resolution(value: &str) -> Result<(u32, u32), String> {
+    let error = || format!("Expected WIDTHxHEIGHT, found {}", value);
+    let (width, height) = value.split_once('x').ok_or_else(error)?;
+    let width: u32 = width.parse().map_err(|_| error())?;
+    let height: u32 = height.parse().map_err(|_| error())?;
+    if width == 0 || height == 0 {
+        return Err("Width and height must be greater than zero".into());
+    }
+    Ok((width, height))
+}
 
 fn main() {
+    let options = Options::parse();
     let start_time = std::time::Instant::now();
-    // Create a test image
-    let mut my_image: image::RgbImage = image::RgbImage::new(800, 600);
 
-    {
-        println!("Creating image...");
-
-        // Create a sample world
-        let world = World::sample_world();
-
-        // Define the camera
-        let mut cam = camera::Camera::new(
-            Location::origin(),
-            Direction::new(1.0, 0.0, 0.0),
-            my_image.width(),
-            my_image.height(),
-            1.0,
-        );
+    // Load the scene and apply the options of the command line
+    let mut description = match scene_file::load_scene(&options.scene) {
+        Ok(description) => description,
+        Err(error) => {
+            eprintln!("Unable to load scene: {}", error);
+            std::process::exit(1);
+        }
+    };
+    if let Some((width, height)) = options.resolution {
+        description.settings.width = width;
+        description.settings.height = height;
+    }
+    if let Some(samples) = options.samples {
+        description.settings.samples = samples;
+    }
+    if let Some(max_depth) = options.max_depth {
+        description.settings.max_depth = max_depth;
+    }
+    if let Some(layout) = options.stereo_layout {
+        description.settings.stereo_layout = layout.into();
+    }
+    let scene = match description.build() {
+        Ok(scene) => scene,
+        Err(error) => {
+            eprintln!("Unable to load scene: {}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut world = scene.world;
+    world.set_render_mode(options.mode.into());
+    let world = Arc::new(world);
 
-        // Wrap the world struct in a Arc to be able to send it to the threadpool
-        let world = std::sync::Arc::new(world);
-        // Create a sender/reciver pair for returning colors to the main function
-        let (tx_color, rx_color) = std::sync::mpsc::channel::<(usize, usize, Color)>();
-        // Create a field to store the colors
-        let mut colors: Vec<Vec<Color>> = Vec::with_capacity(my_image.width() as usize);
-        for _ in 0..my_image.width() {
-            let mut color_col = Vec::with_capacity(my_image.height() as usize);
-            for _ in 0..my_image.height() {
-                color_col.push(Color::black());
-            }
-            colors.push(color_col);
+    let threads = options.threads.unwrap_or_else(num_cpus::get);
+    let settings = scene.settings;
+    let render_camera = |camera| {
+        render(
+            world.clone(),
+            camera,
+            settings.width,
+            settings.height,
+            settings.samples,
+            threads,
+            options.seed,
+        )
+    };
+    let my_image = match scene.stereo {
+        Some(stereo) => {
+            println!("Creating image of the left eye...");
+            let left = render_camera(Arc::new(*stereo.left()));
+            println!("Creating image of the right eye...");
+            let right = render_camera(Arc::new(*stereo.right()));
+            settings.stereo_layout.combine(&left, &right)
         }
-        {
-            // Check number of cores
-            let cores = num_cpus::get();
-            println!(
-                "Found {} cores. Spawning this number of render threads.",
-                cores
-            );
-
-            // Create a progress bar
-            let mut progress =
-                progress_bar::progress_bar::ProgressBar::new(my_image.height() as usize);
-            progress.set_action(
-                "Raytracing",
-                progress_bar::color::Color::Blue,
-                progress_bar::color::Style::Bold,
-            );
-            // Create a threadpool for rendering the image
-            let mut pool = threadpool::ThreadPool::new(cores);
-            // Number of active Jobs
-            let mut active_jobs = 0;
-            // Iterate over the image
-            for (u, v, _) in my_image.enumerate_pixels() {
-                // Collect Rays
-                let mut rays = Vec::new();
-                for _ in 0..ANTI_ALIASING {
-                    // Get a ray to the pixel from the cam
-                    rays.push(cam.get_ray(u, v).unwrap());
+        None => {
+            println!("Creating image...");
+            render_camera(scene.camera)
+        }
+    };
+
+    println!("Saving image ...");
+    if let Err(error) = my_image.save(&options.output) {
+        eprintln!("Unable to save {}: {}", options.output, error);
+        std::process::exit(1);
+    }
+    let end_time = std::time::Instant::now();
+    println!("Time to calculate: {}s", (end_time - start_time).as_secs());
+}
+
+/// Renders the world with the given camera in a threadpool
+fn render(
+    world: Arc<World>,
+    cam: Arc<dyn Camera + Send + Sync>,
+    width: u32,
+    height: u32,
+    samples: u32,
+    threads: usize,
+    seed: Option<u64>,
+) -> image::RgbImage {
+    // Create the image
+    let mut my_image: image::RgbImage = image::RgbImage::new(width, height);
+
+    // Create a sender/reciver pair for returning colors to the main function
+    let (tx_color, rx_color) = std::sync::mpsc::channel::<(usize, usize, Color)>();
+    // Create a field to store the colors
+    let mut colors: Vec<Vec<Color>> = Vec::with_capacity(my_image.width() as usize);
+    for _ in 0..my_image.width() {
+        let mut color_col = Vec::with_capacity(my_image.height() as usize);
+        for _ in 0..my_image.height() {
+            color_col.push(Color::black());
+        }
+        colors.push(color_col);
+    }
+    {
+        println!("Spawning {} render threads.", threads);
+
+        // Create a progress bar
+        let mut progress = progress_bar::progress_bar::ProgressBar::new(my_image.height() as usize);
+        progress.set_action(
+            "Raytracing",
+            progress_bar::color::Color::Blue,
+            progress_bar::color::Style::Bold,
+        );
+        // Create a threadpool for rendering the image
+        let mut pool = threadpool::ThreadPool::new(threads);
+        // Number of active Jobs
+        let mut active_jobs = 0;
+        // Iterate over the image
+        for (u, v, _) in my_image.enumerate_pixels() {
+            // Send the pixel to the threadpool to calculate the color
+            let world_clone = world.clone();
+            let cam_clone = cam.clone();
+            let tx_color_clone = tx_color.clone();
+            active_jobs += 1;
+            pool.execute(move || {
+                // Every pixel has its own random sequence, so the image does not depend on the order of the jobs
+                if let Some(seed) = seed {
+                    sampling::seed(seed, v as u64 * width as u64 + u as u64);
                 }
-                // Send the rays to the threadpool to calculate the color
-                let world_clone = world.clone();
-                let tx_color_clone = tx_color.clone();
-                active_jobs += 1;
-                pool.execute(move || {
-                    // Save the Colors in its own vector
-                    let mut color = Color::black();
-                    for ray in rays {
+                // Save the Colors in its own vector
+                let mut color = Color::black();
+                for _ in 0..samples {
+                    // Get a ray to a random point in the pixel from the cam
+                    let s = (u as f64 + sampling::rng().gen::<f64>()) / width as f64;
+                    let t = (v as f64 + sampling::rng().gen::<f64>()) / height as f64;
+                    if let Some(ray) = cam_clone.get_ray(s, t) {
                         color = color + world_clone.get_ray_color(ray, 0);
                     }
-                    tx_color_clone
-                        .send((u as usize, v as usize, color))
-                        .unwrap();
-                });
-
-                // Check if enough jobs are sceduled to keep all threads busy
-                while active_jobs == cores * 4 {
-                    // Collect Results up to this point
-                    while let Ok((x, y, color)) = rx_color.try_recv() {
-                        active_jobs -= 1;
-                        colors[x][y] = colors[x][y] + color;
-                    }
-                    std::thread::yield_now();
                 }
+                tx_color_clone
+                    .send((u as usize, v as usize, color))
+                    .unwrap();
+            });
 
-                // Inc progress counter
-                if u == 0 {
-                    progress.inc();
+            // Check if enough jobs are sceduled to keep all threads busy
+            while active_jobs == threads * 4 {
+                // Collect Results up to this point
+                while let Ok((x, y, color)) = rx_color.try_recv() {
+                    active_jobs -= 1;
+                    colors[x][y] = colors[x][y] + color;
                 }
+                std::thread::yield_now();
             }
-        }
-        // Collect results
-        while let Ok((x, y, color)) = rx_color.try_recv() {
-            colors[x][y] = colors[x][y] + color;
-        }
 
-        for (u, v, pixel) in my_image.enumerate_pixels_mut() {
-            // Correct for anti aliasing
-            let color = colors[u as usize][v as usize] / ANTI_ALIASING as f64;
-            // Do some gamma corrections
-            pixel.0[0] = (color.r().sqrt() * 255.9999) as u8;
-            pixel.0[1] = (color.g().sqrt() * 255.9999) as u8;
-            pixel.0[2] = (color.b().sqrt() * 255.9999) as u8;
+            // Inc progress counter
+            if u == 0 {
+                progress.inc();
+            }
         }
-        println!();
     }
-    println!("Saving image ...");
-    my_image.save("test.png").unwrap();
-    let end_time = std::time::Instant::now();
-    println!("Time to calculate: {}s", (end_time - start_time).as_secs());
+    // Collect results
+    while let Ok((x, y, color)) = rx_color.try_recv() {
+        colors[x][y] = colors[x][y] + color;
+    }
+
+    for (u, v, pixel) in my_image.enumerate_pixels_mut() {
+        // Correct for anti aliasing
+        let color = colors[u as usize][v as usize] / samples as f64;
+        // Do some gamma corrections
+        pixel.0[0] = (color.r().sqrt() * 255.9999) as u8;
+        pixel.0[1] = (color.g().sqrt() * 255.9999) as u8;
+        pixel.0[2] = (color.b().sqrt() * 255.9999) as u8;
+    }
+    println!();
+    my_image
 }
@@ -0,0 +1,521 @@
+//! Materials describe how a ray interacts with the surface of an object it hits.
+
+use crate::color::Color;
+use crate::geometry::Direction;
+use crate::sampling::{random_cosine_direction, random_in_unit_sphere, rng, Onb};
+use crate::scene::{Hit, Ray};
+use crate::texture::{NormalMap, Texture};
+use rand::prelude::*;
+use std::sync::Arc;
+
+mod microfacet;
+
+pub use microfacet::{Conductor, Microfacet};
+
+/// Result of a ray scattering on a surface.
+#[derive(Debug, Copy, Clone)]
+pub struct Scatter {
+    /// The ray leaving the surface
+    pub ray: Ray,
+    /// Color the light of the scattered ray is multiplied with.
+    /// This is the bsdf times the cosine term divided by the pdf of the scattered direction.
+    pub attenuation: Color,
+    /// Pdf (per solid angle) of the scattered direction. None for specular materials whose
+    /// directions can not be combined with sampling the lights directly.
+    pub pdf: Option<f64>,
+}
+
+/// All materials that can be attached to an object must implement this trait
+pub trait Material: std::fmt::Debug {
+    /// Calculates the ray that leaves the surface after the hit.
+    /// Returns None if the ray is absorbed by the material.
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter>;
+
+    /// Light that is emitted by the material at the hitpoint in the direction of the incoming ray.
+    /// Most materials do not emit light.
+    fn emitted(&self, _ray: &Ray, _hit: &Hit) -> Color {
+        Color::black()
+    }
+
+    /// Evaluates the bsdf times the cosine term for light arriving from `direction` and leaving
+    /// in the opposite direction of the ray. Specular materials return black.
+    fn eval(&self, _ray: &Ray, _hit: &Hit, _direction: Direction) -> Color {
+        Color::black()
+    }
+
+    /// Pdf (per solid angle) that `scatter` creates a ray in the given direction.
+    fn pdf(&self, _ray: &Ray, _hit: &Hit, _direction: Direction) -> f64 {
+        0.0
+    }
+
+    /// Normal used for shading at the hitpoint. Materials with a normal map change it.
+    fn shading_normal(&self, hit: &Hit) -> Direction {
+        hit.normal
+    }
+}
+
+/// Ideal diffuse material. The light is scattered in random directions.
+#[derive(Debug, Clone)]
+pub struct Lambertian {
+    /// Color of the surface
+    pub albedo: Arc<dyn Texture + Send + Sync>,
+}
+
+impl Lambertian {
+    /// Creates a new diffuse material with the given color.
+    pub fn new(albedo: Color) -> Self {
+        Self::textured(Arc::new(albedo))
+    }
+
+    /// Creates a new diffuse material whose color is given by a texture
+    pub fn textured(albedo: Arc<dyn Texture + Send + Sync>) -> Self {
+        Self { albedo }
+    }
+
+    /// Color of the surface at the hitpoint
+    fn albedo(&self, hit: &Hit) -> Color {
+        self.albedo.value(hit.uv, hit.point)
+    }
+}
+
+impl Material for Lambertian {
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+        // Scatter cosine weighted around the normal. In this case the bsdf, the cosine and the pdf cancel out.
+        let normal = hit.facing_normal();
+        let (a, b, c) = random_cosine_direction();
+        let direction = Onb::from_w(normal).local(a, b, c).norm();
+        Some(Scatter {
+            ray: Ray {
+                origin: hit.point,
+                direction,
+                time: ray.time,
+            },
+            attenuation: self.albedo(hit),
+            pdf: Some(c / std::f64::consts::PI),
+        })
+    }
+
+    fn eval(&self, _ray: &Ray, hit: &Hit, direction: Direction) -> Color {
+        let cosine = hit.facing_normal().dot(direction);
+        if cosine > 0.0 {
+            self.albedo(hit) * (cosine / std::f64::consts::PI)
+        } else {
+            Color::black()
+        }
+    }
+
+    fn pdf(&self, _ray: &Ray, hit: &Hit, direction: Direction) -> f64 {
+        hit.facing_normal().dot(direction).max(0.0) / std::f64::consts::PI
+    }
+}
+
+/// Reflecting material. The fuzz value blurs the reflection.
+#[derive(Debug, Copy, Clone)]
+pub struct Metal {
+    /// Color of the metal
+    pub albedo: Color,
+    /// Radius of the sphere the reflected direction is randomized in. 0.0 is a perfect mirror.
+    pub fuzz: f64,
+}
+
+impl Metal {
+    /// Creates a new metal. The fuzz value is clamped to 1.0.
+    pub fn new(albedo: Color, fuzz: f64) -> Self {
+        Self {
+            albedo,
+            fuzz: fuzz.min(1.0),
+        }
+    }
+}
+
+impl Material for Metal {
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+        let normal = hit.facing_normal();
+        let reflected = ray.direction.reflect(normal);
+        let direction = (reflected + random_in_unit_sphere() * self.fuzz).norm();
+        // Fuzzy reflections below the surface are absorbed
+        if direction.dot(normal) > 0.0 {
+            Some(Scatter {
+                ray: Ray {
+                    origin: hit.point,
+                    direction,
+                    time: ray.time,
+                },
+                attenuation: self.albedo,
+                pdf: None,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Clear material like glass or water that refracts the light.
+#[derive(Debug, Copy, Clone)]
+pub struct Dielectric {
+    /// Refraction index of the material. Glass is about 1.5
+    pub refraction_index: f64,
+}
+
+impl Dielectric {
+    /// Creates a new dielectric with the given refraction index
+    pub fn new(refraction_index: f64) -> Self {
+        Self { refraction_index }
+    }
+
+    /// Approximation of the fresnel reflectance by Christophe Schlick
+    fn reflectance(cosine: f64, ratio: f64) -> f64 {
+        let r0 = ((1.0 - ratio) / (1.0 + ratio)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
+    }
+}
+
+impl Material for Dielectric {
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+        let (normal, ratio) = if hit.front_face {
+            (hit.normal, 1.0 / self.refraction_index)
+        } else {
+            (hit.normal.invert(), self.refraction_index)
+        };
+
+        let cos_theta = ray.direction.invert().dot(normal).min(1.0);
+        let sin_theta = (1.0 - cos_theta.powi(2)).sqrt();
+
+        // Reflect on total internal reflection or based on the fresnel reflectance
+        let direction = if ratio * sin_theta > 1.0
+            || Self::reflectance(cos_theta, ratio) > rng().gen::<f64>()
+        {
+            ray.direction.reflect(normal)
+        } else {
+            ray.direction.refract(normal, ratio)
+        };
+
+        Some(Scatter {
+            ray: Ray {
+                origin: hit.point,
+                direction: direction.norm(),
+                time: ray.time,
+            },
+            attenuation: Color::white(),
+            pdf: None,
+        })
+    }
+}
+
+/// Distribution of the directions light is scattered to inside of a medium
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PhaseFunction {
+    /// All directions are equally likely
+    Isotropic,
+    /// Phase function by Henyey and Greenstein with the mean cosine g between -1.0 and 1.0.
+    /// Positive values scatter forward like haze, negative values backward.
+    HenyeyGreenstein(f64),
+}
+
+impl PhaseFunction {
+    /// Creates the phase function for the given mean cosine. Zero is isotropic.
+    pub fn new(anisotropy: f64) -> Self {
+        if anisotropy.abs() < 1e-3 {
+            PhaseFunction::Isotropic
+        } else {
+            PhaseFunction::HenyeyGreenstein(anisotropy)
+        }
+    }
+
+    /// Pdf (per solid angle) of scattering by an angle with the given cosine to the direction of travel
+    pub fn value(self, cosine: f64) -> f64 {
+        match self {
+            PhaseFunction::Isotropic => 1.0 / (4.0 * std::f64::consts::PI),
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let denominator = 1.0 + g * g - 2.0 * g * cosine;
+                (1.0 - g * g) / (4.0 * std::f64::consts::PI * denominator * denominator.sqrt())
+            }
+        }
+    }
+
+    /// Samples the scattered direction of light traveling in the given direction
+    pub fn sample(self, direction: Direction) -> Direction {
+        let mut rng = rng();
+        let cosine = match self {
+            PhaseFunction::Isotropic => 1.0 - 2.0 * rng.gen::<f64>(),
+            PhaseFunction::HenyeyGreenstein(g) => {
+                let ratio = (1.0 - g * g) / (1.0 - g + 2.0 * g * rng.gen::<f64>());
+                ((1.0 + g * g - ratio * ratio) / (2.0 * g)).clamp(-1.0, 1.0)
+            }
+        };
+        let sine = (1.0 - cosine * cosine).sqrt();
+        let phi = 2.0 * std::f64::consts::PI * rng.gen::<f64>();
+        Onb::from_w(direction)
+            .local(sine * phi.cos(), sine * phi.sin(), cosine)
+            .norm()
+    }
+}
+
+/// Inside of a participating medium like fog or smoke. Light is scattered in directions given by the phase
+/// function, the albedo is the part of the light that is not absorbed.
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub albedo: Color,
+    pub phase: PhaseFunction,
+    /// Light emitted at every scattering point, e.g. by the flames of a fire
+    pub emission: Arc<dyn Texture + Send + Sync>,
+}
+
+impl Volume {
+    /// Creates a volume that does not emit light
+    pub fn new(albedo: Color, phase: PhaseFunction) -> Self {
+        Self {
+            albedo,
+            phase,
+            emission: Arc::new(Color::black()),
+        }
+    }
+
+    /// Sets the emitted light
+    pub fn with_emission(mut self, emission: Arc<dyn Texture + Send + Sync>) -> Self {
+        self.emission = emission;
+        self
+    }
+}
+
+impl Material for Volume {
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+        // The direction is sampled with the phase function, so only the albedo remains
+        let direction = self.phase.sample(ray.direction);
+        Some(Scatter {
+            ray: Ray {
+                origin: hit.point,
+                direction,
+                time: ray.time,
+            },
+            attenuation: self.albedo,
+            pdf: Some(self.phase.value(ray.direction.dot(direction))),
+        })
+    }
+
+    fn emitted(&self, _ray: &Ray, hit: &Hit) -> Color {
+        self.emission.value(hit.uv, hit.point)
+    }
+
+    fn eval(&self, ray: &Ray, _hit: &Hit, direction: Direction) -> Color {
+        self.albedo * self.phase.value(ray.direction.dot(direction))
+    }
+
+    fn pdf(&self, ray: &Ray, _hit: &Hit, direction: Direction) -> f64 {
+        self.phase.value(ray.direction.dot(direction))
+    }
+}
+
+/// Another material whose shading normal comes from a normal map
+#[derive(Debug, Clone)]
+pub struct NormalMapped {
+    pub material: Arc<dyn Material + Send + Sync>,
+    pub map: NormalMap,
+}
+
+impl NormalMapped {
+    /// Adds the normal map to the material
+    pub fn new(material: Arc<dyn Material + Send + Sync>, map: NormalMap) -> Self {
+        Self { material, map }
+    }
+
+    /// The hit with the normal of the map
+    fn shaded<'a>(&self, hit: &Hit<'a>) -> Hit<'a> {
+        hit.with_shading_normal(self.shading_normal(hit))
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+        self.material.scatter(ray, &self.shaded(hit))
+    }
+
+    fn emitted(&self, ray: &Ray, hit: &Hit) -> Color {
+        self.material.emitted(ray, &self.shaded(hit))
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Direction) -> Color {
+        self.material.eval(ray, &self.shaded(hit), direction)
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &Hit, direction: Direction) -> f64 {
+        self.material.pdf(ray, &self.shaded(hit), direction)
+    }
+
+    fn shading_normal(&self, hit: &Hit) -> Direction {
+        self.map.normal(hit)
+    }
+}
+
+/// Material that emits light. Attached to any object it turns the object into an area light.
+#[derive(Debug, Copy, Clone)]
+pub struct DiffuseLight {
+    /// Emitted radiance. Values above one are allowed and make the light brighter.
+    pub emit: Color,
+}
+
+impl DiffuseLight {
+    /// Creates a new light with the given color and intensity
+    pub fn new(color: Color, intensity: f64) -> Self {
+        Self {
+            emit: color * intensity,
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _ray: &Ray, _hit: &Hit) -> Option<Scatter> {
+        // Lights do not reflect anything
+        None
+    }
+
+    fn emitted(&self, _ray: &Ray, _hit: &Hit) -> Color {
+        self.emit
+    }
+}
+
+mod test {
+
+    #[test]
+    fn lambertian() {
+        use super::{Lambertian, Material};
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::scene::{Hit, Ray};
+
+        let material = Lambertian::new(Color::new(0.8, 0.4, 0.2));
+        // Hit from below the surface
+        let ray = Ray {
+            origin: Location::new(0.0, 0.0, -1.0),
+            direction: Direction::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hit = Hit::new(
+            &ray,
+            1.0,
+            Direction::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            &material,
+        );
+        for _ in 0..1000 {
+            let scatter = material.scatter(&ray, &hit).unwrap();
+            let direction = scatter.ray.direction;
+            assert!(direction.dot(hit.facing_normal()) > 0.0);
+            let pdf = material.pdf(&ray, &hit, direction);
+            assert!((scatter.pdf.unwrap() - pdf).abs() < 1e-9);
+            // The attenuation is eval divided by the pdf
+            let eval = material.eval(&ray, &hit, direction);
+            let expected = scatter.attenuation * pdf;
+            assert!((eval.r() - expected.r()).abs() < 1e-9);
+            assert!((eval.g() - expected.g()).abs() < 1e-9);
+            assert!((eval.b() - expected.b()).abs() < 1e-9);
+        }
+        assert_eq!(material.pdf(&ray, &hit, Direction::new(0.0, 0.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn metal_mirror() {
+        use super::{Material, Metal};
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::scene::{Hit, Ray};
+
+        let material = Metal::new(Color::white(), 0.0);
+        let normal = Direction::new(0.0, 0.0, 1.0);
+        for &side in &[1.0, -1.0] {
+            // From above the surface and from below, for example inside of a sphere
+            let ray = Ray {
+                origin: Location::new(-1.0, 0.0, side),
+                direction: Direction::new(1.0, 0.0, -side).norm(),
+                time: 0.0,
+            };
+            let hit = Hit::new(&ray, 2.0f64.sqrt(), normal, (0.0, 0.0), &material);
+            let scatter = material.scatter(&ray, &hit).unwrap();
+            let expected = Direction::new(1.0, 0.0, side).norm();
+            assert!((scatter.ray.direction - expected).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn dielectric() {
+        use super::{Dielectric, Material};
+        use crate::geometry::{Direction, Location};
+        use crate::scene::{Hit, Ray};
+
+        // Schlick's approximation
+        let r0 = ((1.0 - 1.5) / (1.0 + 1.5)) * ((1.0 - 1.5) / (1.0 + 1.5));
+        assert!((Dielectric::reflectance(1.0, 1.5) - r0).abs() < 1e-12);
+        assert!((Dielectric::reflectance(0.0, 1.5) - 1.0).abs() < 1e-12);
+
+        // Leaving glass at 60 degrees is above the critical angle of about 42 degrees
+        let material = Dielectric::new(1.5);
+        let angle = 60.0f64.to_radians();
+        let ray = Ray {
+            origin: Location::new(-angle.sin(), 0.0, -angle.cos()),
+            direction: Direction::new(angle.sin(), 0.0, angle.cos()),
+            time: 0.0,
+        };
+        let hit = Hit::new(
+            &ray,
+            1.0,
+            Direction::new(0.0, 0.0, 1.0),
+            (0.0, 0.0),
+            &material,
+        );
+        assert!(!hit.front_face);
+        let reflected = Direction::new(angle.sin(), 0.0, -angle.cos());
+        for _ in 0..100 {
+            let scatter = material.scatter(&ray, &hit).unwrap();
+            assert!((scatter.ray.direction - reflected).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn diffuse_light() {
+        use super::{DiffuseLight, Material};
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::scene::{Hit, Ray};
+
+        let light = DiffuseLight::new(Color::new(1.0, 0.5, 0.25), 4.0);
+        let ray = Ray {
+            origin: Location::new(0.0, 0.0, 1.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let hit = Hit::new(&ray, 1.0, Direction::new(0.0, 0.0, 1.0), (0.0, 0.0), &light);
+
+        // Emits the color times the intensity and reflects nothing
+        assert_eq!(light.emitted(&ray, &hit), Color::new(4.0, 2.0, 1.0));
+        assert!(light.scatter(&ray, &hit).is_none());
+    }
+
+    #[test]
+    fn phase_functions() {
+        use super::PhaseFunction;
+        use crate::geometry::Direction;
+
+        let direction = Direction::new(0.0, 0.0, 1.0);
+        for &g in &[0.0, 0.6, -0.3] {
+            let phase = PhaseFunction::new(g);
+
+            // Normalized over the sphere
+            let steps = 10000;
+            let integral: f64 = (0..steps)
+                .map(|i| {
+                    let cosine = -1.0 + 2.0 * (i as f64 + 0.5) / steps as f64;
+                    phase.value(cosine) * 2.0 * std::f64::consts::PI * 2.0 / steps as f64
+                })
+                .sum();
+            assert!((integral - 1.0).abs() < 1e-3);
+
+            // The mean cosine of the sampled directions is the anisotropy
+            let count = 20000;
+            let mean = (0..count)
+                .map(|_| phase.sample(direction).dot(direction))
+                .sum::<f64>()
+                / count as f64;
+            assert!((mean - g).abs() < 0.02);
+        }
+    }
+}
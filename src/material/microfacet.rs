@@ -0,0 +1,405 @@
+//! Physically based rough metals with the GGX microfacet distribution.
+
+use super::{Material, Scatter};
+use crate::color::Color;
+use crate::geometry::Direction;
+use crate::sampling::rng;
+use crate::scene::{Hit, Ray};
+use rand::prelude::*;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Smallest roughness, smoother surfaces are numerically unstable
+const MIN_ALPHA: f64 = 1e-3;
+/// Entries of the albedo table along the cosine and the azimuth of the view direction
+const COSINE_STEPS: usize = 16;
+const AZIMUTH_STEPS: usize = 8;
+/// Stratified samples per axis to integrate the albedo
+const ALBEDO_SAMPLES: usize = 32;
+
+/// Complex index of refraction of a metal for red, green and blue
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Conductor {
+    /// Real part, the index of refraction
+    pub eta: Color,
+    /// Imaginary part, the absorption
+    pub k: Color,
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color) -> Self {
+        Self { eta, k }
+    }
+
+    pub fn gold() -> Self {
+        Self::new(
+            Color::new(0.18299, 0.42108, 1.37340),
+            Color::new(3.42420, 2.34590, 1.77040),
+        )
+    }
+
+    pub fn copper() -> Self {
+        Self::new(
+            Color::new(0.27105, 0.67693, 1.31640),
+            Color::new(3.60920, 2.62480, 2.29210),
+        )
+    }
+
+    pub fn aluminium() -> Self {
+        Self::new(
+            Color::new(1.34560, 0.96521, 0.61722),
+            Color::new(7.47460, 6.39950, 5.30310),
+        )
+    }
+
+    pub fn silver() -> Self {
+        Self::new(
+            Color::new(0.15943, 0.14512, 0.13547),
+            Color::new(3.92910, 3.19000, 2.38080),
+        )
+    }
+
+    /// Fresnel reflectance of unpolarized light for the cosine of the angle to the normal
+    pub fn fresnel(&self, cosine: f64) -> Color {
+        Color::new(
+            fresnel_conductor(cosine, self.eta.r(), self.k.r()),
+            fresnel_conductor(cosine, self.eta.g(), self.k.g()),
+            fresnel_conductor(cosine, self.eta.b(), self.k.b()),
+        )
+    }
+}
+
+/// Exact Fresnel equations for a conductor in vacuum
+fn fresnel_conductor(cosine: f64, eta: f64, k: f64) -> f64 {
+    let cos2 = cosine.clamp(0.0, 1.0).powi(2);
+    let sin2 = 1.0 - cos2;
+    let t0 = eta * eta - k * k - sin2;
+    let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+    let t1 = a2_plus_b2 + cos2;
+    let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+    let t2 = 2.0 * cosine.clamp(0.0, 1.0) * a;
+    let rs = (t1 - t2) / (t1 + t2);
+    let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+    let t4 = t2 * sin2;
+    let rp = rs * (t3 - t4) / (t3 + t4);
+    0.5 * (rp + rs)
+}
+
+/// GGX distribution of the microfacet normals, stretched along the tangent and the bitangent.
+/// All directions are in the local frame with the normal as z axis.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Ggx {
+    alpha_x: f64,
+    alpha_y: f64,
+}
+
+impl Ggx {
+    /// Density of the microfacet normal h
+    fn distribution(self, h: Direction) -> f64 {
+        let x = h.x() / self.alpha_x;
+        let y = h.y() / self.alpha_y;
+        let denominator = x * x + y * y + h.z() * h.z();
+        1.0 / (PI * self.alpha_x * self.alpha_y * denominator * denominator)
+    }
+
+    /// Smith's lambda, the ratio of hidden to visible microfacet area in the direction
+    fn lambda(self, w: Direction) -> f64 {
+        let x = self.alpha_x * w.x();
+        let y = self.alpha_y * w.y();
+        ((1.0 + (x * x + y * y) / (w.z() * w.z())).sqrt() - 1.0) / 2.0
+    }
+
+    /// Part of the microfacets visible from the direction
+    fn masking(self, w: Direction) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Part of the microfacets visible from both directions, height correlated
+    fn masking_shadowing(self, wo: Direction, wi: Direction) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Samples a microfacet normal visible from `wo` with the method by Eric Heitz
+    fn sample_visible_normal(self, wo: Direction, u1: f64, u2: f64) -> Direction {
+        // Stretch the view direction, so the distribution becomes a hemisphere
+        let v = Direction::new(self.alpha_x * wo.x(), self.alpha_y * wo.y(), wo.z()).norm();
+        let length2 = v.x() * v.x() + v.y() * v.y();
+        let t1 = if length2 > 0.0 {
+            Direction::new(-v.y(), v.x(), 0.0) / length2.sqrt()
+        } else {
+            Direction::new(1.0, 0.0, 0.0)
+        };
+        let t2 = v.cross(t1);
+        // Uniform point on the projected disk, the part hidden by the hemisphere is squeezed out
+        let r = u1.sqrt();
+        let phi = 2.0 * PI * u2;
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + v.z());
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let n = t1 * p1 + t2 * p2 + v * (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt();
+        Direction::new(self.alpha_x * n.x(), self.alpha_y * n.y(), n.z().max(0.0)).norm()
+    }
+
+    /// Pdf (per solid angle) of reflecting `wo` into `wi` with a sampled visible normal
+    fn pdf(self, wo: Direction, wi: Direction) -> f64 {
+        let h = (wo + wi).norm();
+        let cosine = wo.dot(h);
+        if cosine <= 0.0 {
+            return 0.0;
+        }
+        // Density of the visible normals divided by the jacobian of the reflection
+        self.masking(wo) * self.distribution(h) / (4.0 * wo.z())
+    }
+}
+
+/// Reflected fraction of the light of a perfectly reflecting GGX surface with a single bounce, by the cosine and
+/// the azimuth of the view direction. It is below one because the light that bounces between the microfacets is
+/// missing.
+#[derive(Debug, Clone)]
+struct AlbedoTable {
+    azimuths: usize,
+    values: Vec<f64>,
+}
+
+impl AlbedoTable {
+    fn new(ggx: Ggx) -> Self {
+        // The distribution is mirror symmetric, so a quarter of the azimuths is enough. Isotropic ones need only one.
+        let azimuths = if ggx.alpha_x == ggx.alpha_y {
+            1
+        } else {
+            AZIMUTH_STEPS
+        };
+        let mut values = Vec::with_capacity(COSINE_STEPS * azimuths);
+        for i in 0..COSINE_STEPS {
+            let cosine = (i as f64 / (COSINE_STEPS - 1) as f64).max(1e-3);
+            let sine = (1.0 - cosine * cosine).sqrt();
+            for j in 0..azimuths {
+                let phi = Self::azimuth(j, azimuths);
+                let wo = Direction::new(sine * phi.cos(), sine * phi.sin(), cosine);
+                // Stratified samples of the visible normals, each has the weight G2 / G1
+                let mut sum = 0.0;
+                for a in 0..ALBEDO_SAMPLES {
+                    for b in 0..ALBEDO_SAMPLES {
+                        let u1 = (a as f64 + 0.5) / ALBEDO_SAMPLES as f64;
+                        let u2 = (b as f64 + 0.5) / ALBEDO_SAMPLES as f64;
+                        let wi = wo.reflect_about(ggx.sample_visible_normal(wo, u1, u2));
+                        if wi.z() > 0.0 {
+                            sum += ggx.masking_shadowing(wo, wi) / ggx.masking(wo);
+                        }
+                    }
+                }
+                values.push(sum / (ALBEDO_SAMPLES * ALBEDO_SAMPLES) as f64);
+            }
+        }
+        Self { azimuths, values }
+    }
+
+    fn azimuth(index: usize, count: usize) -> f64 {
+        if count > 1 {
+            index as f64 / (count - 1) as f64 * PI / 2.0
+        } else {
+            0.0
+        }
+    }
+
+    /// Bilinear interpolation of the albedo for the direction in the local frame
+    fn albedo(&self, wo: Direction) -> f64 {
+        let position = |value: f64, count: usize| {
+            if count < 2 {
+                return (0, 0, 0.0);
+            }
+            let x = value.clamp(0.0, 1.0) * (count - 1) as f64;
+            let low = (x.floor() as usize).min(count - 2);
+            (low, low + 1, x - low as f64)
+        };
+        let (i0, i1, fi) = position(wo.z(), COSINE_STEPS);
+        let phi = wo.y().abs().atan2(wo.x().abs()) / (PI / 2.0);
+        let (j0, j1, fj) = position(phi, self.azimuths);
+        let entry = |i: usize, j: usize| self.values[i * self.azimuths + j];
+        let low = entry(i0, j0) * (1.0 - fj) + entry(i0, j1) * fj;
+        let high = entry(i1, j0) * (1.0 - fj) + entry(i1, j1) * fj;
+        low * (1.0 - fi) + high * fi
+    }
+}
+
+trait Reflect {
+    fn reflect_about(self, normal: Direction) -> Direction;
+}
+
+impl Reflect for Direction {
+    /// Mirrors the direction pointing away from the surface at the normal
+    fn reflect_about(self, normal: Direction) -> Direction {
+        normal * (2.0 * self.dot(normal)) - self
+    }
+}
+
+/// Rough metal after Cook and Torrance with the GGX distribution of microfacets. The reflected directions are
+/// sampled from the visible microfacet normals. The light that bounces several times between the microfacets is
+/// added back with the energy compensation by Turquin, so rough metals do not get darker.
+#[derive(Debug, Clone)]
+pub struct Microfacet {
+    conductor: Conductor,
+    ggx: Ggx,
+    /// Reflectance at normal incidence, the color of the light that bounces several times
+    f0: Color,
+    /// Single scattering albedo, shared by the clones
+    albedo: Arc<AlbedoTable>,
+}
+
+impl Microfacet {
+    /// `roughness` from 0.0 for polished to 1.0 for very rough metal. `anisotropy` from -1.0 to 1.0 stretches
+    /// the highlights along the bitangent for positive and along the tangent for negative values.
+    pub fn new(conductor: Conductor, roughness: f64, anisotropy: f64) -> Self {
+        let alpha = roughness.clamp(0.0, 1.0).powi(2);
+        let aspect = (1.0 - 0.9 * anisotropy.abs().min(1.0)).sqrt();
+        let (alpha_x, alpha_y) = if anisotropy >= 0.0 {
+            (alpha / aspect, alpha * aspect)
+        } else {
+            (alpha * aspect, alpha / aspect)
+        };
+        let ggx = Ggx {
+            alpha_x: alpha_x.max(MIN_ALPHA),
+            alpha_y: alpha_y.max(MIN_ALPHA),
+        };
+        Self {
+            conductor,
+            ggx,
+            f0: conductor.fresnel(1.0),
+            albedo: Arc::new(AlbedoTable::new(ggx)),
+        }
+    }
+
+    /// Local frame of the hit, with the normal on the side of the ray
+    fn frame(hit: &Hit) -> (Direction, Direction, Direction) {
+        let normal = hit.facing_normal();
+        let tangent = hit.tangent;
+        (tangent, normal.cross(tangent), normal)
+    }
+
+    fn to_local(frame: (Direction, Direction, Direction), w: Direction) -> Direction {
+        Direction::new(w.dot(frame.0), w.dot(frame.1), w.dot(frame.2))
+    }
+
+    /// Factor that adds the light of multiple bounces for the view direction
+    fn compensation(&self, wo: Direction) -> Color {
+        let albedo = self.albedo.albedo(wo).max(1e-3);
+        Color::white() + self.f0 * (1.0 / albedo - 1.0)
+    }
+}
+
+impl Material for Microfacet {
+    fn scatter(&self, ray: &Ray, hit: &Hit) -> Option<Scatter> {
+        let frame = Self::frame(hit);
+        let wo = Self::to_local(frame, ray.direction.invert());
+        if wo.z() <= 0.0 {
+            return None;
+        }
+        let mut rng = rng();
+        let h = self.ggx.sample_visible_normal(wo, rng.gen(), rng.gen());
+        let wi = wo.reflect_about(h);
+        if wi.z() <= 0.0 {
+            // Reflected into the surface
+            return None;
+        }
+        let direction = (frame.0 * wi.x() + frame.1 * wi.y() + frame.2 * wi.z()).norm();
+        // bsdf * cosine / pdf, most terms cancel with the visible normal sampling
+        let weight = self.ggx.masking_shadowing(wo, wi) / self.ggx.masking(wo);
+        Some(Scatter {
+            ray: Ray {
+                origin: hit.point,
+                direction,
+                time: ray.time,
+            },
+            attenuation: self.conductor.fresnel(wo.dot(h)) * self.compensation(wo) * weight,
+            pdf: Some(self.ggx.pdf(wo, wi)),
+        })
+    }
+
+    fn eval(&self, ray: &Ray, hit: &Hit, direction: Direction) -> Color {
+        let frame = Self::frame(hit);
+        let wo = Self::to_local(frame, ray.direction.invert());
+        let wi = Self::to_local(frame, direction);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return Color::black();
+        }
+        let h = (wo + wi).norm();
+        // D * G * F / (4 * cos_o * cos_i) times cos_i
+        let value = self.ggx.distribution(h) * self.ggx.masking_shadowing(wo, wi) / (4.0 * wo.z());
+        self.conductor.fresnel(wo.dot(h)) * self.compensation(wo) * value
+    }
+
+    fn pdf(&self, ray: &Ray, hit: &Hit, direction: Direction) -> f64 {
+        let frame = Self::frame(hit);
+        let wo = Self::to_local(frame, ray.direction.invert());
+        let wi = Self::to_local(frame, direction);
+        if wo.z() <= 0.0 || wi.z() <= 0.0 {
+            return 0.0;
+        }
+        self.ggx.pdf(wo, wi)
+    }
+}
+
+mod test {
+
+    #[test]
+    fn fresnel_presets() {
+        use super::Conductor;
+
+        // Gold reflects more red than blue, aluminium is bright and neutral
+        let gold = Conductor::gold().fresnel(1.0);
+        assert!(gold.r() > 0.9 && gold.b() < 0.5);
+        let aluminium = Conductor::aluminium().fresnel(1.0);
+        assert!(aluminium.r() > 0.85 && aluminium.b() > 0.85);
+        // Every metal becomes a mirror at grazing angles
+        let copper = Conductor::copper().fresnel(0.0);
+        assert!((copper.r() - 1.0).abs() < 1e-9 && (copper.b() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn white_furnace() {
+        use super::{Conductor, Microfacet};
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Material;
+        use crate::scene::{Hit, Ray};
+
+        // A metal that reflects everything must not lose energy, independent of the roughness
+        let perfect = Conductor::new(Color::new(0.0, 0.0, 0.0), Color::new(1e4, 1e4, 1e4));
+        for &(roughness, anisotropy) in &[(0.1, 0.0), (0.5, 0.0), (1.0, 0.0), (0.6, 0.8)] {
+            let material = Microfacet::new(perfect, roughness, anisotropy);
+            let direction = Direction::new(1.0, 0.0, -1.0).norm();
+            let ray = Ray {
+                origin: Location::new(-1.0, 0.0, 1.0),
+                direction,
+                time: 0.0,
+            };
+            let hit = Hit::new(
+                &ray,
+                2f64.sqrt(),
+                Direction::new(0.0, 0.0, 1.0),
+                (0.0, 0.0),
+                &material,
+            )
+            .with_tangent(Direction::new(1.0, 0.0, 0.0));
+
+            let count = 20000;
+            let mut energy = 0.0;
+            for _ in 0..count {
+                if let Some(scatter) = material.scatter(&ray, &hit) {
+                    assert!(scatter.ray.direction.z() > 0.0);
+                    let pdf = material.pdf(&ray, &hit, scatter.ray.direction);
+                    assert!((pdf - scatter.pdf.unwrap()).abs() <= 1e-9 * pdf);
+                    // Sampling and evaluation agree
+                    let expected = material.eval(&ray, &hit, scatter.ray.direction) / pdf;
+                    assert!(
+                        (expected.g() - scatter.attenuation.g()).abs()
+                            < 1e-6 * expected.g().max(1.0)
+                    );
+                    energy += scatter.attenuation.g();
+                }
+            }
+            let energy = energy / count as f64;
+            assert!((energy - 1.0).abs() < 0.03, "{} {}", roughness, energy);
+        }
+    }
+}
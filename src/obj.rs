@@ -0,0 +1,513 @@
+//! Loader for wavefront obj files and their mtl material libraries.
+//!
+//! Supported are positions, normals, texture coordinates, polygon faces (triangulated as fan),
+//! groups and materials. The mtl materials are mapped to the materials of the raytracer:
+//! - a dissolve `d` below one results in a `Dielectric` with the refraction index `Ni`
+//! - a specular color `Ks` brighter than the diffuse color `Kd` results in a `Metal`, whose fuzz is derived from `Ns`
+//! - everything else is `Lambertian` with the color `Kd` or the texture `map_Kd`
+
+use crate::color::Color;
+use crate::geometry::{Direction, Location};
+use crate::material::{Dielectric, Lambertian, Material, Metal};
+use crate::scene::objects::{TriangleMesh, VertexBuffer};
+use crate::texture::ImageTexture;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Error while loading an obj or mtl file
+#[derive(Debug, Clone)]
+pub struct ObjError {
+    /// File in which the error occured
+    pub file: PathBuf,
+    /// Line of the error, None if the file could not be read
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: {}", self.file.display(), line, self.message),
+            None => write!(f, "{}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+/// Part of an obj file with the same group and material
+pub struct ObjGroup {
+    /// Name of the group, empty if the faces are not part of a group
+    pub name: String,
+    /// Name of the material, empty if no material is used
+    pub material: String,
+    pub mesh: TriangleMesh,
+}
+
+/// Indices of position, texture coordinate and normal of a vertex of a face
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+/// Triangles of a group with the same material while parsing
+struct FaceGroup {
+    name: String,
+    material: String,
+    triangles: Vec<[usize; 3]>,
+}
+
+/// Loads an obj file. Material libraries are searched relative to the obj file.
+/// Each group has its own vertices, so smooth shading and textures are decided per group.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<Vec<ObjGroup>, ObjError> {
+    let path = path.as_ref();
+    let source = read_file(path)?;
+    parse_obj(&source, path)
+}
+
+/// Parses the content of an obj file. `file` is used for error messages and to find material libraries.
+pub fn parse_obj(source: &str, file: &Path) -> Result<Vec<ObjGroup>, ObjError> {
+    let base_dir = file.parent().unwrap_or_else(|| Path::new(""));
+
+    // Data as it is in the file
+    let mut positions: Vec<Location> = Vec::new();
+    let mut normals: Vec<Direction> = Vec::new();
+    let mut uvs: Vec<(f64, f64)> = Vec::new();
+    let mut materials: HashMap<String, Arc<dyn Material + Send + Sync>> = HashMap::new();
+
+    // Vertices of the meshes. Every combination of position, uv and normal is a vertex.
+    let mut vertex_indices: HashMap<VertexKey, usize> = HashMap::new();
+    let mut vertices: Vec<VertexKey> = Vec::new();
+    // Triangles for each combination of group and material in the order of appearance
+    let mut groups: Vec<FaceGroup> = Vec::new();
+
+    let mut group = String::new();
+    let mut material = String::new();
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| ObjError {
+            file: file.to_path_buf(),
+            line: Some(line_index + 1),
+            message,
+        };
+        let line = strip_comment(line);
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        match keyword {
+            "v" => {
+                let v = parse_floats(&args, 3, 4).map_err(error)?;
+                positions.push(Location::new(v[0], v[1], v[2]));
+            }
+            "vn" => {
+                let v = parse_floats(&args, 3, 3).map_err(error)?;
+                normals.push(Direction::new(v[0], v[1], v[2]).norm());
+            }
+            "vt" => {
+                let v = parse_floats(&args, 1, 3).map_err(error)?;
+                uvs.push((v[0], v.get(1).copied().unwrap_or(0.0)));
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error(format!(
+                        "Face needs at least three vertices, found {}",
+                        args.len()
+                    )));
+                }
+                let mut face = Vec::with_capacity(args.len());
+                for arg in &args {
+                    let key = parse_face_vertex(arg, positions.len(), uvs.len(), normals.len())
+                        .map_err(error)?;
+                    let index = *vertex_indices.entry(key).or_insert_with(|| {
+                        vertices.push(key);
+                        vertices.len() - 1
+                    });
+                    face.push(index);
+                }
+
+                let triangles = match groups
+                    .iter_mut()
+                    .find(|g| g.name == group && g.material == material)
+                {
+                    Some(g) => &mut g.triangles,
+                    None => {
+                        groups.push(FaceGroup {
+                            name: group.clone(),
+                            material: material.clone(),
+                            triangles: Vec::new(),
+                        });
+                        &mut groups.last_mut().unwrap().triangles
+                    }
+                };
+                // Triangulate the polygon as a fan
+                for i in 1..face.len() - 1 {
+                    triangles.push([face[0], face[i], face[i + 1]]);
+                }
+            }
+            "g" | "o" => {
+                group = args.join(" ");
+            }
+            "usemtl" => {
+                material = args.join(" ");
+                if !materials.contains_key(&material) {
+                    return Err(error(format!("Unknown material {}", material)));
+                }
+            }
+            "mtllib" => {
+                for library in &args {
+                    let path = base_dir.join(library);
+                    let source = read_file(&path).map_err(|e| error(e.to_string()))?;
+                    materials.extend(parse_mtl(&source, &path)?);
+                }
+            }
+            // Smoothing groups, lines, points and curves are not supported
+            _ => {}
+        }
+    }
+
+    let default_material: Arc<dyn Material + Send + Sync> =
+        Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8)));
+    Ok(groups
+        .into_iter()
+        .map(|group| {
+            // Number the vertices of the group from zero
+            let mut group_indices: HashMap<usize, usize> = HashMap::new();
+            let mut group_vertices: Vec<VertexKey> = Vec::new();
+            let triangles = group
+                .triangles
+                .iter()
+                .map(|triangle| {
+                    triangle.map(|index| {
+                        *group_indices.entry(index).or_insert_with(|| {
+                            group_vertices.push(vertices[index]);
+                            group_vertices.len() - 1
+                        })
+                    })
+                })
+                .collect();
+            let vertex_buffer = vertex_buffer(&group_vertices, &positions, &normals, &uvs);
+            let mesh_material = materials
+                .get(&group.material)
+                .cloned()
+                .unwrap_or_else(|| default_material.clone());
+            ObjGroup {
+                name: group.name,
+                material: group.material,
+                mesh: TriangleMesh::new(Arc::new(vertex_buffer), triangles, mesh_material),
+            }
+        })
+        .collect())
+}
+
+/// Collects the data of the vertices. Normals and uvs are only used if all vertices have one.
+fn vertex_buffer(
+    vertices: &[VertexKey],
+    positions: &[Location],
+    normals: &[Direction],
+    uvs: &[(f64, f64)],
+) -> VertexBuffer {
+    VertexBuffer {
+        positions: vertices.iter().map(|v| positions[v.0]).collect(),
+        normals: vertices
+            .iter()
+            .map(|v| v.2.map(|n| normals[n]))
+            .collect::<Option<_>>()
+            .unwrap_or_default(),
+        uvs: vertices
+            .iter()
+            .map(|v| v.1.map(|uv| uvs[uv]))
+            .collect::<Option<_>>()
+            .unwrap_or_default(),
+    }
+}
+
+/// Parses the content of a mtl file. `file` is used for error messages and to find textures.
+pub fn parse_mtl(
+    source: &str,
+    file: &Path,
+) -> Result<HashMap<String, Arc<dyn Material + Send + Sync>>, ObjError> {
+    let base_dir = file.parent().unwrap_or_else(|| Path::new(""));
+    let mut materials = HashMap::new();
+    let mut current: Option<(String, MtlMaterial)> = None;
+
+    for (line_index, line) in source.lines().enumerate() {
+        let error = |message: String| ObjError {
+            file: file.to_path_buf(),
+            line: Some(line_index + 1),
+            message,
+        };
+        let line = strip_comment(line);
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args: Vec<&str> = tokens.collect();
+
+        if keyword == "newmtl" {
+            if let Some((name, material)) = current.take() {
+                materials.insert(name, material.into_material());
+            }
+            current = Some((args.join(" "), MtlMaterial::default()));
+            continue;
+        }
+
+        let material = match current.as_mut() {
+            Some((_, material)) => material,
+            None => return Err(error(format!("{} before the first newmtl", keyword))),
+        };
+        match keyword {
+            "Kd" => material.diffuse = parse_color(&args).map_err(error)?,
+            "Ks" => material.specular = parse_color(&args).map_err(error)?,
+            "Ns" => material.shininess = parse_floats(&args, 1, 1).map_err(error)?[0],
+            "Ni" => material.refraction_index = parse_floats(&args, 1, 1).map_err(error)?[0],
+            "d" => material.dissolve = parse_floats(&args, 1, 1).map_err(error)?[0],
+            "Tr" => material.dissolve = 1.0 - parse_floats(&args, 1, 1).map_err(error)?[0],
+            "map_Kd" => {
+                // Options of the texture are not supported, the file name is the last argument
+                let name = args
+                    .last()
+                    .ok_or_else(|| error("map_Kd needs a file name".into()))?;
+                let texture = ImageTexture::open(base_dir.join(name)).map_err(error)?;
+                material.diffuse_texture = Some(Arc::new(texture));
+            }
+            // Everything else has no equivalent in the raytracer
+            _ => {}
+        }
+    }
+    if let Some((name, material)) = current {
+        materials.insert(name, material.into_material());
+    }
+    Ok(materials)
+}
+
+/// Material values of a mtl file
+#[derive(Debug)]
+struct MtlMaterial {
+    diffuse: Color,
+    diffuse_texture: Option<Arc<ImageTexture>>,
+    specular: Color,
+    shininess: f64,
+    refraction_index: f64,
+    dissolve: f64,
+}
+
+impl Default for MtlMaterial {
+    fn default() -> Self {
+        Self {
+            diffuse: Color::new(0.8, 0.8, 0.8),
+            diffuse_texture: None,
+            specular: Color::black(),
+            shininess: 0.0,
+            refraction_index: 1.5,
+            dissolve: 1.0,
+        }
+    }
+}
+
+impl MtlMaterial {
+    /// Maps the values to the best matching material of the raytracer
+    fn into_material(self) -> Arc<dyn Material + Send + Sync> {
+        let brightness = |c: Color| c.r().max(c.g()).max(c.b());
+        if self.dissolve < 1.0 {
+            Arc::new(Dielectric::new(self.refraction_index))
+        } else if self.diffuse_texture.is_none()
+            && brightness(self.specular) > brightness(self.diffuse)
+        {
+            // Map the phong exponent to the fuzz of the reflection
+            let fuzz = (2.0 / (self.shininess + 2.0)).sqrt();
+            Arc::new(Metal::new(self.specular, fuzz))
+        } else if let Some(texture) = self.diffuse_texture {
+            Arc::new(Lambertian::textured(texture))
+        } else {
+            Arc::new(Lambertian::new(self.diffuse))
+        }
+    }
+}
+
+/// Reads a file into a string
+fn read_file(path: &Path) -> Result<String, ObjError> {
+    std::fs::read_to_string(path).map_err(|e| ObjError {
+        file: path.to_path_buf(),
+        line: None,
+        message: e.to_string(),
+    })
+}
+
+/// Removes everything after a #
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+/// Parses between `min` and `max` floating point numbers
+fn parse_floats(args: &[&str], min: usize, max: usize) -> Result<Vec<f64>, String> {
+    if args.len() < min || args.len() > max {
+        return Err(if min == max {
+            format!("Expected {} numbers, found {}", min, args.len())
+        } else {
+            format!("Expected {} to {} numbers, found {}", min, max, args.len())
+        });
+    }
+    args.iter()
+        .map(|arg| {
+            arg.parse::<f64>()
+                .map_err(|_| format!("Invalid number {}", arg))
+        })
+        .collect()
+}
+
+/// Parses a color with three components
+fn parse_color(args: &[&str]) -> Result<Color, String> {
+    let v = parse_floats(args, 3, 3)?;
+    Ok(Color::new(v[0], v[1], v[2]))
+}
+
+/// Parses a vertex of a face (`v`, `v/vt`, `v//vn` or `v/vt/vn`) into zero based indices.
+/// Negative indices are relative to the end of the current lists.
+fn parse_face_vertex(
+    arg: &str,
+    positions: usize,
+    uvs: usize,
+    normals: usize,
+) -> Result<VertexKey, String> {
+    let mut parts = arg.split('/');
+    let position = parts.next().unwrap_or("");
+    let uv = parts.next().unwrap_or("");
+    let normal = parts.next().unwrap_or("");
+    if parts.next().is_some() {
+        return Err(format!("Invalid face vertex {}", arg));
+    }
+
+    let resolve = |index: &str, count: usize, name: &str| -> Result<usize, String> {
+        let value: i64 = index
+            .parse()
+            .map_err(|_| format!("Invalid {} index {}", name, index))?;
+        let resolved = if value < 0 {
+            count as i64 + value
+        } else {
+            value - 1
+        };
+        if resolved < 0 || resolved >= count as i64 {
+            Err(format!("{} index {} out of range", name, value))
+        } else {
+            Ok(resolved as usize)
+        }
+    };
+
+    let position = resolve(position, positions, "Position")?;
+    let uv = if uv.is_empty() {
+        None
+    } else {
+        Some(resolve(uv, uvs, "Texture coordinate")?)
+    };
+    let normal = if normal.is_empty() {
+        None
+    } else {
+        Some(resolve(normal, normals, "Normal")?)
+    };
+    Ok((position, uv, normal))
+}
+
+mod test {
+
+    #[test]
+    fn parse_quad_with_groups() {
+        let source = "
+            # A quad split in two groups
+            v 0 0 0
+            v 1 0 0
+            v 1 1 0
+            v 0 1 0
+            vt 0 0
+            vt 1 0
+            vt 1 1
+            vt 0 1
+            vn 0 0 1
+            g first
+            f 1/1/1 2/2/1 3/3/1 4/4/1
+            g second
+            f -4/-4/-1 -2/-2/-1 -1/-1/-1
+        ";
+        let groups = super::parse_obj(source, std::path::Path::new("test.obj")).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "first");
+        assert_eq!(groups[0].mesh.len(), 2);
+        assert_eq!(groups[1].name, "second");
+        assert_eq!(groups[1].mesh.len(), 1);
+    }
+
+    #[test]
+    fn normals_per_group() {
+        use crate::geometry::{Direction, Location};
+        use crate::scene::{Hittable, Ray};
+
+        // A smooth triangle with tilted normals and a flat one without normals
+        let source = "
+            v 0 0 0
+            v 1 0 0
+            v 0 1 0
+            v 0 0 1
+            vn 0 1 1
+            g smooth
+            f 1//1 2//1 3//1
+            g flat
+            f 1 2 4
+        ";
+        let groups = super::parse_obj(source, std::path::Path::new("test.obj")).unwrap();
+        let ray = Ray {
+            origin: Location::new(0.25, 0.25, 1.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let hit = groups[0].mesh.get_hits(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.normal - Direction::new(0.0, 1.0, 1.0).norm()).length() < 1e-12);
+        let ray = Ray {
+            origin: Location::new(0.25, -1.0, 0.25),
+            direction: Direction::new(0.0, 1.0, 0.0),
+            time: 0.0,
+        };
+        let hit = groups[1].mesh.get_hits(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.normal.dot(Direction::new(0.0, 1.0, 0.0)).abs() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn errors_with_line_numbers() {
+        let path = std::path::Path::new("broken.obj");
+        let error = super::parse_obj("v 0 0 0\nv 1 0 x\n", path).err().unwrap();
+        assert_eq!(error.line, Some(2));
+        assert_eq!(error.to_string(), "broken.obj:2: Invalid number x");
+
+        let error = super::parse_obj("v 0 0 0\nv 1 0 0\n\nf 1 2 3\n", path)
+            .err()
+            .unwrap();
+        assert_eq!(error.line, Some(4));
+
+        let error = super::parse_obj("usemtl missing\n", path).err().unwrap();
+        assert_eq!(error.line, Some(1));
+    }
+
+    #[test]
+    fn mtl_mapping() {
+        let source = "
+            newmtl glass
+            Kd 1 1 1
+            Ni 1.33
+            d 0.2
+            newmtl gold
+            Kd 0.1 0.1 0.1
+            Ks 0.9 0.7 0.2
+            Ns 200
+            newmtl red
+            Kd 0.8 0.1 0.1
+        ";
+        let materials = super::parse_mtl(source, std::path::Path::new("test.mtl")).unwrap();
+        assert!(format!("{:?}", materials["glass"]).starts_with("Dielectric"));
+        assert!(format!("{:?}", materials["gold"]).starts_with("Metal"));
+        assert!(format!("{:?}", materials["red"]).starts_with("Lambertian"));
+    }
+}
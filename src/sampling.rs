@@ -0,0 +1,242 @@
+//! Helper functions to create random samples for the monte carlo integration of the render equation.
+
+use crate::geometry::Direction;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+use std::cell::RefCell;
+
+thread_local! {
+    /// Random number generator of the thread. Seeded from the system unless `seed` is called.
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_entropy());
+}
+
+/// Handle to the random number generator of the current thread.
+/// Used for all random numbers of the renderer, so the image can be reproduced with `seed`.
+#[derive(Debug, Copy, Clone, Default)]
+pub struct LocalRng;
+
+impl RngCore for LocalRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        RNG.with(|rng| rng.borrow_mut().next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        RNG.with(|rng| rng.borrow_mut().fill_bytes(dest))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        RNG.with(|rng| rng.borrow_mut().try_fill_bytes(dest))
+    }
+}
+
+/// Returns the random number generator of the current thread
+pub fn rng() -> LocalRng {
+    LocalRng
+}
+
+/// Seeds the random number generator of the current thread.
+/// `stream` selects an independent sequence for the same seed, for example the index of a pixel.
+pub fn seed(seed: u64, stream: u64) {
+    // Mix seed and stream with splitmix64 so neighbouring streams are not correlated
+    let mut z = seed ^ stream.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(z));
+}
+
+/// Orthonormal basis used to transform directions from a local coordinate system into the world.
+/// The w axis is the given direction, mostly a surface normal.
+#[derive(Debug, Copy, Clone)]
+pub struct Onb {
+    pub u: Direction,
+    pub v: Direction,
+    pub w: Direction,
+}
+
+impl Onb {
+    /// Builds a basis around the given direction. The direction has to be normalized.
+    pub fn from_w(w: Direction) -> Self {
+        // Choose a helper axis that is not parallel to w
+        let helper = if w.x().abs() > 0.9 {
+            Direction::new(0.0, 1.0, 0.0)
+        } else {
+            Direction::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(helper).norm();
+        let u = v.cross(w);
+        Self { u, v, w }
+    }
+
+    /// Transforms the local coordinates into a world direction
+    pub fn local(&self, a: f64, b: f64, c: f64) -> Direction {
+        self.u * a + self.v * b + self.w * c
+    }
+}
+
+/// Returns a random direction with a length smaller than one.
+pub fn random_in_unit_sphere() -> Direction {
+    let mut rng = rng();
+    // Create a bad and ugly random generator
+    let offset_dir = Direction::new(1.0, 1.0, 1.0);
+    loop {
+        let rnd_dir = Direction::new(rng.gen(), rng.gen(), rng.gen()) * 2.0 - offset_dir;
+        if rnd_dir.length() <= 1.0 {
+            return rnd_dir;
+        }
+    }
+}
+
+/// Returns a random direction with length one.
+pub fn random_unit_vector() -> Direction {
+    random_in_unit_sphere().norm()
+}
+
+/// Returns a random direction in the hemisphere around z with a density proportional to the cosine to z.
+/// The pdf of the direction is cos(theta) / pi.
+pub fn random_cosine_direction() -> (f64, f64, f64) {
+    let mut rng = rng();
+    let r1: f64 = rng.gen();
+    let r2: f64 = rng.gen();
+    let phi = 2.0 * std::f64::consts::PI * r1;
+    let r = r2.sqrt();
+    (phi.cos() * r, phi.sin() * r, (1.0 - r2).sqrt())
+}
+
+/// Returns a random direction in a cone around z with an opening angle of acos(cos_theta_max).
+/// All directions in the cone are equally likely, the pdf is 1 / (2 pi (1 - cos_theta_max)).
+pub fn random_in_cone(cos_theta_max: f64) -> (f64, f64, f64) {
+    let mut rng = rng();
+    let r1: f64 = rng.gen();
+    let r2: f64 = rng.gen();
+    let z = 1.0 + r2 * (cos_theta_max - 1.0);
+    let phi = 2.0 * std::f64::consts::PI * r1;
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    (phi.cos() * r, phi.sin() * r, z)
+}
+
+/// Returns a random point in the disk with radius one. All points are equally likely.
+pub fn random_in_unit_disk() -> (f64, f64) {
+    let mut rng = rng();
+    let r1: f64 = rng.gen();
+    let r2: f64 = rng.gen();
+    let phi = 2.0 * std::f64::consts::PI * r1;
+    let r = r2.sqrt();
+    (phi.cos() * r, phi.sin() * r)
+}
+
+/// Returns a random point in a regular polygon with `sides` corners on the unit circle.
+/// The first corner is at the angle `rotation` in radians. All points are equally likely.
+pub fn random_in_polygon(sides: u32, rotation: f64) -> (f64, f64) {
+    let mut rng = rng();
+    // Select one of the triangles between the center and an edge
+    let step = 2.0 * std::f64::consts::PI / sides as f64;
+    let angle = rotation + step * rng.gen_range(0, sides) as f64;
+    let (a, b) = (
+        (angle.cos(), angle.sin()),
+        ((angle + step).cos(), (angle + step).sin()),
+    );
+    // Uniform point in the triangle
+    let mut r1: f64 = rng.gen();
+    let mut r2: f64 = rng.gen();
+    if r1 + r2 > 1.0 {
+        r1 = 1.0 - r1;
+        r2 = 1.0 - r2;
+    }
+    (a.0 * r1 + b.0 * r2, a.1 * r1 + b.1 * r2)
+}
+
+/// Distance a ray travels in a medium of the given density before it scatters. Exponentially distributed.
+pub fn random_free_path(density: f64) -> f64 {
+    -(1.0 - rng().gen::<f64>()).ln() / density
+}
+
+/// Weight of a sample for multiple importance sampling with the power heuristic (beta = 2).
+/// `pdf` is the density of the strategy that created the sample, `other_pdf` the one of the other strategy.
+pub fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let pdf = pdf * pdf;
+    let other_pdf = other_pdf * other_pdf;
+    if pdf + other_pdf > 0.0 {
+        pdf / (pdf + other_pdf)
+    } else {
+        0.0
+    }
+}
+
+mod test {
+
+    #[test]
+    fn onb_is_orthonormal() {
+        for w in &[
+            super::Direction::new(0.0, 0.0, 1.0),
+            super::Direction::new(1.0, 0.0, 0.0),
+            super::Direction::new(1.0, -2.0, 3.0).norm(),
+        ] {
+            let onb = super::Onb::from_w(*w);
+            assert!((onb.u.length() - 1.0).abs() < 1e-12);
+            assert!((onb.v.length() - 1.0).abs() < 1e-12);
+            assert!(onb.u.dot(onb.v).abs() < 1e-12);
+            assert!(onb.u.dot(onb.w).abs() < 1e-12);
+            assert!(onb.v.dot(onb.w).abs() < 1e-12);
+            // Right handed
+            assert!((onb.u.cross(onb.v) - onb.w).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn cosine_direction_in_hemisphere() {
+        for _ in 0..1000 {
+            let (x, y, z) = super::random_cosine_direction();
+            assert!(z >= 0.0);
+            assert!(((x * x + y * y + z * z) - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn cone_direction_in_cone() {
+        for _ in 0..1000 {
+            let (x, y, z) = super::random_in_cone(0.9);
+            assert!(z >= 0.9);
+            assert!(((x * x + y * y + z * z) - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn polygon_point_in_polygon() {
+        let sides = 5;
+        let step = 2.0 * std::f64::consts::PI / sides as f64;
+        for _ in 0..1000 {
+            let (x, y) = super::random_in_polygon(sides, 0.3);
+            // Inside of all edges, which are at a distance of cos(step / 2) from the center
+            for i in 0..sides {
+                let normal = 0.3 + step * (i as f64 + 0.5);
+                assert!(x * normal.cos() + y * normal.sin() <= (step / 2.0).cos() + 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_rng_is_reproducible() {
+        use rand::Rng;
+
+        super::seed(42, 7);
+        let a: Vec<f64> = (0..10).map(|_| super::rng().gen()).collect();
+        super::seed(42, 7);
+        let b: Vec<f64> = (0..10).map(|_| super::rng().gen()).collect();
+        super::seed(42, 8);
+        let c: Vec<f64> = (0..10).map(|_| super::rng().gen()).collect();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn power_heuristic() {
+        assert_eq!(super::power_heuristic(1.0, 0.0), 1.0);
+        assert_eq!(super::power_heuristic(0.0, 1.0), 0.0);
+        assert_eq!(super::power_heuristic(2.0, 2.0), 0.5);
+    }
+}
@@ -1,85 +1,294 @@
 //! Objects used to describe a scene for the ray tracer.
 
+use crate::bvh::Bvh;
 use crate::color::Color;
-use crate::geometry::{Direction, Location};
+use crate::geometry::{Aabb, Direction, Location};
+use crate::material::Material;
+use crate::sampling::{power_heuristic, random_free_path, rng, Onb};
 use rand::prelude::*;
+use std::sync::Arc;
 
+/// Default for the maximum number of bounces
 const RAY_DEPTH_LIMIG: u32 = 50;
-const RENDER_NORMAL: bool = false;
+/// Minimal distance of a hit. Avoids that a scattered ray hits the surface it starts on.
+const MIN_HIT_DISTANCE: f64 = 0.001;
+
+/// Light that is returned for rays that do not hit any object
+#[derive(Debug, Copy, Clone)]
+pub enum Background {
+    /// Gradient from white at the horizon to light blue in the zenith
+    Sky,
+    /// The same color in every direction. Use black for closed indoor scenes.
+    Color(Color),
+}
+
+impl Background {
+    /// Returns the color of the background in the direction of the ray
+    pub fn get_color(&self, ray: &Ray) -> Color {
+        match self {
+            Background::Sky => {
+                let t = ray.direction.norm().z() / 2.0 + 0.5;
+                Color::white() * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+            }
+            Background::Color(color) => *color,
+        }
+    }
+}
+
+/// What is shown in the rendered image
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Path traced image with all lighting effects
+    Shaded,
+    /// Colors based on the direction of the normal at the first hit
+    Normal,
+}
+
+/// Medium that fills the space between the objects, like fog or haze
+#[derive(Debug, Clone)]
+pub struct Fog {
+    /// Chance to scatter per unit of distance
+    pub density: f64,
+    /// Material at the scattering points, mostly a `Volume`
+    pub material: Arc<dyn Material + Send + Sync>,
+}
 
 pub struct World {
-    objects: Vec<Box<dyn Hittable + Send + Sync>>,
+    /// Hierarchy over all objects with a bounding box
+    bvh: Bvh,
+    /// Objects without a bounding box, they are checked for every ray
+    unbounded: Vec<Arc<dyn Hittable + Send + Sync>>,
+    /// Participating media, they are checked for every ray
+    media: Vec<Arc<dyn Hittable + Send + Sync>>,
+    /// Objects that are sampled directly for the lighting. They are part of the objects as well.
+    lights: Vec<Arc<dyn Hittable + Send + Sync>>,
+    background: Background,
+    /// Maximum number of bounces of a ray
+    max_depth: u32,
+    render_mode: RenderMode,
+    fog: Option<Fog>,
 }
 
 impl World {
-    /// Create a sample world with a small and a very large sphere
-    pub fn sample_world() -> Self {
-        let mut objects: Vec<Box<dyn Hittable + Send + Sync>> = Vec::new();
-        objects.push(Box::new(objects::Sphere {
-            origin: Location::new(1.0, 0.0, 0.0),
-            radius: 0.5,
-        }));
-        objects.push(Box::new(objects::Sphere {
-            origin: Location::new(1.0, 0.0, -100.5),
-            radius: 100.0,
-        }));
-        // Create a simple and fast rng generator
-        Self { objects }
+    /// Create a world from the given objects and lights. Lights are rendered like every other object
+    /// and additionally sampled directly, which reduces the noise especially for small lights.
+    /// All objects with a bounding box are put into a bvh, media are kept apart.
+    pub fn new(
+        mut objects: Vec<Arc<dyn Hittable + Send + Sync>>,
+        lights: Vec<Arc<dyn Hittable + Send + Sync>>,
+        background: Background,
+    ) -> Self {
+        objects.extend(lights.iter().cloned());
+        let (media, objects): (Vec<_>, Vec<_>) =
+            objects.into_iter().partition(|object| object.is_medium());
+        let (bounded, unbounded) = objects
+            .into_iter()
+            .partition(|object| object.bounding_box().is_some());
+        Self {
+            bvh: Bvh::new(bounded),
+            unbounded,
+            media,
+            lights,
+            background,
+            max_depth: RAY_DEPTH_LIMIG,
+            render_mode: RenderMode::Shaded,
+            fog: None,
+        }
+    }
+
+    /// Sets the maximum number of bounces of a ray
+    pub fn set_max_depth(&mut self, max_depth: u32) {
+        self.max_depth = max_depth;
+    }
+
+    /// Sets what is shown in the rendered image
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    /// Fills the space between the objects with a medium. None removes it.
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+
+    /// Objects that are sampled directly for the lighting
+    pub fn lights(&self) -> &[Arc<dyn Hittable + Send + Sync>] {
+        &self.lights
     }
 
     /// Function returns the color of a given ray
     pub fn get_ray_color(&self, ray: Ray, depth: u32) -> Color {
+        self.trace(ray, depth, None)
+    }
+
+    /// Follows the ray recursively through the scene. `scatter_pdf` is the pdf of the material
+    /// that created the ray, None for camera rays and specular reflections.
+    fn trace(&self, ray: Ray, depth: u32, scatter_pdf: Option<f64>) -> Color {
         // If bouncing to much return black
-        if depth >= RAY_DEPTH_LIMIG {
+        if depth >= self.max_depth {
             return Color::black();
         }
 
         // Check if the ray is hitting something
-        if let (_hit_distance, Some(hit_normal)) = self.get_hit(ray) {
-            if RENDER_NORMAL {
+        if let Some(hit) = self.get_hit(ray) {
+            if self.render_mode == RenderMode::Normal {
                 // Return color based on the normal
-                let hit_normal = hit_normal.as_slice();
+                let hit_normal = hit.material.shading_normal(&hit).as_slice();
                 // Calculate color based on the normal of the hit
                 Color::new(-hit_normal[1] / 2.0 + 0.5, 0.0, 0.0)
                     + Color::new(0.0, hit_normal[2] / 2.0 + 0.5, 0.0)
                     + Color::new(0.0, 0.0, -hit_normal[0] / 2.0 + 0.5)
             } else {
-                // Do real render things
-                // The point in the scene where the ray hit the object
-                let target = ray.origin + ray.direction * _hit_distance;
-                // Calculate the new direction Based on the normal and a random unit vector
-                let new_ray = (hit_normal + random_in_unit_sphere()).norm();
-                self.get_ray_color(
-                    Ray {
-                        origin: target,
-                        direction: new_ray,
-                    },
-                    depth + 1,
-                ) * 0.5
+                // Light emitted by the object itself
+                let mut emitted = hit.material.emitted(&ray, &hit);
+                // If the hit is on a light, it could have been found by sampling the lights in the last bounce as
+                // well. Weight both strategies with multiple importance sampling.
+                if let Some(scatter_pdf) = scatter_pdf {
+                    if !emitted.is_black() {
+                        if let Some(light_pdf) = self.light_pdf(&ray, hit.distance) {
+                            emitted = emitted * power_heuristic(scatter_pdf, light_pdf);
+                        }
+                    }
+                }
+
+                if let Some(scatter) = hit.material.scatter(&ray, &hit) {
+                    // Sample the lights directly for non specular materials
+                    let direct = if scatter.pdf.is_some() {
+                        self.sample_lights(&ray, &hit)
+                    } else {
+                        Color::black()
+                    };
+                    // Let the material decide where the ray goes next
+                    emitted
+                        + direct
+                        + self.trace(scatter.ray, depth + 1, scatter.pdf) * scatter.attenuation
+                } else {
+                    // The ray got absorbed
+                    emitted
+                }
             }
         } else {
             // Did not hit an object.. Return some background color
-            let t = ray.direction.norm().z() / 2.0 + 0.5;
-            Color::white() * (1.0 - t) + Color::new(0.5, 0.7, 1.0) * t
+            self.background.get_color(&ray)
         }
     }
 
-    /// Iterate over all objects and get the one with a hit and the smales distance value
-    fn get_hit(&self, ray: Ray) -> (f64, Option<Direction>) {
-        self.objects
+    /// Pdf (per solid angle) that `sample_lights` finds the hit at the distance along the ray. None if the hit is not
+    /// on a light, then only the material can find it. Lights behind the hit are not counted, because their samples
+    /// in this direction are blocked.
+    fn light_pdf(&self, ray: &Ray, distance: f64) -> Option<f64> {
+        self.lights
             .iter()
-            .fold((f64::MAX, None), |act_hit, object| {
-                if let Some(hit) = object.get_hits(&ray) {
-                    // Check distance
-                    if hit.distance < act_hit.0 && hit.distance > 0.001 {
-                        (hit.distance, Some(hit.normal))
-                    } else {
-                        act_hit
-                    }
+            .find(|light| {
+                light
+                    .get_hits(ray, MIN_HIT_DISTANCE, f64::MAX)
+                    .is_some_and(|hit| (hit.distance - distance).abs() <= 1e-6 * distance)
+            })
+            .map(|light| light.pdf_value(ray) / self.lights.len() as f64)
+    }
+
+    /// Estimates the light arriving directly from a randomly choosen light at the hitpoint.
+    /// A shadow ray checks if the sampled point on the light is visible.
+    fn sample_lights(&self, ray: &Ray, hit: &Hit) -> Color {
+        if self.lights.is_empty() {
+            return Color::black();
+        }
+        let light = &self.lights[rng().gen_range(0, self.lights.len())];
+
+        let origin = hit.point;
+        let sample = match light.sample_direction(origin, ray.time) {
+            Some(sample) => sample,
+            None => return Color::black(),
+        };
+        let bsdf = hit.material.eval(ray, hit, sample.direction);
+        if bsdf.is_black() {
+            return Color::black();
+        }
+
+        // The first solid object hit by the shadow ray has to be the sampled point on the light.
+        // Media in between let a part of the light pass.
+        let shadow_ray = Ray {
+            origin,
+            direction: sample.direction,
+            time: ray.time,
+        };
+        match self.get_solid_hit(shadow_ray) {
+            Some(light_hit) if light_hit.distance > sample.distance * (1.0 - 1e-6) => {
+                // Only the sampled light is visible in this direction
+                let light_pdf = sample.pdf / self.lights.len() as f64;
+                if light_pdf <= 0.0 {
+                    return Color::black();
+                }
+                let transmittance = self.transmittance(&shadow_ray, light_hit.distance);
+                if transmittance <= 0.0 {
+                    return Color::black();
+                }
+                let weight =
+                    power_heuristic(light_pdf, hit.material.pdf(ray, hit, sample.direction));
+                light_hit.material.emitted(&shadow_ray, &light_hit)
+                    * bsdf
+                    * (transmittance * weight / light_pdf)
+            }
+            _ => Color::black(),
+        }
+    }
+
+    /// Iterate over all objects and get the one with a hit and the smales distance value.
+    /// Media and the fog scatter the ray at a random distance, which is a hit if it is in front of the first
+    /// solid object. Rays that do not hit any object pass the fog, so the background stays visible.
+    pub(crate) fn get_hit(&self, ray: Ray) -> Option<Hit<'_>> {
+        let solid_hit = self.get_solid_hit(ray);
+        let hit = self.media.iter().fold(solid_hit, |act_hit, medium| {
+            let max_distance = act_hit.map_or(f64::MAX, |hit: Hit| hit.distance);
+            medium
+                .get_hits(&ray, MIN_HIT_DISTANCE, max_distance)
+                .or(act_hit)
+        });
+        match &self.fog {
+            Some(fog) => {
+                let distance = MIN_HIT_DISTANCE + random_free_path(fog.density);
+                if hit.is_some_and(|hit| distance < hit.distance) {
+                    // There is no surface, the normal just faces the ray
+                    let normal = ray.direction.invert();
+                    Some(Hit::new(
+                        &ray,
+                        distance,
+                        normal,
+                        (0.0, 0.0),
+                        fog.material.as_ref(),
+                    ))
                 } else {
-                    act_hit
+                    hit
                 }
-            })
+            }
+            None => hit,
+        }
+    }
+
+    /// Nearest hit of all objects except the media and the fog
+    fn get_solid_hit(&self, ray: Ray) -> Option<Hit<'_>> {
+        let bvh_hit = self.bvh.get_hits(&ray, MIN_HIT_DISTANCE, f64::MAX);
+        self.unbounded.iter().fold(bvh_hit, |act_hit, object| {
+            let max_distance = act_hit.map_or(f64::MAX, |hit: Hit| hit.distance);
+            object
+                .get_hits(&ray, MIN_HIT_DISTANCE, max_distance)
+                .or(act_hit)
+        })
+    }
+
+    /// Fraction of the light that passes the media and the fog along the ray up to the distance
+    fn transmittance(&self, ray: &Ray, distance: f64) -> f64 {
+        let fog = match &self.fog {
+            Some(fog) => (-fog.density * (distance - MIN_HIT_DISTANCE)).exp(),
+            None => 1.0,
+        };
+        self.media.iter().fold(fog, |transmittance, medium| {
+            if transmittance > 0.0 {
+                transmittance * medium.transmittance(ray, MIN_HIT_DISTANCE, distance)
+            } else {
+                0.0
+            }
+        })
     }
 }
 
@@ -88,99 +297,236 @@ impl World {
 pub struct Ray {
     pub origin: Location,
     pub direction: Direction,
+    /// Point in time at which the ray travels, used for motion blur
+    pub time: f64,
+}
+
+impl Ray {
+    /// Returns the location on the ray at the given distance from the origin
+    pub fn at(&self, distance: f64) -> Location {
+        self.origin + self.direction * distance
+    }
 }
 
 /// Holding information about a hit of a ray and an object.
 #[derive(Debug, Copy, Clone)]
-pub struct Hit {
+pub struct Hit<'a> {
     /// Distance of the hint in direction of theray
     pub distance: f64,
-    /// Direction of the normal in the hitpoint
+    /// Location of the hit
+    pub point: Location,
+    /// Direction of the normal in the hitpoint. Points out of closed objects.
     pub normal: Direction,
+    /// True if the ray hits the side the normal points to
+    pub front_face: bool,
+    /// Direction in which u grows along the surface, perpendicular to the normal
+    pub tangent: Direction,
+    /// Texture coordinates of the hitpoint. Both are in the range 0.0 to 1.0 for most objects.
+    pub uv: (f64, f64),
+    /// World length of a step of one in u along the tangent and in v along the bitangent
+    pub uv_scale: (f64, f64),
+    /// Material of the object at the hitpoint
+    pub material: &'a dyn Material,
+}
+
+impl<'a> Hit<'a> {
+    /// Creates the hit of the ray at the given distance. The normal has to be normalized.
+    /// The tangent is an arbitrary direction perpendicular to the normal until it is set with `with_tangent`.
+    pub fn new(
+        ray: &Ray,
+        distance: f64,
+        normal: Direction,
+        uv: (f64, f64),
+        material: &'a dyn Material,
+    ) -> Self {
+        Self {
+            distance,
+            point: ray.at(distance),
+            normal,
+            front_face: ray.direction.dot(normal) < 0.0,
+            tangent: Onb::from_w(normal).u,
+            uv,
+            uv_scale: (1.0, 1.0),
+            material,
+        }
+    }
+
+    /// Sets the tangent. The part along the normal is removed, so the tangent only needs to lie roughly in the
+    /// surface. Degenerated tangents are ignored.
+    pub fn with_tangent(mut self, tangent: Direction) -> Self {
+        let tangent = tangent - self.normal * self.normal.dot(tangent);
+        let length = tangent.length();
+        if length > 1e-12 {
+            self.tangent = tangent / length;
+        }
+        self
+    }
+
+    /// Sets the world length of a step of one in u and in v. It is 1.0 for both until it is set.
+    pub fn with_uv_scale(mut self, u: f64, v: f64) -> Self {
+        self.uv_scale = (u, v);
+        self
+    }
+
+    /// Replaces the normal used for shading, e.g. by a normal map. The tangent is adjusted to stay perpendicular,
+    /// the side of the surface that was hit does not change.
+    pub fn with_shading_normal(self, normal: Direction) -> Self {
+        let tangent = self.tangent;
+        Self { normal, ..self }.with_tangent(tangent)
+    }
+
+    /// Completes the tangent and the normal to a right handed frame. v grows in this direction for most objects.
+    pub fn bitangent(&self) -> Direction {
+        self.normal.cross(self.tangent)
+    }
+
+    /// Normal on the side of the surface the ray is coming from
+    pub fn facing_normal(&self) -> Direction {
+        if self.front_face {
+            self.normal
+        } else {
+            self.normal.invert()
+        }
+    }
+
+    /// Turns the hit to the other side of the surface
+    pub fn flipped(mut self) -> Self {
+        self.normal = self.normal.invert();
+        self.front_face = !self.front_face;
+        self
+    }
+}
+
+/// Interval along a ray in which the ray is inside of a closed object
+#[derive(Debug, Copy, Clone)]
+pub struct Span<'a> {
+    /// Hit where the ray enters the object
+    pub enter: Hit<'a>,
+    /// Hit where the ray leaves the object
+    pub exit: Hit<'a>,
+}
+
+/// Direction from a point towards a randomly choosen point on an object
+#[derive(Debug, Copy, Clone)]
+pub struct LightSample {
+    /// Normalized direction towards the sampled point
+    pub direction: Direction,
+    /// Distance to the sampled point
+    pub distance: f64,
+    /// Pdf of the direction per solid angle
+    pub pdf: f64,
 }
 
 /// All Objects that interact in some way with a ray must implment this Trait
 pub trait Hittable {
-    fn get_hits(&self, ray: &Ray) -> Option<Hit>;
-}
+    /// Returns the nearest hit of the ray with a distance between `min_distance` and `max_distance`
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>>;
 
-pub mod objects {
-    use super::{Hit, Hittable, Ray};
-    use crate::geometry::*;
-
-    pub struct Sphere {
-        pub origin: Location,
-        pub radius: f64,
-    }
-
-    impl Hittable for Sphere {
-        /// Checks if a ray hits the sphere
-        fn get_hits(&self, ray: &Ray) -> Option<Hit> {
-            // Algorithm from https://en.wikipedia.org/wiki/Line%E2%80%93sphere_intersection with the sphere transformt to origin
-
-            // Get stuff together
-            let ray_orig = ray.origin - self.origin;
-            let ray_dir = ray.direction;
-            let r = self.radius;
-
-            // Calculate wether it is a hit or not
-            let d_3 = -ray_dir.dot(ray_orig);
-            let d_1 = d_3.powi(2);
-            let d_2 = ray_orig.length().powi(2) - r.powi(2);
-            let discriminant = d_1 - d_2;
-
-            if discriminant > 0.0 {
-                let discriminant_sqrt = discriminant.sqrt();
-                // Two Hits --> Return the one with the lowest positive Vlaue
-                // Calculate the hits
-                // Safe to call sqrt because we checked the value under the root
-                let one_div_radius = 1.0 / self.radius;
-                let distance = d_3 - discriminant_sqrt;
-                let normal = (ray_dir * distance + ray_orig) * one_div_radius;
-                let hit_1 = Hit { distance, normal };
-                let distance = d_3 + discriminant_sqrt;
-                let normal = (ray_dir * distance + ray_orig) * one_div_radius;
-                let hit_2 = Hit { distance, normal };
-
-                match (hit_1.distance, hit_2.distance) {
-                    (d1, d2) if d1 > 0.0 && d2 <= 0.0 => Some(hit_1),
-                    (d1, d2) if d2 > 0.0 && d1 <= 0.0 => Some(hit_2),
-                    (d1, d2) if d1 <= 0.0 && d2 <= 0.0 => None,
-                    (d1, d2) if d1 > 0.0 && d2 > 0.0 && d1 > d2 => Some(hit_2),
-                    (d1, d2) if d1 > 0.0 && d2 > 0.0 && d1 < d2 => Some(hit_1),
-                    (d1, d2) if d1 > 0.0 && d2 > 0.0 => Some(hit_1), // Both are Equeal and greater zero so the two above do not match. Return simply hit1
-                    (_, _) => {
-                        dbg!(hit_1);
-                        dbg!(hit_2);
-                        panic!("Unable to dertermen Order of hit_1 and hit_2 in sphere")
-                    }
-                }
-            } else if discriminant < -0.0 {
-                // No Hits
-                None
-            } else {
-                // Just one hit
-                let distance = d_3;
-                if distance > 0.0 {
-                    let normal = (ray_dir * distance + ray_orig) / self.radius;
-                    Some(Hit { distance, normal })
-                } else {
-                    None
-                }
-            }
-        }
+    /// Returns all intervals in which the line of the ray is inside of the object, sorted by distance.
+    /// The distances can be negative. None for objects that are not closed, they can not be used for CSG.
+    fn get_spans(&self, _ray: &Ray) -> Option<Vec<Span<'_>>> {
+        None
+    }
+
+    /// Samples a direction from `origin` towards the object at the given time. Needed to use the object as a light.
+    /// Returns None if the object can not be sampled from this point.
+    fn sample_direction(&self, _origin: Location, _time: f64) -> Option<LightSample> {
+        None
+    }
+
+    /// Pdf (per solid angle) that `sample_direction` returns the direction of the ray
+    fn pdf_value(&self, _ray: &Ray) -> f64 {
+        0.0
+    }
+
+    /// True for participating media like smoke. The light passes them partly, shadow rays use `transmittance`
+    /// instead of the hits.
+    fn is_medium(&self) -> bool {
+        false
+    }
+
+    /// Estimates the fraction of the light that passes the medium between the distances without being
+    /// scattered or absorbed. Only used for media.
+    fn transmittance(&self, _ray: &Ray, _min_distance: f64, _max_distance: f64) -> f64 {
+        1.0
     }
+
+    /// Axis aligned box that contains the whole object. None for infinite objects.
+    fn bounding_box(&self) -> Option<Aabb>;
 }
 
-/// Some helper function
-fn random_in_unit_sphere() -> Direction {
-    let mut rng = thread_rng();
-    // Create a bad and ugly random generator
-    let offset_dir = Direction::new(1.0, 1.0, 1.0);
-    loop {
-        let rnd_dir = Direction::new(rng.gen(), rng.gen(), rng.gen()) * 2.0 - offset_dir;
-        if rnd_dir.length() <= 1.0 {
-            return rnd_dir;
-        }
+pub mod graph;
+pub mod objects;
+
+mod test {
+
+    #[test]
+    fn background_on_miss() {
+        use super::{Background, Ray, World};
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::DiffuseLight;
+        use crate::scene::objects::Sphere;
+        use std::sync::Arc;
+
+        let background = Color::new(0.1, 0.2, 0.3);
+        let light = Arc::new(Sphere {
+            origin: Location::new(5.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(DiffuseLight::new(Color::white(), 2.0)),
+        });
+        let world = World::new(vec![light], Vec::new(), Background::Color(background));
+        let ray = |direction| Ray {
+            origin: Location::new(0.0, 0.0, 0.0),
+            direction,
+            time: 0.0,
+        };
+
+        // Rays that miss get the background, rays that hit the light its emission
+        assert_eq!(
+            world.get_ray_color(ray(Direction::new(0.0, 0.0, 1.0)), 0),
+            background
+        );
+        assert_eq!(
+            world.get_ray_color(ray(Direction::new(1.0, 0.0, 0.0)), 0),
+            Color::new(2.0, 2.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn light_pdf_of_hit_light() {
+        use super::{Background, Ray, World};
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::DiffuseLight;
+        use crate::scene::objects::Sphere;
+        use crate::scene::Hittable;
+        use std::sync::Arc;
+
+        let light = |x: f64| {
+            Arc::new(Sphere {
+                origin: Location::new(x, 0.0, 0.0),
+                radius: 1.0,
+                material: Arc::new(DiffuseLight::new(Color::white(), 1.0)),
+            })
+        };
+        let (front, back) = (light(5.0), light(10.0));
+        let world = World::new(
+            Vec::new(),
+            vec![front.clone(), back.clone()],
+            Background::Color(Color::black()),
+        );
+        let ray = Ray {
+            origin: Location::origin(),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+
+        // Only the light in front counts, the samples of the one behind it are blocked
+        let pdf = world.light_pdf(&ray, 4.0).unwrap();
+        assert!((pdf - front.pdf_value(&ray) / 2.0).abs() < 1e-12);
+        assert!(back.pdf_value(&ray) > 0.0);
+        // Hits that are not on a light can not be found by sampling the lights
+        assert!(world.light_pdf(&ray, 3.0).is_none());
     }
 }
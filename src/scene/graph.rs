@@ -0,0 +1,292 @@
+//! Hierarchy of named groups. Each group places its objects and child groups with a transformation
+//! relative to its parent, can replace their materials and can be hidden.
+//! The hierarchy is flattened to a list of objects before rendering.
+
+use crate::geometry::{Motion, Transform};
+use crate::material::Material;
+use crate::scene::objects::Instance;
+use crate::scene::Hittable;
+use std::sync::Arc;
+
+/// Group of objects and other groups
+pub struct SceneNode {
+    pub name: String,
+    /// Transformation from the group to its parent
+    pub transform: Transform,
+    /// Replaces the transformation with one that changes over time, for motion blur
+    pub motion: Option<Motion>,
+    /// Replaces the materials of all objects in the group, unless a child group sets its own material.
+    /// Lights and media keep their materials.
+    pub material: Option<Arc<dyn Material + Send + Sync>>,
+    /// Hidden groups are not rendered, including their children
+    pub visible: bool,
+    pub objects: Vec<Arc<dyn Hittable + Send + Sync>>,
+    /// Objects that are sampled directly as lights, placed like the other objects
+    pub lights: Vec<Arc<dyn Hittable + Send + Sync>>,
+    pub children: Vec<SceneNode>,
+}
+
+impl SceneNode {
+    /// Creates an empty visible group without transformation
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            transform: Transform::identity(),
+            motion: None,
+            material: None,
+            visible: true,
+            objects: vec![],
+            lights: vec![],
+            children: vec![],
+        }
+    }
+
+    /// Sets the transformation from the group to its parent
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    /// Replaces the materials of the objects in the group
+    pub fn with_material(mut self, material: Arc<dyn Material + Send + Sync>) -> Self {
+        self.material = Some(material);
+        self
+    }
+
+    /// Adds an object to the group
+    pub fn with_object(mut self, object: Arc<dyn Hittable + Send + Sync>) -> Self {
+        self.objects.push(object);
+        self
+    }
+
+    /// Adds a child group
+    pub fn with_child(mut self, child: SceneNode) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Finds a group below this one by the names of the groups on the way separated by `/`, for example `car/wheel`
+    pub fn find(&self, path: &str) -> Option<&SceneNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| {
+                node.children.iter().find(|child| child.name == name)
+            })
+    }
+
+    /// Finds a group like `find` to change it
+    pub fn find_mut(&mut self, path: &str) -> Option<&mut SceneNode> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self, |node, name| {
+                node.children.iter_mut().find(|child| child.name == name)
+            })
+    }
+
+    /// Returns all visible objects placed in the world. Objects of transformed groups or groups with
+    /// a material are wrapped in an instance, the objects itself are shared.
+    pub fn flatten(&self) -> Vec<Arc<dyn Hittable + Send + Sync>> {
+        let mut objects = vec![];
+        self.flatten_into(
+            Transform::identity(),
+            None,
+            &|node| &node.objects,
+            true,
+            &mut objects,
+        );
+        objects
+    }
+
+    /// Returns all visible lights placed in the world like `flatten`
+    pub fn flatten_lights(&self) -> Vec<Arc<dyn Hittable + Send + Sync>> {
+        let mut lights = vec![];
+        self.flatten_into(
+            Transform::identity(),
+            None,
+            &|node| &node.lights,
+            false,
+            &mut lights,
+        );
+        lights
+    }
+
+    /// Places the objects selected by `select` of this group and its children.
+    /// `group_materials` is false for objects that keep their materials in any group.
+    fn flatten_into(
+        &self,
+        parent_transform: Transform,
+        parent_material: Option<&Arc<dyn Material + Send + Sync>>,
+        select: &dyn Fn(&SceneNode) -> &Vec<Arc<dyn Hittable + Send + Sync>>,
+        group_materials: bool,
+        objects: &mut Vec<Arc<dyn Hittable + Send + Sync>>,
+    ) {
+        if !self.visible {
+            return;
+        }
+        let material = self.material.as_ref().or(parent_material);
+        match &self.motion {
+            None => self.place(
+                parent_transform * self.transform,
+                material,
+                select,
+                group_materials,
+                objects,
+            ),
+            Some(motion) => {
+                // Place the content relative to the group, then move all of it with the group
+                let mut content = vec![];
+                self.place(
+                    Transform::identity(),
+                    material,
+                    select,
+                    group_materials,
+                    &mut content,
+                );
+                objects.extend(content.into_iter().map(
+                    |object| -> Arc<dyn Hittable + Send + Sync> {
+                        Arc::new(Instance::new(object, parent_transform).with_motion(*motion))
+                    },
+                ));
+            }
+        }
+    }
+
+    /// Places the selected objects of this group and its children with the transformation of the group
+    fn place(
+        &self,
+        transform: Transform,
+        material: Option<&Arc<dyn Material + Send + Sync>>,
+        select: &dyn Fn(&SceneNode) -> &Vec<Arc<dyn Hittable + Send + Sync>>,
+        group_materials: bool,
+        objects: &mut Vec<Arc<dyn Hittable + Send + Sync>>,
+    ) {
+        for object in select(self) {
+            // Media scatter with the phase function of their own material
+            let material = material.filter(|_| group_materials && !object.is_medium());
+            if transform == Transform::identity() && material.is_none() {
+                objects.push(object.clone());
+            } else {
+                objects.push(Arc::new(
+                    Instance::new(object.clone(), transform).with_material(material.cloned()),
+                ));
+            }
+        }
+        for child in &self.children {
+            child.flatten_into(transform, material, select, group_materials, objects);
+        }
+    }
+}
+
+mod test {
+
+    #[test]
+    fn flatten_nested_groups() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location, Transform};
+        use crate::material::{Lambertian, Material, Metal};
+        use crate::scene::objects::Sphere;
+        use crate::scene::Ray;
+        use std::sync::Arc;
+
+        let red: Arc<dyn Material + Send + Sync> =
+            Arc::new(Lambertian::new(Color::new(1.0, 0.0, 0.0)));
+        let metal: Arc<dyn Material + Send + Sync> = Arc::new(Metal::new(Color::white(), 0.0));
+        let wheel = Arc::new(Sphere {
+            origin: Location::origin(),
+            radius: 0.5,
+            material: red.clone(),
+        });
+
+        let car = super::SceneNode::new("car")
+            .with_transform(Transform::translation(Direction::new(10.0, 0.0, 0.0)))
+            .with_child(
+                super::SceneNode::new("front")
+                    .with_transform(Transform::translation(Direction::new(0.0, 2.0, 0.0)))
+                    .with_material(metal.clone())
+                    .with_object(wheel.clone()),
+            )
+            .with_child(
+                super::SceneNode::new("back")
+                    .with_transform(Transform::translation(Direction::new(0.0, -2.0, 0.0)))
+                    .with_object(wheel),
+            );
+        let root = super::SceneNode::new("root").with_child(car);
+        assert!(root.find("car/front").is_some());
+        assert!(root.find("car/middle").is_none());
+
+        // Compares the materials by address, equal fields are not enough
+        let same = |a: &dyn Material, b: &dyn Material| {
+            std::ptr::eq(
+                a as *const dyn Material as *const u8,
+                b as *const dyn Material as *const u8,
+            )
+        };
+        let objects = root.flatten();
+        assert_eq!(objects.len(), 2);
+        let ray = |y: f64| Ray {
+            origin: Location::new(0.0, y, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        // The front wheel has the material of its group
+        let hit = objects[0].get_hits(&ray(2.0), 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 9.5).abs() < 1e-9);
+        assert!(same(hit.material, metal.as_ref()));
+        let hit = objects[1].get_hits(&ray(-2.0), 0.001, f64::MAX).unwrap();
+        assert!(same(hit.material, red.as_ref()));
+
+        // Hidden groups are skipped
+        let mut garage =
+            super::SceneNode::new("garage").with_child(root.children.into_iter().next().unwrap());
+        garage.find_mut("car/front").unwrap().visible = false;
+        assert_eq!(garage.flatten().len(), 1);
+    }
+
+    #[test]
+    fn group_material_keeps_lights_and_media() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location, Transform};
+        use crate::material::{DiffuseLight, Lambertian, Material, PhaseFunction, Volume};
+        use crate::scene::objects::{ConstantMedium, Sphere};
+        use crate::scene::Ray;
+        use std::sync::Arc;
+
+        let same = |a: &dyn Material, b: &dyn Material| {
+            std::ptr::eq(
+                a as *const dyn Material as *const u8,
+                b as *const dyn Material as *const u8,
+            )
+        };
+        let red: Arc<dyn Material + Send + Sync> =
+            Arc::new(Lambertian::new(Color::new(1.0, 0.0, 0.0)));
+        let light: Arc<dyn Material + Send + Sync> =
+            Arc::new(DiffuseLight::new(Color::white(), 1.0));
+        let smoke: Arc<dyn Material + Send + Sync> =
+            Arc::new(Volume::new(Color::white(), PhaseFunction::Isotropic));
+        let sphere = |y: f64, material: &Arc<dyn Material + Send + Sync>| {
+            Arc::new(Sphere {
+                origin: Location::new(0.0, y, 0.0),
+                radius: 0.5,
+                material: material.clone(),
+            })
+        };
+        let mut group = super::SceneNode::new("group")
+            .with_transform(Transform::translation(Direction::new(10.0, 0.0, 0.0)))
+            .with_material(red.clone())
+            .with_object(Arc::new(ConstantMedium::new(sphere(2.0, &smoke), 1e6)));
+        group.lights.push(sphere(-2.0, &light));
+        let root = super::SceneNode::new("root").with_child(group);
+
+        let ray = |y: f64| Ray {
+            origin: Location::new(0.0, y, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let objects = root.flatten();
+        let hit = objects[0].get_hits(&ray(2.0), 0.001, f64::MAX).unwrap();
+        assert!(same(hit.material, smoke.as_ref()));
+        let lights = root.flatten_lights();
+        let hit = lights[0].get_hits(&ray(-2.0), 0.001, f64::MAX).unwrap();
+        assert!(same(hit.material, light.as_ref()));
+    }
+}
@@ -0,0 +1,121 @@
+//! All objects that can be placed in the world.
+
+mod csg;
+mod cuboid;
+mod cylinder;
+mod instance;
+mod medium;
+mod mesh;
+mod plane;
+mod sdf;
+mod sphere;
+mod torus;
+
+pub use csg::{Csg, CsgOperation};
+pub use cuboid::Cuboid;
+pub use cylinder::{Cone, Cylinder};
+pub use instance::Instance;
+pub use medium::{ConstantMedium, GridMedium};
+pub use mesh::{Triangle, TriangleMesh, VertexBuffer};
+pub use plane::{Disk, Plane, Quad};
+pub use sdf::{
+    smooth_min, DistanceField, Mandelbulb, SdfBlend, SdfBox, SdfCapsule, SdfFunction, SdfObject,
+    SdfSphere, SdfTorus,
+};
+pub use sphere::{MovingSphere, Sphere};
+pub use torus::Torus;
+
+use crate::geometry::{Aabb, Direction, Location};
+use crate::sampling::Onb;
+use crate::scene::{Hit, LightSample, Ray, Span, MIN_HIT_DISTANCE};
+
+/// Span from the nearest to the farthest hit of a convex object. None if there are no hits.
+fn convex_span<'a>(hits: impl IntoIterator<Item = Hit<'a>>) -> Option<Span<'a>> {
+    hits.into_iter().fold(None, |span, hit| match span {
+        None => Some(Span {
+            enter: hit,
+            exit: hit,
+        }),
+        Some(Span { enter, exit }) => Some(Span {
+            enter: if hit.distance < enter.distance {
+                hit
+            } else {
+                enter
+            },
+            exit: if hit.distance > exit.distance {
+                hit
+            } else {
+                exit
+            },
+        }),
+    })
+}
+
+/// Nearest entry or exit of the spans between `min_distance` and `max_distance`
+fn nearest_hit<'a>(spans: &[Span<'a>], min_distance: f64, max_distance: f64) -> Option<Hit<'a>> {
+    spans
+        .iter()
+        .flat_map(|span| std::iter::once(span.enter).chain(std::iter::once(span.exit)))
+        .find(|hit| hit.distance > min_distance && hit.distance < max_distance)
+}
+
+/// Origin and direction of the ray in the coordinates of the basis placed at `origin`
+fn local_ray(onb: &Onb, origin: Location, ray: &Ray) -> (Direction, Direction) {
+    let to_local = |v: Direction| Direction::new(v.dot(onb.u), v.dot(onb.v), v.dot(onb.w));
+    (to_local(ray.origin - origin), to_local(ray.direction))
+}
+
+/// Bounding box of a disk
+fn disk_bounds(center: Location, normal: Direction, radius: f64) -> Aabb {
+    let extent = |n: f64| radius * (1.0 - n * n).max(0.0).sqrt();
+    let extent = Direction::new(extent(normal.x()), extent(normal.y()), extent(normal.z()));
+    Aabb::new(center - extent, center + extent)
+}
+
+/// Angle around the z axis mapped to the range 0.0 to 1.0
+fn azimuth(x: f64, y: f64) -> f64 {
+    y.atan2(x) / (2.0 * std::f64::consts::PI) + 0.5
+}
+
+/// Light sample towards a uniformly choosen point on a flat object with the given normal and area
+fn area_sample(
+    origin: Location,
+    point: Location,
+    normal: Direction,
+    area: f64,
+) -> Option<LightSample> {
+    let offset = point - origin;
+    let distance = offset.length();
+    if distance <= MIN_HIT_DISTANCE {
+        return None;
+    }
+    let direction = offset / distance;
+    let pdf = area_pdf(distance, direction.dot(normal), area);
+    if pdf > 0.0 {
+        Some(LightSample {
+            direction,
+            distance,
+            pdf,
+        })
+    } else {
+        None
+    }
+}
+
+/// Pdf per solid angle of a uniformly choosen point on a flat object, seen at the distance under an angle with
+/// the given cosine to the normal
+fn area_pdf(distance: f64, cosine: f64, area: f64) -> f64 {
+    let cosine = cosine.abs();
+    if cosine < 1e-12 || area <= 0.0 {
+        0.0
+    } else {
+        distance * distance / (cosine * area)
+    }
+}
+
+/// Pdf of `area_sample` for the direction of the ray, which is zero if the ray misses the object
+fn hit_area_pdf(ray: &Ray, hit: Option<Hit>, area: f64) -> f64 {
+    hit.map_or(0.0, |hit| {
+        area_pdf(hit.distance, ray.direction.dot(hit.normal), area)
+    })
+}
@@ -0,0 +1,171 @@
+//! Constructive solid geometry combines closed objects with boolean operations.
+
+use crate::geometry::Aabb;
+use crate::scene::{Hit, Hittable, Ray, Span};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Boolean operation of a CSG node
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsgOperation {
+    /// Everything inside of at least one object
+    Union,
+    /// Everything inside of both objects
+    Intersection,
+    /// Everything inside of the left but not inside of the right object
+    Difference,
+}
+
+impl CsgOperation {
+    /// Checks if a point is inside of the result
+    fn contains(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Combination of two closed objects. Both objects have to return their spans, other objects are never hit.
+/// Nodes can be nested to build more complex shapes.
+pub struct Csg {
+    operation: CsgOperation,
+    left: Arc<dyn Hittable + Send + Sync>,
+    right: Arc<dyn Hittable + Send + Sync>,
+}
+
+impl Csg {
+    pub fn new(
+        operation: CsgOperation,
+        left: Arc<dyn Hittable + Send + Sync>,
+        right: Arc<dyn Hittable + Send + Sync>,
+    ) -> Self {
+        Self {
+            operation,
+            left,
+            right,
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        super::nearest_hit(&self.get_spans(ray)?, min_distance, max_distance)
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        // Surfaces of both objects along the ray as (hit, is part of the left object, ray enters the object)
+        let mut surfaces = Vec::new();
+        for (spans, is_left) in &[
+            (self.left.get_spans(ray)?, true),
+            (self.right.get_spans(ray)?, false),
+        ] {
+            for span in spans {
+                surfaces.push((span.enter, *is_left, true));
+                surfaces.push((span.exit, *is_left, false));
+            }
+        }
+        surfaces.sort_by(|a, b| a.0.distance.total_cmp(&b.0.distance));
+
+        // Walk along the ray and track in which objects it is
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut enter = None;
+        let mut spans = Vec::new();
+        for (mut hit, is_left, entering) in surfaces {
+            if is_left {
+                in_left = entering;
+            } else {
+                in_right = entering;
+                if self.operation == CsgOperation::Difference {
+                    // The surface of the subtracted object faces inwards
+                    hit = hit.flipped();
+                }
+            }
+            match (enter, self.operation.contains(in_left, in_right)) {
+                (None, true) => enter = Some(hit),
+                (Some(enter_hit), false) => {
+                    spans.push(Span {
+                        enter: enter_hit,
+                        exit: hit,
+                    });
+                    enter = None;
+                }
+                _ => (),
+            }
+        }
+        Some(spans)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let left = self.left.bounding_box();
+        let right = self.right.bounding_box();
+        match self.operation {
+            CsgOperation::Union => Some(left?.union(&right?)),
+            CsgOperation::Intersection => match (left, right) {
+                (Some(left), Some(right)) => left.intersection(&right).or(Some(left)),
+                (left, right) => left.or(right),
+            },
+            CsgOperation::Difference => left,
+        }
+    }
+}
+
+mod test {
+
+    #[test]
+    fn boolean_operations() {
+        use super::{Csg, CsgOperation};
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::objects::Sphere;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let material = Arc::new(Lambertian::new(Color::white()));
+        // Two overlapping spheres on the x axis, from 4 to 6 and from 5 to 7
+        let a = Arc::new(Sphere {
+            origin: Location::new(5.0, 0.0, 0.0),
+            radius: 1.0,
+            material: material.clone(),
+        });
+        let b = Arc::new(Sphere {
+            origin: Location::new(6.0, 0.0, 0.0),
+            radius: 1.0,
+            material,
+        });
+        let ray = Ray {
+            origin: Location::origin(),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let spans = |operation: CsgOperation| {
+            let csg = Csg::new(operation, a.clone(), b.clone());
+            csg.get_spans(&ray)
+                .unwrap()
+                .iter()
+                .map(|span| (span.enter.distance, span.exit.distance))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(spans(CsgOperation::Union), vec![(4.0, 7.0)]);
+        assert_eq!(spans(CsgOperation::Intersection), vec![(5.0, 6.0)]);
+        assert_eq!(spans(CsgOperation::Difference), vec![(4.0, 5.0)]);
+
+        // The exit of the difference is the entry of the subtracted sphere, facing towards the ray
+        let csg = Csg::new(CsgOperation::Difference, a.clone(), b.clone());
+        let hit = csg.get_hits(&ray, 4.5, f64::MAX).unwrap();
+        assert_eq!(hit.distance, 5.0);
+        assert!((hit.normal - Direction::new(1.0, 0.0, 0.0)).length() < 1e-12);
+        // Nested nodes
+        let nested = Csg::new(CsgOperation::Difference, Arc::new(csg), b.clone());
+        assert_eq!(nested.get_spans(&ray).unwrap().len(), 1);
+        let bounds = Csg::new(CsgOperation::Intersection, a, b)
+            .bounding_box()
+            .unwrap();
+        assert_eq!(bounds.min.x(), 5.0);
+        assert_eq!(bounds.max.x(), 6.0);
+    }
+}
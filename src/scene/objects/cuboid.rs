@@ -0,0 +1,142 @@
+//! Axis aligned boxes.
+
+use super::convex_span;
+use crate::geometry::*;
+use crate::material::Material;
+use crate::scene::{Hit, Hittable, Ray, Span};
+use std::sync::Arc;
+
+/// Axis aligned box between the corners `min` and `max`. The texture coordinates of each face go from
+/// the smaller to the larger coordinates of the other two axis.
+pub struct Cuboid {
+    pub min: Location,
+    pub max: Location,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Cuboid {
+    /// Hit on the face perpendicular to `axis`. `sign` selects the side of the face normal.
+    fn hit(&self, ray: &Ray, distance: f64, axis: usize, sign: f64) -> Hit<'_> {
+        let mut normal = [0.0; 3];
+        normal[axis] = sign;
+        let point = ray.at(distance).as_slice();
+        let (min, max) = (self.min.as_slice(), self.max.as_slice());
+        let size = |a: usize| max[(axis + a) % 3] - min[(axis + a) % 3];
+        let coordinate = |a: usize| {
+            let a = (axis + a) % 3;
+            ((point[a] - min[a]) / (max[a] - min[a])).clamp(0.0, 1.0)
+        };
+        let mut tangent = [0.0; 3];
+        tangent[(axis + 1) % 3] = 1.0;
+        Hit::new(
+            ray,
+            distance,
+            Direction::new(normal[0], normal[1], normal[2]),
+            (coordinate(1), coordinate(2)),
+            self.material.as_ref(),
+        )
+        .with_tangent(Direction::new(tangent[0], tangent[1], tangent[2]))
+        .with_uv_scale(size(1), size(2))
+    }
+
+    /// Interval in which the line of the ray is inside of the box, using the slab method
+    fn span(&self, ray: &Ray) -> Option<Span<'_>> {
+        let origin = ray.origin.as_slice();
+        let direction = ray.direction.as_slice();
+        let (min, max) = (self.min.as_slice(), self.max.as_slice());
+
+        // Nearest exit and farthest entry with the axis of the face
+        let mut enter = (f64::NEG_INFINITY, 0, 0.0);
+        let mut exit = (f64::INFINITY, 0, 0.0);
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                // Parallel to the slab
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t_min = (min[axis] - origin[axis]) / direction[axis];
+            let t_max = (max[axis] - origin[axis]) / direction[axis];
+            // The ray enters through the face at min if it moves in positive direction
+            let (near, far, sign) = if t_min < t_max {
+                (t_min, t_max, -1.0)
+            } else {
+                (t_max, t_min, 1.0)
+            };
+            if near > enter.0 {
+                enter = (near, axis, sign);
+            }
+            if far < exit.0 {
+                exit = (far, axis, -sign);
+            }
+        }
+        if enter.0 > exit.0 {
+            return None;
+        }
+        convex_span(vec![
+            self.hit(ray, enter.0, enter.1, enter.2),
+            self.hit(ray, exit.0, exit.1, exit.2),
+        ])
+    }
+}
+
+impl Hittable for Cuboid {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        super::nearest_hit(&[self.span(ray)?], min_distance, max_distance)
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        Some(self.span(ray).into_iter().collect())
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+}
+
+mod test {
+
+    #[test]
+    fn box_faces() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let cuboid = super::Cuboid {
+            min: Location::new(2.0, -1.0, -1.0),
+            max: Location::new(4.0, 1.0, 3.0),
+            material: Arc::new(Lambertian::new(Color::white())),
+        };
+        let ray = Ray {
+            origin: Location::new(0.0, 0.0, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let spans = cuboid.get_spans(&ray).unwrap();
+        assert_eq!(spans[0].enter.distance, 2.0);
+        assert_eq!(spans[0].exit.distance, 4.0);
+        assert_eq!(spans[0].enter.normal.x(), -1.0);
+        assert_eq!(spans[0].exit.normal.x(), 1.0);
+        assert_eq!(spans[0].enter.uv, (0.5, 0.25));
+
+        // From inside the box the exit is hit
+        let inside = Ray {
+            origin: Location::new(3.0, 0.0, 0.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let hit = cuboid.get_hits(&inside, 0.001, f64::MAX).unwrap();
+        assert_eq!(hit.distance, 1.0);
+        assert_eq!(hit.normal.z(), -1.0);
+
+        let miss = Ray {
+            origin: Location::new(0.0, 2.0, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert!(cuboid.get_spans(&miss).unwrap().is_empty());
+    }
+}
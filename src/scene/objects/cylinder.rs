@@ -0,0 +1,286 @@
+//! Capped cylinders and cones around an arbitrary axis.
+
+use super::{azimuth, convex_span, disk_bounds, local_ray, nearest_hit};
+use crate::geometry::*;
+use crate::material::Material;
+use crate::sampling::Onb;
+use crate::scene::{Hit, Hittable, Ray, Span};
+use std::sync::Arc;
+
+/// Real solutions of a t² + b t + c = 0
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 {
+        return if b.abs() < 1e-12 {
+            vec![]
+        } else {
+            vec![-c / b]
+        };
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return vec![];
+    }
+    let root = discriminant.sqrt();
+    vec![(-b - root) / (2.0 * a), (-b + root) / (2.0 * a)]
+}
+
+/// Hits of the line of the ray with the caps of a shape along the local z axis.
+/// `caps` are the height and the radius of each cap, the normal of the cap at z = 0 points down.
+/// Each hit has the distance, the normal, the texture coordinates and the length along v.
+fn cap_hits(
+    origin: Direction,
+    direction: Direction,
+    caps: &[(f64, f64)],
+) -> Vec<(f64, Direction, (f64, f64), f64)> {
+    if direction.z().abs() < 1e-12 {
+        return vec![];
+    }
+    caps.iter()
+        .filter_map(|&(height, radius)| {
+            let t = (height - origin.z()) / direction.z();
+            let point = origin + direction * t;
+            let distance = point.x().hypot(point.y());
+            if distance > radius {
+                return None;
+            }
+            let normal = if height > 0.0 { 1.0 } else { -1.0 };
+            Some((
+                t,
+                Direction::new(0.0, 0.0, normal),
+                (azimuth(point.x(), point.y()), distance / radius),
+                radius,
+            ))
+        })
+        .collect()
+}
+
+/// Length of the circle around the axis through the point in local coordinates, along which u goes from 0 to 1
+fn around_axis(point: Direction) -> f64 {
+    2.0 * std::f64::consts::PI * point.x().hypot(point.y())
+}
+
+/// Transforms a hit in local coordinates to the world. The tangent goes around the axis.
+fn local_hit<'a>(
+    ray: &Ray,
+    onb: &Onb,
+    point: Direction,
+    distance: f64,
+    normal: Direction,
+    uv: (f64, f64),
+    material: &'a dyn Material,
+) -> Hit<'a> {
+    let normal = onb.local(normal.x(), normal.y(), normal.z());
+    Hit::new(ray, distance, normal, uv, material).with_tangent(onb.local(
+        -point.y(),
+        point.x(),
+        0.0,
+    ))
+}
+
+/// Cylinder from the center of the bottom cap `base` to the center of the top cap `top`.
+/// On the side u goes around the axis and v from the bottom to the top.
+/// On the caps v is the distance to the axis relative to the radius.
+pub struct Cylinder {
+    pub base: Location,
+    pub top: Location,
+    pub radius: f64,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Cylinder {
+    fn span(&self, ray: &Ray) -> Option<Span<'_>> {
+        let axis = self.top - self.base;
+        let height = axis.length();
+        let onb = Onb::from_w(axis / height);
+        let (o, d) = local_ray(&onb, self.base, ray);
+
+        // Side of the infinite cylinder, limited to the height
+        let side = solve_quadratic(
+            d.x() * d.x() + d.y() * d.y(),
+            2.0 * (o.x() * d.x() + o.y() * d.y()),
+            o.x() * o.x() + o.y() * o.y() - self.radius * self.radius,
+        )
+        .into_iter()
+        .filter_map(|t| {
+            let point = o + d * t;
+            if point.z() < 0.0 || point.z() > height {
+                return None;
+            }
+            Some((
+                t,
+                Direction::new(point.x(), point.y(), 0.0) / self.radius,
+                (azimuth(point.x(), point.y()), point.z() / height),
+                height,
+            ))
+        });
+        let caps = cap_hits(o, d, &[(0.0, self.radius), (height, self.radius)]);
+
+        convex_span(side.chain(caps).map(|(distance, normal, uv, v_scale)| {
+            local_hit(
+                ray,
+                &onb,
+                o + d * distance,
+                distance,
+                normal,
+                uv,
+                self.material.as_ref(),
+            )
+            .with_uv_scale(around_axis(o + d * distance), v_scale)
+        }))
+    }
+}
+
+impl Hittable for Cylinder {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        nearest_hit(&[self.span(ray)?], min_distance, max_distance)
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        Some(self.span(ray).into_iter().collect())
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let axis = (self.top - self.base).norm();
+        Some(
+            disk_bounds(self.base, axis, self.radius).union(&disk_bounds(
+                self.top,
+                axis,
+                self.radius,
+            )),
+        )
+    }
+}
+
+/// Cone with a round cap of `radius` around `base` and the tip at `apex`.
+/// The texture coordinates are the same as for a cylinder.
+pub struct Cone {
+    pub base: Location,
+    pub apex: Location,
+    pub radius: f64,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Cone {
+    fn span(&self, ray: &Ray) -> Option<Span<'_>> {
+        let axis = self.apex - self.base;
+        let height = axis.length();
+        let onb = Onb::from_w(axis / height);
+        let (o, d) = local_ray(&onb, self.base, ray);
+
+        // Side of the double cone x² + y² = (k (h - z))², limited to the part below the apex
+        let k = self.radius / height;
+        let k2 = k * k;
+        let h = height - o.z();
+        let side = solve_quadratic(
+            d.x() * d.x() + d.y() * d.y() - k2 * d.z() * d.z(),
+            2.0 * (o.x() * d.x() + o.y() * d.y() + k2 * h * d.z()),
+            o.x() * o.x() + o.y() * o.y() - k2 * h * h,
+        )
+        .into_iter()
+        .filter_map(|t| {
+            let point = o + d * t;
+            if point.z() < 0.0 || point.z() > height {
+                return None;
+            }
+            // Gradient of the implicit surface
+            let normal = Direction::new(point.x(), point.y(), k2 * (height - point.z()));
+            let normal = if normal.length() > 0.0 {
+                normal.norm()
+            } else {
+                Direction::new(0.0, 0.0, 1.0)
+            };
+            Some((
+                t,
+                normal,
+                (azimuth(point.x(), point.y()), point.z() / height),
+                height.hypot(self.radius),
+            ))
+        });
+        let caps = cap_hits(o, d, &[(0.0, self.radius)]);
+
+        convex_span(side.chain(caps).map(|(distance, normal, uv, v_scale)| {
+            local_hit(
+                ray,
+                &onb,
+                o + d * distance,
+                distance,
+                normal,
+                uv,
+                self.material.as_ref(),
+            )
+            .with_uv_scale(around_axis(o + d * distance), v_scale)
+        }))
+    }
+}
+
+impl Hittable for Cone {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        nearest_hit(&[self.span(ray)?], min_distance, max_distance)
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        Some(self.span(ray).into_iter().collect())
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let axis = (self.apex - self.base).norm();
+        Some(disk_bounds(self.base, axis, self.radius).include(self.apex))
+    }
+}
+
+mod test {
+
+    #[test]
+    fn cylinder_and_cone() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::{Lambertian, Material};
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let material: Arc<dyn Material + Send + Sync> = Arc::new(Lambertian::new(Color::white()));
+        // Standing on the ground at x = 5
+        let cylinder = super::Cylinder {
+            base: Location::new(5.0, 0.0, 0.0),
+            top: Location::new(5.0, 0.0, 2.0),
+            radius: 1.0,
+            material: material.clone(),
+        };
+        let ray = |z: f64| Ray {
+            origin: Location::new(0.0, 0.0, z),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let hit = cylinder.get_hits(&ray(1.0), 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 4.0).abs() < 1e-12);
+        assert!((hit.normal - Direction::new(-1.0, 0.0, 0.0)).length() < 1e-12);
+        assert!((hit.uv.1 - 0.5).abs() < 1e-12);
+        assert!(cylinder.get_hits(&ray(2.5), 0.001, f64::MAX).is_none());
+        // Looking down onto the top cap
+        let down = Ray {
+            origin: Location::new(5.5, 0.0, 10.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let span = cylinder.get_spans(&down).unwrap()[0];
+        assert!((span.enter.distance - 8.0).abs() < 1e-12);
+        assert!((span.enter.normal - Direction::new(0.0, 0.0, 1.0)).length() < 1e-12);
+        assert!((span.exit.distance - 10.0).abs() < 1e-12);
+        let bounds = cylinder.bounding_box().unwrap();
+        assert!((bounds.min.x() - 4.0).abs() < 1e-12 && (bounds.max.z() - 2.0).abs() < 1e-12);
+
+        let cone = super::Cone {
+            base: Location::new(5.0, 0.0, 0.0),
+            apex: Location::new(5.0, 0.0, 2.0),
+            radius: 1.0,
+            material,
+        };
+        // Half way up the radius is 0.5, the side is tilted by atan(1/2)
+        let hit = cone.get_hits(&ray(1.0), 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 4.5).abs() < 1e-12);
+        assert!((hit.normal - Direction::new(-2.0, 0.0, 1.0).norm()).length() < 1e-12);
+        assert!(cone.get_hits(&ray(2.1), 0.001, f64::MAX).is_none());
+        let span = cone.get_spans(&down).unwrap()[0];
+        assert!((span.exit.distance - 10.0).abs() < 1e-12);
+    }
+}
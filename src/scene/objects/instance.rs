@@ -0,0 +1,360 @@
+//! Instances place a shared object with a transformation in the world.
+
+use crate::geometry::*;
+use crate::material::Material;
+use crate::scene::{Hit, Hittable, LightSample, Ray, Span};
+use std::sync::Arc;
+
+/// Transformed copy of an object. The object itself is shared, so a mesh can be placed many times
+/// without copying its triangles.
+pub struct Instance {
+    object: Arc<dyn Hittable + Send + Sync>,
+    /// Transformation from the object space to the world
+    transform: Transform,
+    /// Moves the object over time before the transformation is applied
+    motion: Option<Motion>,
+    /// Replaces the material of the object if set
+    material: Option<Arc<dyn Material + Send + Sync>>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable + Send + Sync>, transform: Transform) -> Self {
+        Self {
+            object,
+            transform,
+            motion: None,
+            material: None,
+        }
+    }
+
+    /// Moves the object over time, for motion blur. The motion is applied before the transformation.
+    pub fn with_motion(mut self, motion: Motion) -> Self {
+        self.motion = Some(motion);
+        self
+    }
+
+    /// Transformation from the object space to the world at the given time
+    fn transform_at(&self, time: f64) -> Transform {
+        match &self.motion {
+            Some(motion) => self.transform * motion.at(time),
+            None => self.transform,
+        }
+    }
+
+    /// Replaces the material of the object. None keeps the material of the object.
+    pub fn with_material(mut self, material: Option<Arc<dyn Material + Send + Sync>>) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Transforms the ray into the object space. Returns the ray with a normalized direction and
+    /// the factor from distances in the world to distances in the object space.
+    fn object_ray(&self, ray: &Ray) -> (Ray, f64) {
+        let transform = self.transform_at(ray.time);
+        let direction = transform.inverse_direction(ray.direction);
+        let scale = direction.length();
+        (
+            Ray {
+                origin: transform.inverse_location(ray.origin),
+                direction: direction / scale,
+                time: ray.time,
+            },
+            scale,
+        )
+    }
+
+    /// Transforms a hit of the object ray back into the world
+    fn world_hit<'a>(&'a self, ray: &Ray, hit: Hit<'a>, scale: f64) -> Hit<'a> {
+        let material = match &self.material {
+            Some(material) => material.as_ref(),
+            None => hit.material,
+        };
+        let transform = self.transform_at(ray.time);
+        let tangent = transform.direction(hit.tangent);
+        Hit::new(
+            ray,
+            hit.distance / scale,
+            transform.normal(hit.normal).norm(),
+            hit.uv,
+            material,
+        )
+        .with_tangent(tangent)
+        .with_uv_scale(
+            hit.uv_scale.0 * tangent.length(),
+            hit.uv_scale.1 * transform.direction(hit.bitangent()).length(),
+        )
+    }
+
+    /// Ratio of a solid angle in the object space to the solid angle of the world direction
+    fn solid_angle_scale(transform: &Transform, direction: Direction) -> f64 {
+        let object_direction = transform.inverse_direction(direction);
+        (1.0 / transform.determinant()).abs() / object_direction.length().powi(3)
+    }
+}
+
+impl Hittable for Instance {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let (object_ray, scale) = self.object_ray(ray);
+        let hit = self
+            .object
+            .get_hits(&object_ray, min_distance * scale, max_distance * scale)?;
+        Some(self.world_hit(ray, hit, scale))
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        let (object_ray, scale) = self.object_ray(ray);
+        let spans = self.object.get_spans(&object_ray)?;
+        Some(
+            spans
+                .into_iter()
+                .map(|span| Span {
+                    enter: self.world_hit(ray, span.enter, scale),
+                    exit: self.world_hit(ray, span.exit, scale),
+                })
+                .collect(),
+        )
+    }
+
+    fn sample_direction(&self, origin: Location, time: f64) -> Option<LightSample> {
+        let transform = self.transform_at(time);
+        let sample = self
+            .object
+            .sample_direction(transform.inverse_location(origin), time)?;
+        let direction = transform.direction(sample.direction);
+        let length = direction.length();
+        let direction = direction / length;
+        Some(LightSample {
+            direction,
+            distance: sample.distance * length,
+            pdf: sample.pdf * Self::solid_angle_scale(&transform, direction),
+        })
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        let (object_ray, _) = self.object_ray(ray);
+        self.object.pdf_value(&object_ray)
+            * Self::solid_angle_scale(&self.transform_at(ray.time), ray.direction)
+    }
+
+    fn is_medium(&self) -> bool {
+        self.object.is_medium()
+    }
+
+    fn transmittance(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> f64 {
+        let (object_ray, scale) = self.object_ray(ray);
+        self.object
+            .transmittance(&object_ray, min_distance * scale, max_distance * scale)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bounds = self.object.bounding_box()?;
+        let motion = match &self.motion {
+            Some(motion) => motion,
+            None => return Some(self.transform.aabb(&bounds)),
+        };
+        // Boxes at several times, enlarged by the largest distance a corner moves between two of them, contain
+        // the whole path. The movement of the object is largest at the corners of its box.
+        let steps = 16;
+        let transforms: Vec<Transform> = (0..=steps)
+            .map(|i| {
+                let t = i as f64 / steps as f64;
+                self.transform_at(motion.start_time * (1.0 - t) + motion.end_time * t)
+            })
+            .collect();
+        let corners: Vec<Location> = (0..8)
+            .map(|i| {
+                let pick = |bit: usize, axis: usize| {
+                    if i >> bit & 1 == 0 {
+                        bounds.min.as_slice()[axis]
+                    } else {
+                        bounds.max.as_slice()[axis]
+                    }
+                };
+                Location::new(pick(0, 0), pick(1, 1), pick(2, 2))
+            })
+            .collect();
+        let margin = transforms
+            .windows(2)
+            .flat_map(|pair| {
+                corners.iter().map(move |&corner| {
+                    (pair[1].location(corner) - pair[0].location(corner)).length()
+                })
+            })
+            .fold(0.0, f64::max);
+        let margin = Direction::new(margin, margin, margin);
+        let path = transforms
+            .iter()
+            .map(|transform| transform.aabb(&bounds))
+            .reduce(|a, b| a.union(&b))?;
+        Some(Aabb::new(path.min - margin, path.max + margin))
+    }
+}
+
+mod test {
+
+    #[test]
+    fn transformed_sphere() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location, Quaternion, Transform};
+        use crate::material::Lambertian;
+        use crate::scene::objects::Sphere;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let sphere = Arc::new(Sphere {
+            origin: Location::origin(),
+            radius: 1.0,
+            material: Arc::new(Lambertian::new(Color::white())),
+        });
+        // Ellipsoid stretched along z, rotated to lie along x and moved to x = 10
+        let transform = Transform::translation(Direction::new(10.0, 0.0, 0.0))
+            * Transform::rotation(Quaternion::from_axis_angle(
+                Direction::new(0.0, 1.0, 0.0),
+                90.0,
+            ))
+            * Transform::scaling(1.0, 1.0, 3.0);
+        let instance = super::Instance::new(sphere, transform);
+
+        let ray = Ray {
+            origin: Location::origin(),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let hit = instance.get_hits(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 7.0).abs() < 1e-9);
+        assert!((hit.normal - Direction::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+        // The range is measured in the world
+        assert!(instance.get_hits(&ray, 0.001, 6.9).is_none());
+
+        let bounds = instance.bounding_box().unwrap();
+        assert!((bounds.min.x() - 7.0).abs() < 1e-9);
+        assert!((bounds.max.x() - 13.0).abs() < 1e-9);
+        assert!((bounds.max.z() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn light_pdf_matches_samples() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location, Transform};
+        use crate::material::DiffuseLight;
+        use crate::scene::objects::Sphere;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let sphere = Arc::new(Sphere {
+            origin: Location::origin(),
+            radius: 1.0,
+            material: Arc::new(DiffuseLight::new(Color::white(), 1.0)),
+        });
+        let transform = Transform::translation(Direction::new(0.0, 0.0, 5.0))
+            * Transform::scaling(2.0, 2.0, 2.0);
+        let instance = super::Instance::new(sphere, transform);
+
+        let origin = Location::origin();
+        let sample = instance.sample_direction(origin, 0.0).unwrap();
+        let ray = Ray {
+            origin,
+            direction: sample.direction,
+            time: 0.0,
+        };
+        assert!((instance.pdf_value(&ray) - sample.pdf).abs() < 1e-9 * sample.pdf);
+        // Uniform scaling keeps the cone of the sphere with radius 2 at distance 5
+        let cos_theta_max = (1.0 - 4.0 / 25.0_f64).sqrt();
+        let expected = 1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_theta_max));
+        assert!((sample.pdf - expected).abs() < 1e-9 * expected);
+        let hit = instance.get_hits(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - sample.distance).abs() < 1e-9);
+    }
+
+    #[test]
+    fn instance_keeps_time() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location, Transform};
+        use crate::material::Lambertian;
+        use crate::scene::objects::MovingSphere;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        // Moves from y = 0 to y = 4 in the object space, the instance is scaled by two
+        let sphere = Arc::new(MovingSphere {
+            start: Location::origin(),
+            end: Location::new(0.0, 4.0, 0.0),
+            start_time: 0.0,
+            end_time: 1.0,
+            radius: 0.5,
+            material: Arc::new(Lambertian::new(Color::white())),
+        });
+        let transform = Transform::translation(Direction::new(10.0, 0.0, 0.0))
+            * Transform::scaling(2.0, 2.0, 2.0);
+        let instance = super::Instance::new(sphere, transform);
+        let ray = |y: f64, time: f64| Ray {
+            origin: Location::new(0.0, y, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time,
+        };
+        let hit = instance.get_hits(&ray(0.0, 0.0), 0.001, f64::MAX).unwrap();
+        // The texture is stretched with the sphere
+        let pi = std::f64::consts::PI;
+        assert!((hit.uv_scale.0 - 2.0 * pi).abs() < 1e-9 && (hit.uv_scale.1 - pi).abs() < 1e-9);
+        assert!(instance.get_hits(&ray(0.0, 1.0), 0.001, f64::MAX).is_none());
+        assert!(instance.get_hits(&ray(4.0, 0.5), 0.001, f64::MAX).is_some());
+        assert!(instance.get_hits(&ray(8.0, 1.0), 0.001, f64::MAX).is_some());
+        // Light samples aim at the sphere at the time of the sample
+        let sample = instance.sample_direction(Location::origin(), 1.0).unwrap();
+        assert!(sample.direction.y() > 0.5);
+    }
+
+    #[test]
+    fn moving_instance() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location, Motion, Pose, Quaternion, Transform};
+        use crate::material::Lambertian;
+        use crate::scene::objects::Sphere;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let sphere = Arc::new(Sphere {
+            origin: Location::new(2.0, 0.0, 0.0),
+            radius: 0.5,
+            material: Arc::new(Lambertian::new(Color::white())),
+        });
+        // Turns half around the z axis, then the whole motion is moved up
+        let motion = Motion {
+            start: Pose::new(
+                Direction::new(0.0, 0.0, 0.0),
+                Quaternion::identity(),
+                Direction::new(1.0, 1.0, 1.0),
+            ),
+            end: Pose::new(
+                Direction::new(0.0, 0.0, 0.0),
+                Quaternion::from_axis_angle(Direction::new(0.0, 0.0, 1.0), 180.0),
+                Direction::new(1.0, 1.0, 1.0),
+            ),
+            start_time: 0.0,
+            end_time: 1.0,
+        };
+        let instance = super::Instance::new(
+            sphere,
+            Transform::translation(Direction::new(0.0, 0.0, 5.0)),
+        )
+        .with_motion(motion);
+        let ray = |direction: Direction, time: f64| Ray {
+            origin: Location::new(0.0, 0.0, 5.0),
+            direction,
+            time,
+        };
+        let x = Direction::new(1.0, 0.0, 0.0);
+        let y = Direction::new(0.0, 1.0, 0.0);
+        assert!(instance.get_hits(&ray(x, 0.0), 0.001, f64::MAX).is_some());
+        assert!(instance.get_hits(&ray(x, 0.5), 0.001, f64::MAX).is_none());
+        let hit = instance.get_hits(&ray(y, 0.5), 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 1.5).abs() < 1e-9);
+        assert!((hit.normal - y.invert()).length() < 1e-9);
+
+        // The bounds contain the whole arc, not only the start and the end
+        let bounds = instance.bounding_box().unwrap();
+        assert!(bounds.max.y() >= 2.5 && bounds.min.y() <= -0.5);
+        assert!(bounds.min.x() <= -2.5 && bounds.max.x() >= 2.5);
+        assert!(bounds.min.z() <= 4.5 && bounds.max.z() >= 5.5);
+    }
+}
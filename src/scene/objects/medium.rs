@@ -0,0 +1,296 @@
+//! Participating media like smoke or fog that scatter light inside of a volume.
+
+use crate::geometry::*;
+use crate::material::Material;
+use crate::sampling::{random_free_path, rng};
+use crate::scene::{Hit, Hittable, Ray};
+use crate::voxel::VoxelGrid;
+use rand::prelude::*;
+use std::sync::Arc;
+
+/// Medium of constant density inside of a closed object. Rays scatter at random points inside of the object,
+/// the chance to travel a distance without scattering falls exponentially with the density.
+/// The material of the boundary is used at the scattering points, mostly a `Volume`.
+pub struct ConstantMedium {
+    boundary: Arc<dyn Hittable + Send + Sync>,
+    /// Chance to scatter per unit of distance
+    density: f64,
+}
+
+impl ConstantMedium {
+    /// Fills the boundary with the medium. The boundary has to be a closed object that returns spans.
+    pub fn new(boundary: Arc<dyn Hittable + Send + Sync>, density: f64) -> Self {
+        Self { boundary, density }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let spans = self.boundary.get_spans(ray)?;
+        // Distance inside of the medium until the ray scatters
+        let mut remaining = random_free_path(self.density);
+        for span in spans {
+            let start = span.enter.distance.max(min_distance);
+            let end = span.exit.distance.min(max_distance);
+            if start >= end {
+                continue;
+            }
+            if remaining < end - start {
+                // There is no surface, the normal just faces the ray
+                return Some(Hit::new(
+                    ray,
+                    start + remaining,
+                    ray.direction.invert(),
+                    (0.0, 0.0),
+                    span.enter.material,
+                ));
+            }
+            remaining -= end - start;
+        }
+        None
+    }
+
+    fn is_medium(&self) -> bool {
+        true
+    }
+
+    /// Exact for a constant density, it falls exponentially with the distance inside of the medium
+    fn transmittance(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> f64 {
+        let inside: f64 = match self.boundary.get_spans(ray) {
+            Some(spans) => spans
+                .iter()
+                .map(|span| {
+                    (span.exit.distance.min(max_distance) - span.enter.distance.max(min_distance))
+                        .max(0.0)
+                })
+                .sum(),
+            None => 0.0,
+        };
+        (-self.density * inside).exp()
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+}
+
+/// Medium whose density is given by a voxel grid that fills a box. Rays are traced with delta tracking and the
+/// transmittance of shadow rays is estimated with ratio tracking, both against the largest density of the grid.
+/// The texture coordinates of the hits are the density relative to the largest density and the temperature in
+/// kelvin, which can be used by the textures of the material.
+pub struct GridMedium {
+    grid: Arc<VoxelGrid>,
+    bounds: Aabb,
+    /// Factor from the values of the grid to the chance to scatter per unit of distance
+    density: f64,
+    /// Factor from the values of the grid to kelvin
+    temperature_scale: f64,
+    material: Arc<dyn Material + Send + Sync>,
+}
+
+impl GridMedium {
+    pub fn new(
+        grid: Arc<VoxelGrid>,
+        bounds: Aabb,
+        density: f64,
+        material: Arc<dyn Material + Send + Sync>,
+    ) -> Self {
+        Self {
+            grid,
+            bounds,
+            density,
+            temperature_scale: 1.0,
+            material,
+        }
+    }
+
+    /// Sets the factor from the temperatures of the grid to kelvin
+    pub fn with_temperature_scale(mut self, temperature_scale: f64) -> Self {
+        self.temperature_scale = temperature_scale;
+        self
+    }
+
+    /// Upper bound of the density in the whole box
+    fn majorant(&self) -> f64 {
+        self.grid.max_density() * self.density
+    }
+
+    /// Position of the point relative to the box, 0.0 to 1.0 on each axis
+    fn grid_position(&self, point: Location) -> [f64; 3] {
+        let relative = point - self.bounds.min;
+        let size = self.bounds.max - self.bounds.min;
+        [
+            relative.x() / size.x(),
+            relative.y() / size.y(),
+            relative.z() / size.z(),
+        ]
+    }
+
+    /// Part of the ray inside of the box
+    fn clip(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<(f64, f64)> {
+        let direction = ray.direction.as_slice();
+        let inv_direction = [1.0 / direction[0], 1.0 / direction[1], 1.0 / direction[2]];
+        self.bounds.clip(
+            &ray.origin.as_slice(),
+            &inv_direction,
+            min_distance,
+            max_distance,
+        )
+    }
+}
+
+impl Hittable for GridMedium {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return None;
+        }
+        let (mut distance, exit) = self.clip(ray, min_distance, max_distance)?;
+        // Delta tracking: sample collisions with the majorant and accept them with the ratio of the real density
+        loop {
+            distance += random_free_path(majorant);
+            if distance >= exit {
+                return None;
+            }
+            let position = self.grid_position(ray.at(distance));
+            let density = self.grid.density(position) * self.density;
+            if rng().gen::<f64>() * majorant < density {
+                let temperature = self.grid.temperature(position) * self.temperature_scale;
+                // There is no surface, the normal just faces the ray
+                return Some(Hit::new(
+                    ray,
+                    distance,
+                    ray.direction.invert(),
+                    (density / majorant, temperature),
+                    self.material.as_ref(),
+                ));
+            }
+        }
+    }
+
+    fn is_medium(&self) -> bool {
+        true
+    }
+
+    fn transmittance(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> f64 {
+        let majorant = self.majorant();
+        let (mut distance, exit) = match self.clip(ray, min_distance, max_distance) {
+            Some(span) if majorant > 0.0 => span,
+            _ => return 1.0,
+        };
+        // Ratio tracking: every tentative collision keeps the part of the light that is not scattered
+        let mut transmittance = 1.0;
+        loop {
+            distance += random_free_path(majorant);
+            if distance >= exit {
+                return transmittance;
+            }
+            let density = self.grid.density(self.grid_position(ray.at(distance))) * self.density;
+            transmittance *= 1.0 - density / majorant;
+            // Russian roulette, so rays through dense regions stop early without a bias
+            if transmittance < 0.1 {
+                if rng().gen::<f64>() < 0.5 {
+                    return 0.0;
+                }
+                transmittance *= 2.0;
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.bounds)
+    }
+}
+
+mod test {
+
+    #[test]
+    fn transmittance() {
+        use super::ConstantMedium;
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::{PhaseFunction, Volume};
+        use crate::scene::objects::Sphere;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let medium = ConstantMedium::new(
+            Arc::new(Sphere {
+                origin: Location::origin(),
+                radius: 1.0,
+                material: Arc::new(Volume::new(Color::white(), PhaseFunction::Isotropic)),
+            }),
+            0.5,
+        );
+        let ray = Ray {
+            origin: Location::new(-5.0, 0.0, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+
+        // The ray passes 2 units of the medium without scattering with a chance of exp(-1)
+        let count = 20000;
+        let mut passed = 0;
+        for _ in 0..count {
+            match medium.get_hits(&ray, 0.001, f64::MAX) {
+                Some(hit) => assert!(hit.distance >= 4.0 && hit.distance <= 6.0),
+                None => passed += 1,
+            }
+        }
+        let expected = (-1.0f64).exp();
+        assert!((passed as f64 / count as f64 - expected).abs() < 0.02);
+
+        // Only the part of the medium in front of the maximum distance counts
+        assert!(medium.get_hits(&ray, 0.001, 4.0).is_none());
+        assert!((medium.transmittance(&ray, 0.001, f64::MAX) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn grid_transmittance() {
+        use super::GridMedium;
+        use crate::color::Color;
+        use crate::geometry::{Aabb, Direction, Location};
+        use crate::material::{PhaseFunction, Volume};
+        use crate::scene::{Hittable, Ray};
+        use crate::voxel::VoxelGrid;
+        use std::sync::Arc;
+
+        // Empty first half and dense second half along x, with temperatures
+        let grid = VoxelGrid::new([2, 1, 1], vec![0.0, 1.0])
+            .unwrap()
+            .with_temperature(vec![300.0, 1500.0])
+            .unwrap();
+        let medium = GridMedium::new(
+            Arc::new(grid),
+            Aabb::new(Location::new(0.0, -1.0, -1.0), Location::new(4.0, 1.0, 1.0)),
+            0.5,
+            Arc::new(Volume::new(Color::white(), PhaseFunction::Isotropic)),
+        );
+        let ray = Ray {
+            origin: Location::new(-1.0, 0.0, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        // The density rises linearly from 0.0 at x = 1 to 0.5 at x = 3 and stays there, the optical depth is 1.0
+        let expected = (-1.0f64).exp();
+
+        let count = 20000;
+        let mut passed = 0;
+        let mut ratio = 0.0;
+        for _ in 0..count {
+            match medium.get_hits(&ray, 0.001, f64::MAX) {
+                Some(hit) => {
+                    assert!(hit.distance >= 2.0 && hit.distance <= 5.0);
+                    assert!(hit.uv.1 > 300.0 && hit.uv.1 <= 1500.0);
+                }
+                None => passed += 1,
+            }
+            ratio += medium.transmittance(&ray, 0.001, f64::MAX);
+        }
+        assert!((passed as f64 / count as f64 - expected).abs() < 0.02);
+        assert!((ratio / count as f64 - expected).abs() < 0.02);
+
+        // Nothing blocks rays through the empty half
+        assert_eq!(medium.transmittance(&ray, 0.001, 2.0), 1.0);
+    }
+}
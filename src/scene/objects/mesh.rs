@@ -0,0 +1,366 @@
+//! Triangles and indexed triangle meshes.
+
+use super::{area_sample, hit_area_pdf};
+use crate::bvh::BvhTree;
+use crate::geometry::*;
+use crate::material::Material;
+use crate::sampling::rng;
+use crate::scene::{Hit, Hittable, LightSample, Ray, MIN_HIT_DISTANCE};
+use rand::prelude::*;
+use std::sync::Arc;
+
+/// Result of the intersection of a ray with a triangle
+#[derive(Debug, Copy, Clone)]
+struct TriangleHit {
+    distance: f64,
+    /// Barycentric weights of the three vertices
+    weights: [f64; 3],
+}
+
+/// Watertight ray triangle intersection from "Watertight Ray/Triangle Intersection" by Woop, Benthin and Wald.
+/// Rays hitting a shared edge or vertex of neighbouring triangles hit at least one of them.
+fn intersect_triangle(
+    ray: &Ray,
+    vertices: [Location; 3],
+    min_distance: f64,
+    max_distance: f64,
+) -> Option<TriangleHit> {
+    let dir = ray.direction.as_slice();
+
+    // Permute the axis so that z is the largest component of the direction
+    let kz = if dir[0].abs() > dir[1].abs() {
+        if dir[0].abs() > dir[2].abs() {
+            0
+        } else {
+            2
+        }
+    } else if dir[1].abs() > dir[2].abs() {
+        1
+    } else {
+        2
+    };
+    let mut kx = (kz + 1) % 3;
+    let mut ky = (kx + 1) % 3;
+    // Keep the winding direction
+    if dir[kz] < 0.0 {
+        std::mem::swap(&mut kx, &mut ky);
+    }
+
+    // Shear constants to transform the ray direction to the z axis
+    let sx = dir[kx] / dir[kz];
+    let sy = dir[ky] / dir[kz];
+    let sz = 1.0 / dir[kz];
+
+    // Vertices relative to the ray origin
+    let a = (vertices[0] - ray.origin).as_slice();
+    let b = (vertices[1] - ray.origin).as_slice();
+    let c = (vertices[2] - ray.origin).as_slice();
+
+    // Shear and scale the vertices
+    let ax = a[kx] - sx * a[kz];
+    let ay = a[ky] - sy * a[kz];
+    let bx = b[kx] - sx * b[kz];
+    let by = b[ky] - sy * b[kz];
+    let cx = c[kx] - sx * c[kz];
+    let cy = c[ky] - sy * c[kz];
+
+    // Scaled barycentric coordinates
+    let u = cx * by - cy * bx;
+    let v = ax * cy - ay * cx;
+    let w = bx * ay - by * ax;
+
+    // The ray misses if the signs differ. Zero values are on an edge and count as hit.
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+
+    // Scaled distance of the hit
+    let t = u * sz * a[kz] + v * sz * b[kz] + w * sz * c[kz];
+    let distance = t / det;
+    if distance <= min_distance || distance >= max_distance {
+        return None;
+    }
+
+    Some(TriangleHit {
+        distance,
+        weights: [u / det, v / det, w / det],
+    })
+}
+
+/// Normal of the plane spanned by the vertices. Counter clockwise vertices look at the viewer.
+fn geometric_normal(vertices: [Location; 3]) -> Direction {
+    (vertices[1] - vertices[0])
+        .cross(vertices[2] - vertices[0])
+        .norm()
+}
+
+/// Change of the position per unit of u and of v on the triangle with the given texture coordinates at the corners
+fn uv_derivatives(corners: [Location; 3], uvs: [(f64, f64); 3]) -> (Direction, Direction) {
+    let e1 = corners[1] - corners[0];
+    let e2 = corners[2] - corners[0];
+    let (du1, dv1) = (uvs[1].0 - uvs[0].0, uvs[1].1 - uvs[0].1);
+    let (du2, dv2) = (uvs[2].0 - uvs[0].0, uvs[2].1 - uvs[0].1);
+    let determinant = du1 * dv2 - du2 * dv1;
+    if determinant.abs() < 1e-12 {
+        // The texture coordinates do not span the triangle
+        return (e1, e2);
+    }
+    (
+        (e1 * dv2 - e2 * dv1) / determinant,
+        (e2 * du1 - e1 * du2) / determinant,
+    )
+}
+
+/// Bounding box of the vertices
+fn triangle_bounds(vertices: [Location; 3]) -> Aabb {
+    Aabb::new(vertices[0], vertices[1]).include(vertices[2])
+}
+
+/// A single triangle
+pub struct Triangle {
+    /// Corners of the triangle in counter clockwise order seen from the front side
+    pub vertices: [Location; 3],
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Hittable for Triangle {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let hit = intersect_triangle(ray, self.vertices, min_distance, max_distance)?;
+        Some(
+            Hit::new(
+                ray,
+                hit.distance,
+                geometric_normal(self.vertices),
+                (hit.weights[1], hit.weights[2]),
+                self.material.as_ref(),
+            )
+            .with_tangent(self.vertices[1] - self.vertices[0])
+            .with_uv_scale(
+                (self.vertices[1] - self.vertices[0]).length(),
+                (self.vertices[2] - self.vertices[0]).length(),
+            ),
+        )
+    }
+
+    /// Samples a uniformly choosen point on the triangle
+    fn sample_direction(&self, origin: Location, _time: f64) -> Option<LightSample> {
+        let mut rng = rng();
+        let s = rng.gen::<f64>().sqrt();
+        let t: f64 = rng.gen();
+        let [a, b, c] = self.vertices;
+        let point = a + (b - a) * (s * (1.0 - t)) + (c - a) * (s * t);
+        area_sample(origin, point, geometric_normal(self.vertices), self.area())
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        hit_area_pdf(
+            ray,
+            self.get_hits(ray, MIN_HIT_DISTANCE, f64::MAX),
+            self.area(),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(triangle_bounds(self.vertices))
+    }
+}
+
+impl Triangle {
+    fn area(&self) -> f64 {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(c - a).length() / 2.0
+    }
+}
+
+/// Vertex data of a mesh. Can be shared by several meshes, for example if parts of a model use different materials.
+#[derive(Debug, Clone, Default)]
+pub struct VertexBuffer {
+    pub positions: Vec<Location>,
+    /// Normals for smooth shading. Either empty or one for each position.
+    pub normals: Vec<Direction>,
+    /// Texture coordinates. Either empty or one for each position.
+    pub uvs: Vec<(f64, f64)>,
+}
+
+/// Mesh of triangles that reference their corners by index in a vertex buffer.
+/// The triangles are stored in their own bvh.
+pub struct TriangleMesh {
+    vertices: Arc<VertexBuffer>,
+    /// Indices of the corners of each triangle in counter clockwise order
+    indices: Vec<[usize; 3]>,
+    material: Arc<dyn Material + Send + Sync>,
+    bvh: BvhTree,
+}
+
+impl TriangleMesh {
+    /// Creates a new mesh and builds the bvh for the triangles.
+    ///
+    /// # Panics
+    /// Panics if an index is out of the vertex buffer or the buffer has the wrong number of normals or uvs.
+    pub fn new(
+        vertices: Arc<VertexBuffer>,
+        indices: Vec<[usize; 3]>,
+        material: Arc<dyn Material + Send + Sync>,
+    ) -> Self {
+        let vertex_count = vertices.positions.len();
+        assert!(vertices.normals.is_empty() || vertices.normals.len() == vertex_count);
+        assert!(vertices.uvs.is_empty() || vertices.uvs.len() == vertex_count);
+        assert!(indices.iter().flatten().all(|index| *index < vertex_count));
+
+        let bounds: Vec<Aabb> = indices
+            .iter()
+            .map(|triangle| triangle_bounds(Self::corners(&vertices, triangle)))
+            .collect();
+        Self {
+            bvh: BvhTree::new(&bounds),
+            vertices,
+            indices,
+            material,
+        }
+    }
+
+    /// Replaces the material of all triangles
+    pub fn with_material(mut self, material: Arc<dyn Material + Send + Sync>) -> Self {
+        self.material = material;
+        self
+    }
+
+    /// Number of triangles in the mesh
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns true if the mesh has no triangles
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Positions of the corners of a triangle
+    fn corners(vertices: &VertexBuffer, triangle: &[usize; 3]) -> [Location; 3] {
+        [
+            vertices.positions[triangle[0]],
+            vertices.positions[triangle[1]],
+            vertices.positions[triangle[2]],
+        ]
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        self.bvh
+            .get_hits(ray, min_distance, max_distance, |index, max_distance| {
+                let triangle = &self.indices[index];
+                let corners = Self::corners(&self.vertices, triangle);
+                let hit = intersect_triangle(ray, corners, min_distance, max_distance)?;
+                let [w0, w1, w2] = hit.weights;
+
+                // Interpolate the vertex normals for smooth shading
+                let normal = if self.vertices.normals.is_empty() {
+                    geometric_normal(corners)
+                } else {
+                    let n = &self.vertices.normals;
+                    (n[triangle[0]] * w0 + n[triangle[1]] * w1 + n[triangle[2]] * w2).norm()
+                };
+                let (uv, (dp_du, dp_dv)) = if self.vertices.uvs.is_empty() {
+                    ((w1, w2), (corners[1] - corners[0], corners[2] - corners[0]))
+                } else {
+                    let uv = &self.vertices.uvs;
+                    let uvs = [uv[triangle[0]], uv[triangle[1]], uv[triangle[2]]];
+                    let [uv0, uv1, uv2] = uvs;
+                    (
+                        (
+                            uv0.0 * w0 + uv1.0 * w1 + uv2.0 * w2,
+                            uv0.1 * w0 + uv1.1 * w1 + uv2.1 * w2,
+                        ),
+                        uv_derivatives(corners, uvs),
+                    )
+                };
+
+                Some(
+                    Hit::new(ray, hit.distance, normal, uv, self.material.as_ref())
+                        .with_tangent(dp_du)
+                        .with_uv_scale(dp_du.length(), dp_dv.length()),
+                )
+            })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+}
+
+mod test {
+
+    #[test]
+    fn watertight_edges() {
+        use crate::geometry::{Direction, Location};
+        use crate::scene::Ray;
+
+        // Two triangles sharing the diagonal of the unit square
+        let a = Location::new(0.0, 0.0, 0.0);
+        let b = Location::new(1.0, 0.0, 0.0);
+        let c = Location::new(1.0, 1.0, 0.0);
+        let d = Location::new(0.0, 1.0, 0.0);
+
+        for i in 0..=100 {
+            // Rays exactly through the shared edge
+            let s = i as f64 / 100.0;
+            let ray = Ray {
+                origin: Location::new(s, s, 1.0),
+                direction: Direction::new(0.0, 0.0, -1.0),
+                time: 0.0,
+            };
+            let hit_1 = super::intersect_triangle(&ray, [a, b, c], 0.0, f64::MAX);
+            let hit_2 = super::intersect_triangle(&ray, [a, c, d], 0.0, f64::MAX);
+            assert!(hit_1.is_some() || hit_2.is_some());
+        }
+    }
+
+    #[test]
+    fn mesh_interpolation() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let vertices = super::VertexBuffer {
+            positions: vec![
+                Location::new(0.0, 0.0, 0.0),
+                Location::new(1.0, 0.0, 0.0),
+                Location::new(1.0, 1.0, 0.0),
+                Location::new(0.0, 1.0, 0.0),
+            ],
+            normals: vec![Direction::new(0.0, 0.0, 1.0); 4],
+            uvs: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+        };
+        let mesh = super::TriangleMesh::new(
+            Arc::new(vertices),
+            vec![[0, 1, 2], [0, 2, 3]],
+            Arc::new(Lambertian::new(Color::white())),
+        );
+        assert_eq!(mesh.len(), 2);
+
+        let ray = Ray {
+            origin: Location::new(0.25, 0.75, 2.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let hit = mesh.get_hits(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 2.0).abs() < 1e-12);
+        assert!((hit.uv.0 - 0.25).abs() < 1e-12);
+        assert!((hit.uv.1 - 0.75).abs() < 1e-12);
+        assert!((hit.normal - Direction::new(0.0, 0.0, 1.0)).length() < 1e-12);
+
+        let miss = Ray {
+            origin: Location::new(1.5, 0.5, 2.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(mesh.get_hits(&miss, 0.001, f64::MAX).is_none());
+    }
+}
@@ -0,0 +1,349 @@
+//! Flat objects: infinite planes, disks and parallelograms.
+
+use super::{area_sample, disk_bounds, hit_area_pdf};
+use crate::geometry::*;
+use crate::material::Material;
+use crate::sampling::{random_in_unit_disk, rng, Onb};
+use crate::scene::{Hit, Hittable, LightSample, Ray, Span, MIN_HIT_DISTANCE};
+use rand::prelude::*;
+use std::sync::Arc;
+
+/// Distance at which the line of the ray crosses the plane. None if the ray is parallel to the plane.
+fn intersect_plane(point: Location, normal: Direction, ray: &Ray) -> Option<f64> {
+    let denominator = normal.dot(ray.direction);
+    if denominator.abs() < 1e-12 {
+        None
+    } else {
+        Some(normal.dot(point - ray.origin) / denominator)
+    }
+}
+
+/// Infinite plane through `point`. The texture repeats every unit in both directions.
+/// For CSG the plane is the half space behind the normal.
+pub struct Plane {
+    pub point: Location,
+    /// Normalized direction of the front side
+    pub normal: Direction,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Plane {
+    fn hit(&self, ray: &Ray, distance: f64) -> Hit<'_> {
+        let onb = Onb::from_w(self.normal);
+        let offset = ray.at(distance) - self.point;
+        let uv = (
+            offset.dot(onb.u).rem_euclid(1.0),
+            offset.dot(onb.v).rem_euclid(1.0),
+        );
+        Hit::new(ray, distance, self.normal, uv, self.material.as_ref()).with_tangent(onb.u)
+    }
+}
+
+impl Hittable for Plane {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let distance = intersect_plane(self.point, self.normal, ray)?;
+        if distance > min_distance && distance < max_distance {
+            Some(self.hit(ray, distance))
+        } else {
+            None
+        }
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        let far = |distance: f64| {
+            Hit::new(
+                ray,
+                distance,
+                self.normal,
+                (0.0, 0.0),
+                self.material.as_ref(),
+            )
+        };
+        let span = match intersect_plane(self.point, self.normal, ray) {
+            // Parallel rays are completely inside or outside
+            None if self.normal.dot(ray.origin - self.point) < 0.0 => Some(Span {
+                enter: far(f64::NEG_INFINITY),
+                exit: far(f64::INFINITY),
+            }),
+            None => None,
+            Some(distance) if self.normal.dot(ray.direction) < 0.0 => Some(Span {
+                enter: self.hit(ray, distance),
+                exit: far(f64::INFINITY),
+            }),
+            Some(distance) => Some(Span {
+                enter: far(f64::NEG_INFINITY),
+                exit: self.hit(ray, distance),
+            }),
+        };
+        Some(span.into_iter().collect())
+    }
+
+    /// Planes are infinite
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+}
+
+/// Round flat disk. u is the angle around the normal, v the distance to the center relative to the radius.
+pub struct Disk {
+    pub center: Location,
+    /// Normalized direction of the front side
+    pub normal: Direction,
+    pub radius: f64,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Hittable for Disk {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let distance = intersect_plane(self.center, self.normal, ray)?;
+        if distance <= min_distance || distance >= max_distance {
+            return None;
+        }
+        let onb = Onb::from_w(self.normal);
+        let offset = ray.at(distance) - self.center;
+        let (x, y) = (offset.dot(onb.u), offset.dot(onb.v));
+        let radius = x.hypot(y);
+        if radius > self.radius {
+            return None;
+        }
+        let uv = (super::azimuth(x, y), radius / self.radius);
+        Some(
+            Hit::new(ray, distance, self.normal, uv, self.material.as_ref())
+                .with_tangent(onb.local(-y, x, 0.0))
+                .with_uv_scale(2.0 * std::f64::consts::PI * radius, self.radius),
+        )
+    }
+
+    /// Samples a uniformly choosen point on the disk
+    fn sample_direction(&self, origin: Location, _time: f64) -> Option<LightSample> {
+        let (x, y) = random_in_unit_disk();
+        let point =
+            self.center + Onb::from_w(self.normal).local(x * self.radius, y * self.radius, 0.0);
+        area_sample(origin, point, self.normal, self.area())
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        hit_area_pdf(
+            ray,
+            self.get_hits(ray, MIN_HIT_DISTANCE, f64::MAX),
+            self.area(),
+        )
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(disk_bounds(self.center, self.normal, self.radius))
+    }
+}
+
+impl Disk {
+    fn area(&self) -> f64 {
+        std::f64::consts::PI * self.radius * self.radius
+    }
+}
+
+/// Parallelogram spanned by the edges `u` and `v` from `corner`. The texture coordinates follow the edges.
+/// The front side is in the direction of `u` x `v`.
+pub struct Quad {
+    pub corner: Location,
+    pub u: Direction,
+    pub v: Direction,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Hittable for Quad {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let n = self.u.cross(self.v);
+        let normal = n.norm();
+        let distance = intersect_plane(self.corner, normal, ray)?;
+        if distance <= min_distance || distance >= max_distance {
+            return None;
+        }
+        // Coordinates of the hitpoint along the edges
+        let offset = ray.at(distance) - self.corner;
+        let w = n / n.dot(n);
+        let a = w.dot(offset.cross(self.v));
+        let b = w.dot(self.u.cross(offset));
+        if !(0.0..=1.0).contains(&a) || !(0.0..=1.0).contains(&b) {
+            return None;
+        }
+        Some(
+            Hit::new(ray, distance, normal, (a, b), self.material.as_ref())
+                .with_tangent(self.u)
+                .with_uv_scale(self.u.length(), self.v.length()),
+        )
+    }
+
+    /// Samples a uniformly choosen point on the parallelogram
+    fn sample_direction(&self, origin: Location, _time: f64) -> Option<LightSample> {
+        let mut rng = rng();
+        let point = self.corner + self.u * rng.gen::<f64>() + self.v * rng.gen::<f64>();
+        let n = self.u.cross(self.v);
+        area_sample(origin, point, n.norm(), n.length())
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        let area = self.u.cross(self.v).length();
+        hit_area_pdf(ray, self.get_hits(ray, MIN_HIT_DISTANCE, f64::MAX), area)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let c = self.corner;
+        Some(
+            Aabb::new(c, c + self.u + self.v)
+                .include(c + self.u)
+                .include(c + self.v),
+        )
+    }
+}
+
+mod test {
+
+    #[test]
+    fn flat_objects() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::{Lambertian, Material};
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let material: Arc<dyn Material + Send + Sync> = Arc::new(Lambertian::new(Color::white()));
+        let ray = |y: f64, z: f64| Ray {
+            origin: Location::new(0.0, y, z),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let facing = Direction::new(-1.0, 0.0, 0.0);
+
+        let plane = super::Plane {
+            point: Location::new(3.0, 0.0, 0.0),
+            normal: facing,
+            material: material.clone(),
+        };
+        let hit = plane.get_hits(&ray(100.0, -7.0), 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 3.0).abs() < 1e-12);
+        assert!(hit.uv.0 >= 0.0 && hit.uv.0 < 1.0 && hit.uv.1 >= 0.0 && hit.uv.1 < 1.0);
+        // The half space behind the plane
+        let spans = plane.get_spans(&ray(0.0, 0.0)).unwrap();
+        assert_eq!(spans[0].enter.distance, 3.0);
+        assert_eq!(spans[0].exit.distance, f64::INFINITY);
+
+        let disk = super::Disk {
+            center: Location::new(3.0, 0.0, 0.0),
+            normal: facing,
+            radius: 1.0,
+            material: material.clone(),
+        };
+        assert!(disk.get_hits(&ray(0.5, 0.5), 0.001, f64::MAX).is_some());
+        assert!(disk.get_hits(&ray(0.8, 0.8), 0.001, f64::MAX).is_none());
+        let bounds = disk.bounding_box().unwrap();
+        assert_eq!(bounds.min.x(), 3.0);
+        assert_eq!(bounds.max.y(), 1.0);
+
+        let quad = super::Quad {
+            corner: Location::new(3.0, 0.0, 0.0),
+            u: Direction::new(0.0, 0.0, 2.0),
+            v: Direction::new(0.0, 1.0, 0.0),
+            material,
+        };
+        let hit = quad.get_hits(&ray(0.25, 1.0), 0.001, f64::MAX).unwrap();
+        assert!((hit.normal - facing).length() < 1e-12);
+        assert!((hit.uv.0 - 0.5).abs() < 1e-12 && (hit.uv.1 - 0.25).abs() < 1e-12);
+        assert!(quad.get_hits(&ray(-0.1, 1.0), 0.001, f64::MAX).is_none());
+    }
+
+    #[test]
+    fn light_pdf_matches_samples() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::{DiffuseLight, Material};
+        use crate::scene::objects::Triangle;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let material: Arc<dyn Material + Send + Sync> =
+            Arc::new(DiffuseLight::new(Color::white(), 1.0));
+        let disk = super::Disk {
+            center: Location::new(0.0, 0.0, 2.0),
+            normal: Direction::new(0.0, 0.0, -1.0),
+            radius: 1.0,
+            material: material.clone(),
+        };
+        let quad = super::Quad {
+            corner: Location::new(-1.0, -0.5, 2.0),
+            u: Direction::new(2.0, 0.0, 0.0),
+            v: Direction::new(0.0, 1.0, 0.5),
+            material: material.clone(),
+        };
+        let triangle = Triangle {
+            vertices: [
+                Location::new(-1.0, -1.0, 2.0),
+                Location::new(1.0, -1.0, 2.0),
+                Location::new(0.0, 1.0, 3.0),
+            ],
+            material,
+        };
+
+        // Solid angle of a triangle seen from the origin, after Van Oosterom and Strackee
+        let triangle_angle = |a: Location, b: Location, c: Location| {
+            let (a, b, c) = (
+                a - Location::origin(),
+                b - Location::origin(),
+                c - Location::origin(),
+            );
+            let (la, lb, lc) = (a.length(), b.length(), c.length());
+            let numerator = a.dot(b.cross(c)).abs();
+            let denominator = la * lb * lc + a.dot(b) * lc + a.dot(c) * lb + b.dot(c) * la;
+            2.0 * numerator.atan2(denominator)
+        };
+        let [a, b, c] = triangle.vertices;
+        let corners = [
+            quad.corner,
+            quad.corner + quad.u,
+            quad.corner + quad.u + quad.v,
+            quad.corner + quad.v,
+        ];
+        let lights: [(&dyn Hittable, f64); 3] = [
+            (
+                &disk,
+                2.0 * std::f64::consts::PI * (1.0 - 2.0 / 5.0_f64.sqrt()),
+            ),
+            (
+                &quad,
+                triangle_angle(corners[0], corners[1], corners[2])
+                    + triangle_angle(corners[0], corners[2], corners[3]),
+            ),
+            (&triangle, triangle_angle(a, b, c)),
+        ];
+
+        let origin = Location::origin();
+        for &(light, expected) in lights.iter() {
+            let count = 20000;
+            let mut solid_angle = 0.0;
+            for _ in 0..count {
+                let sample = light.sample_direction(origin, 0.0).unwrap();
+                let ray = Ray {
+                    origin,
+                    direction: sample.direction,
+                    time: 0.0,
+                };
+                // The pdf of the direction is the one of the sample and the sample is on the object
+                assert!((light.pdf_value(&ray) - sample.pdf).abs() < 1e-6 * sample.pdf);
+                let hit = light.get_hits(&ray, 0.001, f64::MAX).unwrap();
+                assert!((hit.distance - sample.distance).abs() < 1e-9);
+                solid_angle += 1.0 / sample.pdf;
+            }
+            // The mean of 1 / pdf estimates the solid angle of the object
+            assert!((solid_angle / count as f64 - expected).abs() < 0.02 * expected);
+        }
+
+        // Directions that miss the object have no pdf
+        let ray = Ray {
+            origin,
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        assert_eq!(disk.pdf_value(&ray), 0.0);
+        assert_eq!(quad.pdf_value(&ray), 0.0);
+        assert_eq!(triangle.pdf_value(&ray), 0.0);
+    }
+}
@@ -0,0 +1,438 @@
+//! Objects defined by signed distance functions and rendered by sphere tracing.
+
+use super::sphere::sphere_uv;
+use super::CsgOperation;
+use crate::geometry::*;
+use crate::material::Material;
+use crate::scene::{Hit, Hittable, Ray};
+use std::sync::Arc;
+
+/// Signed distance to a surface, negative inside. The distance may be underestimated but never overestimated,
+/// otherwise the sphere tracing steps through the surface.
+pub trait DistanceField {
+    fn distance(&self, point: Location) -> f64;
+
+    /// Box that contains the whole surface
+    fn bounds(&self) -> Aabb;
+}
+
+/// Box around a center
+fn centered_bounds(center: Location, extent: Direction) -> Aabb {
+    Aabb::new(center - extent, center + extent)
+}
+
+/// Grows the box on all sides
+fn padded(bounds: Aabb, padding: f64) -> Aabb {
+    centered_bounds(bounds.min, Direction::new(padding, padding, padding)).union(&centered_bounds(
+        bounds.max,
+        Direction::new(padding, padding, padding),
+    ))
+}
+
+pub struct SdfSphere {
+    pub center: Location,
+    pub radius: f64,
+}
+
+impl DistanceField for SdfSphere {
+    fn distance(&self, point: Location) -> f64 {
+        (point - self.center).length() - self.radius
+    }
+
+    fn bounds(&self) -> Aabb {
+        centered_bounds(
+            self.center,
+            Direction::new(self.radius, self.radius, self.radius),
+        )
+    }
+}
+
+/// Axis aligned box with edges rounded by `rounding`
+pub struct SdfBox {
+    pub center: Location,
+    /// Half of the size along each axis, including the rounding
+    pub half_size: Direction,
+    pub rounding: f64,
+}
+
+impl DistanceField for SdfBox {
+    fn distance(&self, point: Location) -> f64 {
+        let p = (point - self.center).as_slice();
+        let h = self.half_size.as_slice();
+        let q: Vec<f64> = (0..3)
+            .map(|axis| p[axis].abs() - (h[axis] - self.rounding))
+            .collect();
+        let outside = Direction::new(q[0].max(0.0), q[1].max(0.0), q[2].max(0.0)).length();
+        let inside = q[0].max(q[1]).max(q[2]).min(0.0);
+        outside + inside - self.rounding
+    }
+
+    fn bounds(&self) -> Aabb {
+        centered_bounds(self.center, self.half_size)
+    }
+}
+
+/// Torus around the z axis
+pub struct SdfTorus {
+    pub center: Location,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl DistanceField for SdfTorus {
+    fn distance(&self, point: Location) -> f64 {
+        let p = point - self.center;
+        (p.x().hypot(p.y()) - self.major_radius).hypot(p.z()) - self.minor_radius
+    }
+
+    fn bounds(&self) -> Aabb {
+        let extent = self.major_radius + self.minor_radius;
+        centered_bounds(
+            self.center,
+            Direction::new(extent, extent, self.minor_radius),
+        )
+    }
+}
+
+/// Cylinder with half spheres at both ends
+pub struct SdfCapsule {
+    pub start: Location,
+    pub end: Location,
+    pub radius: f64,
+}
+
+impl DistanceField for SdfCapsule {
+    fn distance(&self, point: Location) -> f64 {
+        let pa = point - self.start;
+        let ba = self.end - self.start;
+        let h = (pa.dot(ba) / ba.dot(ba)).clamp(0.0, 1.0);
+        (pa - ba * h).length() - self.radius
+    }
+
+    fn bounds(&self) -> Aabb {
+        padded(Aabb::new(self.start, self.end), self.radius)
+    }
+}
+
+/// Mandelbulb fractal with the bulb of radius about `scale` around `center`
+pub struct Mandelbulb {
+    pub center: Location,
+    pub scale: f64,
+    /// Exponent of the iteration, 8 gives the classic shape
+    pub power: f64,
+    /// More iterations add finer details
+    pub iterations: u32,
+}
+
+impl DistanceField for Mandelbulb {
+    /// Distance estimation from the derivative of the iteration
+    fn distance(&self, point: Location) -> f64 {
+        let c = (point - self.center) / self.scale;
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = z.length();
+        for _ in 0..self.iterations {
+            if r > 2.0 {
+                break;
+            }
+            // Power of z in spherical coordinates
+            let theta = if r > 0.0 {
+                (z.z() / r).clamp(-1.0, 1.0).acos()
+            } else {
+                0.0
+            };
+            let phi = z.y().atan2(z.x());
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+            let (theta, phi) = (theta * self.power, phi * self.power);
+            z = Direction::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            ) * r.powf(self.power)
+                + c;
+            r = z.length();
+        }
+        if r == 0.0 {
+            return 0.0;
+        }
+        0.5 * r.ln() * r / dr * self.scale
+    }
+
+    fn bounds(&self) -> Aabb {
+        let extent = 1.5 * self.scale;
+        centered_bounds(self.center, Direction::new(extent, extent, extent))
+    }
+}
+
+/// Boolean combination of two distance fields. With a `smoothness` above zero the surfaces blend into
+/// each other in a region of about this size.
+pub struct SdfBlend {
+    pub operation: CsgOperation,
+    pub a: Arc<dyn DistanceField + Send + Sync>,
+    pub b: Arc<dyn DistanceField + Send + Sync>,
+    pub smoothness: f64,
+}
+
+/// Polynomial smooth minimum. Equals `min` for k = 0 and is at most k / 4 smaller.
+pub fn smooth_min(a: f64, b: f64, k: f64) -> f64 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (k - (a - b).abs()).max(0.0) / k;
+    a.min(b) - h * h * k / 4.0
+}
+
+impl DistanceField for SdfBlend {
+    fn distance(&self, point: Location) -> f64 {
+        let a = self.a.distance(point);
+        let b = self.b.distance(point);
+        let k = self.smoothness;
+        match self.operation {
+            CsgOperation::Union => smooth_min(a, b, k),
+            CsgOperation::Intersection => -smooth_min(-a, -b, k),
+            CsgOperation::Difference => -smooth_min(-a, b, k),
+        }
+    }
+
+    fn bounds(&self) -> Aabb {
+        let a = self.a.bounds();
+        match self.operation {
+            CsgOperation::Union => padded(a.union(&self.b.bounds()), self.smoothness),
+            CsgOperation::Intersection => a.intersection(&self.b.bounds()).unwrap_or(a),
+            CsgOperation::Difference => a,
+        }
+    }
+}
+
+/// Distance field given by a function
+pub struct SdfFunction {
+    function: Box<dyn Fn(Location) -> f64 + Send + Sync>,
+    bounds: Aabb,
+}
+
+impl SdfFunction {
+    /// Uses `function` as distance field. The surface has to be inside of `bounds`.
+    pub fn new<F: Fn(Location) -> f64 + Send + Sync + 'static>(bounds: Aabb, function: F) -> Self {
+        Self {
+            function: Box::new(function),
+            bounds,
+        }
+    }
+}
+
+impl DistanceField for SdfFunction {
+    fn distance(&self, point: Location) -> f64 {
+        (self.function)(point)
+    }
+
+    fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+}
+
+/// Object with the surface of a distance field. The ray marches in steps of the distance to the surface until
+/// it is closer than `epsilon`. Normals are computed with finite differences.
+pub struct SdfObject {
+    field: Arc<dyn DistanceField + Send + Sync>,
+    material: Arc<dyn Material + Send + Sync>,
+    /// Distance at which the surface counts as hit
+    epsilon: f64,
+    /// Maximum number of steps before the ray counts as miss
+    max_steps: u32,
+}
+
+impl SdfObject {
+    pub fn new(
+        field: Arc<dyn DistanceField + Send + Sync>,
+        material: Arc<dyn Material + Send + Sync>,
+    ) -> Self {
+        Self {
+            field,
+            material,
+            epsilon: 1e-4,
+            max_steps: 512,
+        }
+    }
+
+    /// Sets the distance at which the surface counts as hit and the maximum number of steps.
+    /// Fractals need small distances and many steps for the fine details.
+    pub fn with_precision(mut self, epsilon: f64, max_steps: u32) -> Self {
+        self.epsilon = epsilon;
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Gradient of the distance field from four samples on a tetrahedron
+    fn normal(&self, point: Location) -> Direction {
+        let h = self.epsilon;
+        let normal = [
+            Direction::new(1.0, -1.0, -1.0),
+            Direction::new(-1.0, -1.0, 1.0),
+            Direction::new(-1.0, 1.0, -1.0),
+            Direction::new(1.0, 1.0, 1.0),
+        ]
+        .iter()
+        .fold(Direction::new(0.0, 0.0, 0.0), |normal, &offset| {
+            normal + offset * self.field.distance(point + offset * h)
+        });
+        normal.norm()
+    }
+}
+
+impl Hittable for SdfObject {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let direction = ray.direction.as_slice();
+        let inv_direction = [1.0 / direction[0], 1.0 / direction[1], 1.0 / direction[2]];
+        let (enter, exit) = self.field.bounds().clip(
+            &ray.origin.as_slice(),
+            &inv_direction,
+            min_distance,
+            max_distance,
+        )?;
+
+        let mut distance = enter;
+        // Rays starting in the bounds mostly start on the surface. They have to leave it before a hit counts,
+        // otherwise grazing rays hit the surface they start on.
+        let mut leaving = enter <= min_distance;
+        let mut sign = 1.0;
+        for _ in 0..self.max_steps {
+            let field = self.field.distance(ray.at(distance));
+            if leaving {
+                if field.abs() < self.epsilon {
+                    distance += self.epsilon;
+                    if distance > exit {
+                        return None;
+                    }
+                    continue;
+                }
+                // Rays starting inside march towards the surface from the other side
+                sign = field.signum();
+                leaving = false;
+            }
+            let step = field * sign;
+            if step < self.epsilon {
+                let normal = self.normal(ray.at(distance));
+                return Some(Hit::new(
+                    ray,
+                    distance,
+                    normal,
+                    sphere_uv(normal),
+                    self.material.as_ref(),
+                ));
+            }
+            distance += step;
+            if distance > exit {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.field.bounds())
+    }
+}
+
+mod test {
+
+    #[test]
+    fn distance_fields() {
+        use super::{DistanceField, SdfBlend, SdfBox, SdfSphere};
+        use crate::geometry::{Direction, Location};
+        use crate::scene::objects::CsgOperation;
+        use std::sync::Arc;
+
+        let sphere = SdfSphere {
+            center: Location::origin(),
+            radius: 1.0,
+        };
+        assert_eq!(sphere.distance(Location::new(3.0, 0.0, 0.0)), 2.0);
+        assert_eq!(sphere.distance(Location::origin()), -1.0);
+
+        let cube = SdfBox {
+            center: Location::new(3.0, 0.0, 0.0),
+            half_size: Direction::new(1.0, 1.0, 1.0),
+            rounding: 0.0,
+        };
+        assert_eq!(cube.distance(Location::new(0.0, 0.0, 0.0)), 2.0);
+        assert!((cube.distance(Location::new(5.0, 2.0, 0.0)) - 2.0_f64.sqrt()).abs() < 1e-12);
+
+        // Between the objects the smooth union is nearer than both
+        let blend = SdfBlend {
+            operation: CsgOperation::Union,
+            a: Arc::new(sphere),
+            b: Arc::new(cube),
+            smoothness: 0.5,
+        };
+        let between = Location::new(1.5, 0.0, 0.0);
+        assert!(blend.distance(between) < 0.5);
+        assert!(blend.distance(between) >= 0.5 - 0.5 / 4.0);
+        assert_eq!(blend.distance(Location::new(-3.0, 0.0, 0.0)), 2.0);
+    }
+
+    #[test]
+    fn sphere_tracing() {
+        use super::{Mandelbulb, SdfFunction, SdfObject};
+        use crate::color::Color;
+        use crate::geometry::{Aabb, Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let material = Arc::new(Lambertian::new(Color::white()));
+        let center = Location::new(5.0, 0.0, 0.0);
+        let sphere = SdfObject::new(
+            Arc::new(SdfFunction::new(
+                Aabb::new(Location::new(4.0, -1.0, -1.0), Location::new(6.0, 1.0, 1.0)),
+                move |point| (point - center).length() - 1.0,
+            )),
+            material.clone(),
+        );
+        let ray = |z: f64| Ray {
+            origin: Location::new(0.0, 0.0, z),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let hit = sphere.get_hits(&ray(0.0), 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 4.0).abs() < 1e-3);
+        assert!((hit.normal - Direction::new(-1.0, 0.0, 0.0)).length() < 1e-3);
+        assert!(sphere.get_hits(&ray(1.1), 0.001, f64::MAX).is_none());
+        // From inside the exit is found
+        let inside = Ray {
+            origin: center,
+            direction: Direction::new(0.0, 0.0, 1.0),
+            time: 0.0,
+        };
+        let hit = sphere.get_hits(&inside, 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 1.0).abs() < 1e-3);
+        // Rays leaving the surface do not hit it again, even if they graze it
+        let normal = Direction::new(-1.0, 0.0, 1.0).norm();
+        let grazing = Ray {
+            origin: center + normal,
+            direction: (Direction::new(1.0, 0.0, 1.0).norm() + normal * 0.001).norm(),
+            time: 0.0,
+        };
+        assert!(sphere.get_hits(&grazing, 0.001, f64::MAX).is_none());
+        // Rays into the object find the other side
+        let through = Ray {
+            origin: center + normal,
+            direction: normal.invert(),
+            time: 0.0,
+        };
+        let hit = sphere.get_hits(&through, 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 2.0).abs() < 1e-3);
+
+        let bulb = SdfObject::new(
+            Arc::new(Mandelbulb {
+                center,
+                scale: 1.0,
+                power: 8.0,
+                iterations: 10,
+            }),
+            material,
+        );
+        let hit = bulb.get_hits(&ray(0.0), 0.001, f64::MAX).unwrap();
+        assert!(hit.distance > 3.5 && hit.distance < 5.0);
+        assert!(bulb.get_hits(&ray(1.6), 0.001, f64::MAX).is_none());
+    }
+}
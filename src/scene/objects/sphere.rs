@@ -0,0 +1,338 @@
+//! Spheres defined by their center and radius.
+
+use crate::geometry::*;
+use crate::material::Material;
+use crate::sampling::{random_in_cone, Onb};
+use crate::scene::{Hit, Hittable, LightSample, Ray, Span, MIN_HIT_DISTANCE};
+use std::sync::Arc;
+
+pub struct Sphere {
+    pub origin: Location,
+    pub radius: f64,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Hittable for Sphere {
+    /// Checks if a ray hits the sphere
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let (distance, normal) =
+            intersect_sphere(self.origin, self.radius, ray, min_distance, max_distance)?;
+        Some(sphere_hit(
+            ray,
+            distance,
+            self.radius,
+            normal,
+            self.material.as_ref(),
+        ))
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        Some(sphere_spans(
+            self.origin,
+            self.radius,
+            ray,
+            self.material.as_ref(),
+        ))
+    }
+
+    /// Samples the cone of directions in which the sphere is visible from the origin
+    fn sample_direction(&self, origin: Location, time: f64) -> Option<LightSample> {
+        sample_sphere(self.origin, self.radius, origin, time)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(sphere_bounds(self.origin, self.radius))
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        sphere_pdf(self.origin, self.radius, ray)
+    }
+}
+
+/// Sphere that moves with constant speed from `start` at `start_time` to `end` at `end_time`.
+/// Before and after this interval the sphere rests at the start and the end.
+pub struct MovingSphere {
+    pub start: Location,
+    pub end: Location,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub radius: f64,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl MovingSphere {
+    /// Center of the sphere at the given time
+    pub fn center(&self, time: f64) -> Location {
+        let duration = self.end_time - self.start_time;
+        let fraction = if duration > 0.0 {
+            ((time - self.start_time) / duration).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        self.start + (self.end - self.start) * fraction
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        let center = self.center(ray.time);
+        let (distance, normal) =
+            intersect_sphere(center, self.radius, ray, min_distance, max_distance)?;
+        Some(sphere_hit(
+            ray,
+            distance,
+            self.radius,
+            normal,
+            self.material.as_ref(),
+        ))
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        Some(sphere_spans(
+            self.center(ray.time),
+            self.radius,
+            ray,
+            self.material.as_ref(),
+        ))
+    }
+
+    fn sample_direction(&self, origin: Location, time: f64) -> Option<LightSample> {
+        sample_sphere(self.center(time), self.radius, origin, time)
+    }
+
+    /// Bounding box of the whole movement
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(sphere_bounds(self.start, self.radius).union(&sphere_bounds(self.end, self.radius)))
+    }
+
+    fn pdf_value(&self, ray: &Ray) -> f64 {
+        sphere_pdf(self.center(ray.time), self.radius, ray)
+    }
+}
+
+/// Returns the distances at which the line of the ray enters and leaves the sphere
+fn sphere_roots(center: Location, radius: f64, ray: &Ray) -> Option<(f64, f64)> {
+    // Algorithm from https://en.wikipedia.org/wiki/Line%E2%80%93sphere_intersection with the sphere transformt to origin
+
+    // Get stuff together
+    let ray_orig = ray.origin - center;
+    let ray_dir = ray.direction;
+    let r = radius;
+
+    // Calculate wether it is a hit or not
+    let d_3 = -ray_dir.dot(ray_orig);
+    let d_1 = d_3.powi(2);
+    let d_2 = ray_orig.length().powi(2) - r.powi(2);
+    let discriminant = d_1 - d_2;
+
+    if discriminant < 0.0 {
+        // No Hits
+        return None;
+    }
+
+    // Safe to call sqrt because we checked the value under the root
+    let discriminant_sqrt = discriminant.sqrt();
+    Some((d_3 - discriminant_sqrt, d_3 + discriminant_sqrt))
+}
+
+/// Normal of the sphere at the point of the ray at the given distance
+fn sphere_normal(center: Location, radius: f64, ray: &Ray, distance: f64) -> Direction {
+    (ray.at(distance) - center) / radius
+}
+
+/// Returns the distance and the normal of the first hit of the ray with the sphere in the given range
+fn intersect_sphere(
+    center: Location,
+    radius: f64,
+    ray: &Ray,
+    min_distance: f64,
+    max_distance: f64,
+) -> Option<(f64, Direction)> {
+    let (near, far) = sphere_roots(center, radius, ray)?;
+    // Up to two Hits --> Return the one with the lowest Vlaue in the valid range
+    let distance = [near, far]
+        .iter()
+        .copied()
+        .find(|distance| *distance > min_distance && *distance < max_distance)?;
+    Some((distance, sphere_normal(center, radius, ray, distance)))
+}
+
+/// The interval inside of the sphere, if the line of the ray hits it
+fn sphere_spans<'a>(
+    center: Location,
+    radius: f64,
+    ray: &Ray,
+    material: &'a dyn Material,
+) -> Vec<Span<'a>> {
+    let hit = |distance: f64| {
+        sphere_hit(
+            ray,
+            distance,
+            radius,
+            sphere_normal(center, radius, ray, distance),
+            material,
+        )
+    };
+    sphere_roots(center, radius, ray)
+        .map(|(near, far)| Span {
+            enter: hit(near),
+            exit: hit(far),
+        })
+        .into_iter()
+        .collect()
+}
+
+/// Hit with the texture coordinates and tangent of the sphere
+fn sphere_hit<'a>(
+    ray: &Ray,
+    distance: f64,
+    radius: f64,
+    normal: Direction,
+    material: &'a dyn Material,
+) -> Hit<'a> {
+    let ring = normal.x().hypot(normal.y());
+    Hit::new(ray, distance, normal, sphere_uv(normal), material)
+        .with_tangent(Direction::new(-normal.y(), normal.x(), 0.0))
+        .with_uv_scale(
+            2.0 * std::f64::consts::PI * radius * ring,
+            std::f64::consts::PI * radius,
+        )
+}
+
+/// Texture coordinates of a point on the unit sphere. u goes around the z axis starting at -x,
+/// v goes from the bottom to the top.
+pub(super) fn sphere_uv(normal: Direction) -> (f64, f64) {
+    let phi = normal.y().atan2(normal.x()) + std::f64::consts::PI;
+    let theta = (-normal.z()).clamp(-1.0, 1.0).acos();
+    (
+        phi / (2.0 * std::f64::consts::PI),
+        theta / std::f64::consts::PI,
+    )
+}
+
+fn sphere_bounds(center: Location, radius: f64) -> Aabb {
+    let r = Direction::new(radius, radius, radius);
+    Aabb::new(center - r, center + r)
+}
+
+/// Samples the cone of directions in which the sphere is visible from the origin
+fn sample_sphere(
+    center: Location,
+    radius: f64,
+    origin: Location,
+    time: f64,
+) -> Option<LightSample> {
+    let to_center = center - origin;
+    let cos_theta_max = cos_theta_max(radius, to_center)?;
+    let (a, b, c) = random_in_cone(cos_theta_max);
+    let direction = Onb::from_w(to_center.norm()).local(a, b, c).norm();
+    let ray = Ray {
+        origin,
+        direction,
+        time,
+    };
+    // At the edge of the cone the ray can miss the sphere due to rounding
+    let (distance, _) = intersect_sphere(center, radius, &ray, MIN_HIT_DISTANCE, f64::MAX)?;
+    Some(LightSample {
+        direction,
+        distance,
+        pdf: cone_pdf(cos_theta_max),
+    })
+}
+
+/// Pdf of `sample_sphere` for the direction of the ray
+fn sphere_pdf(center: Location, radius: f64, ray: &Ray) -> f64 {
+    match cos_theta_max(radius, center - ray.origin) {
+        Some(cos_theta_max)
+            if intersect_sphere(center, radius, ray, MIN_HIT_DISTANCE, f64::MAX).is_some() =>
+        {
+            cone_pdf(cos_theta_max)
+        }
+        _ => 0.0,
+    }
+}
+
+/// Cosine of the half opening angle of the cone the sphere covers, seen from a point with
+/// the given offset to the center. None if the point is inside the sphere.
+fn cos_theta_max(radius: f64, to_center: Direction) -> Option<f64> {
+    let distance_squared = to_center.length().powi(2);
+    let radius_squared = radius.powi(2);
+    if distance_squared <= radius_squared {
+        None
+    } else {
+        Some((1.0 - radius_squared / distance_squared).sqrt())
+    }
+}
+
+/// Pdf of uniformly sampling a direction in the cone
+fn cone_pdf(cos_theta_max: f64) -> f64 {
+    1.0 / (2.0 * std::f64::consts::PI * (1.0 - cos_theta_max))
+}
+
+mod test {
+
+    #[test]
+    fn moving_sphere_follows_time() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let sphere = super::MovingSphere {
+            start: Location::new(5.0, 0.0, 0.0),
+            end: Location::new(5.0, 4.0, 0.0),
+            start_time: 0.0,
+            end_time: 1.0,
+            radius: 1.0,
+            material: Arc::new(Lambertian::new(Color::white())),
+        };
+        let ray = |y: f64, time: f64| Ray {
+            origin: Location::new(0.0, y, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time,
+        };
+        assert!(sphere.get_hits(&ray(0.0, 0.0), 0.001, f64::MAX).is_some());
+        assert!(sphere.get_hits(&ray(0.0, 1.0), 0.001, f64::MAX).is_none());
+        assert!(sphere.get_hits(&ray(2.0, 0.5), 0.001, f64::MAX).is_some());
+        // The sphere rests after the end of the movement
+        assert!(sphere.get_hits(&ray(4.0, 2.0), 0.001, f64::MAX).is_some());
+
+        let bounds = sphere.bounding_box().unwrap();
+        assert_eq!(bounds.min.y(), -1.0);
+        assert_eq!(bounds.max.y(), 5.0);
+    }
+
+    #[test]
+    fn hit_frame() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let sphere = super::Sphere {
+            origin: Location::new(5.0, 0.0, 0.0),
+            radius: 1.0,
+            material: Arc::new(Lambertian::new(Color::white())),
+        };
+        let ray = Ray {
+            origin: Location::origin(),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let hit = sphere.get_hits(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.point - Location::new(4.0, 0.0, 0.0)).length() < 1e-12);
+        assert!(hit.front_face);
+        // u grows counter clockwise around the z axis
+        assert!((hit.tangent - Direction::new(0.0, -1.0, 0.0)).length() < 1e-12);
+        // u goes around the equator and v from pole to pole
+        let pi = std::f64::consts::PI;
+        assert!((hit.uv_scale.0 - 2.0 * pi).abs() < 1e-12 && (hit.uv_scale.1 - pi).abs() < 1e-12);
+
+        // The exit seen from inside
+        let hit = sphere.get_hits(&ray, 4.5, f64::MAX).unwrap();
+        assert!(!hit.front_face);
+        assert!((hit.facing_normal() - Direction::new(-1.0, 0.0, 0.0)).length() < 1e-12);
+    }
+}
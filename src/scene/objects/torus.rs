@@ -0,0 +1,294 @@
+//! Torus around an arbitrary axis.
+
+use super::{azimuth, local_ray, nearest_hit};
+use crate::geometry::*;
+use crate::material::Material;
+use crate::sampling::Onb;
+use crate::scene::{Hit, Hittable, Ray, Span};
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Values smaller than this are treated as zero by the polynomial solvers
+const EPSILON: f64 = 1e-12;
+
+/// Real roots of x² + p x + q
+fn solve_quadratic(p: f64, q: f64) -> Vec<f64> {
+    let discriminant = p * p / 4.0 - q;
+    if discriminant.abs() < EPSILON {
+        vec![-p / 2.0]
+    } else if discriminant < 0.0 {
+        vec![]
+    } else {
+        let root = discriminant.sqrt();
+        vec![-p / 2.0 - root, -p / 2.0 + root]
+    }
+}
+
+/// Real roots of x³ + a x² + b x + c with the formula of Cardano
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // Substitute x = y - a/3 to eliminate the quadratic term: y³ + 3 p y + 2 q
+    let p = (b - a * a / 3.0) / 3.0;
+    let q = (2.0 / 27.0 * a * a * a - a * b / 3.0 + c) / 2.0;
+    let p3 = p * p * p;
+    let discriminant = q * q + p3;
+
+    let roots = if discriminant.abs() < EPSILON {
+        if q.abs() < EPSILON {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        // Three real roots
+        let phi = (-q / (-p3).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![
+            t * phi.cos(),
+            -t * (phi + PI / 3.0).cos(),
+            -t * (phi - PI / 3.0).cos(),
+        ]
+    } else {
+        let root = discriminant.sqrt();
+        vec![(root - q).cbrt() - (root + q).cbrt()]
+    };
+    roots.into_iter().map(|y| y - a / 3.0).collect()
+}
+
+/// Real roots of x⁴ + a x³ + b x² + c x + d with the method of Ferrari, sorted ascending
+fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // Substitute x = y - a/4 to eliminate the cubic term: y⁴ + p y² + q y + r
+    let a2 = a * a;
+    let p = -3.0 / 8.0 * a2 + b;
+    let q = a2 * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * a2 * a2 + a2 * b / 16.0 - a * c / 4.0 + d;
+
+    let mut roots = if r.abs() < EPSILON {
+        // y (y³ + p y + q) = 0
+        let mut roots = solve_cubic(0.0, p, q);
+        roots.push(0.0);
+        roots
+    } else {
+        // Any real root of the resolvent cubic splits the quartic into two quadratics
+        let z = solve_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0)[0];
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if u.abs() < EPSILON {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if v.abs() < EPSILON {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return vec![];
+        };
+        let v = if q < 0.0 { -v } else { v };
+        let mut roots = solve_quadratic(v, z - u);
+        roots.extend(solve_quadratic(-v, z + u));
+        roots
+    };
+
+    // Polish the roots with the original polynomial, the closed form loses precision
+    for root in roots.iter_mut() {
+        let mut x = *root - a / 4.0;
+        for _ in 0..2 {
+            let f = (((x + a) * x + b) * x + c) * x + d;
+            let df = ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+            if df.abs() > EPSILON {
+                x -= f / df;
+            }
+        }
+        *root = x;
+    }
+    // Degenerate rays, for example with a zero direction, can produce NaN
+    roots.retain(|root| root.is_finite());
+    roots.sort_by(|a, b| a.total_cmp(b));
+    roots
+}
+
+/// Ring around `axis` through `center`. The tube with `minor_radius` follows a circle with `major_radius`.
+/// u goes around the axis and v around the tube.
+pub struct Torus {
+    pub center: Location,
+    /// Normalized direction of the axis
+    pub axis: Direction,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    pub material: Arc<dyn Material + Send + Sync>,
+}
+
+impl Hittable for Torus {
+    fn get_hits(&self, ray: &Ray, min_distance: f64, max_distance: f64) -> Option<Hit<'_>> {
+        nearest_hit(&self.get_spans(ray)?, min_distance, max_distance)
+    }
+
+    fn get_spans(&self, ray: &Ray) -> Option<Vec<Span<'_>>> {
+        let onb = Onb::from_w(self.axis);
+        let (o, d) = local_ray(&onb, self.center, ray);
+        // Start at the point nearest to the center to keep the coefficients small
+        let offset = -o.dot(d);
+        let o = o + d * offset;
+
+        // Insert the ray into (x² + y² + z² + R² - r²)² = 4 R² (x² + y²)
+        let r2 = self.major_radius * self.major_radius;
+        let e = o.dot(o) + r2 - self.minor_radius * self.minor_radius;
+        let f = o.dot(d);
+        let dxy = d.x() * d.x() + d.y() * d.y();
+        let roots = solve_quartic(
+            4.0 * f,
+            4.0 * f * f + 2.0 * e - 4.0 * r2 * dxy,
+            4.0 * f * e - 8.0 * r2 * (o.x() * d.x() + o.y() * d.y()),
+            e * e - 4.0 * r2 * (o.x() * o.x() + o.y() * o.y()),
+        );
+
+        let hit = |t: f64| {
+            let point = o + d * t;
+            // Direction from the center of the tube
+            let ring = point.x().hypot(point.y());
+            let normal = if ring > 0.0 {
+                Direction::new(
+                    point.x() * (1.0 - self.major_radius / ring),
+                    point.y() * (1.0 - self.major_radius / ring),
+                    point.z(),
+                )
+            } else {
+                Direction::new(0.0, 0.0, point.z())
+            };
+            let uv = (
+                azimuth(point.x(), point.y()),
+                azimuth(ring - self.major_radius, point.z()),
+            );
+            Hit::new(
+                ray,
+                t + offset,
+                onb.local(normal.x(), normal.y(), normal.z()).norm(),
+                uv,
+                self.material.as_ref(),
+            )
+            .with_tangent(onb.local(-point.y(), point.x(), 0.0))
+            .with_uv_scale(
+                2.0 * std::f64::consts::PI * ring,
+                2.0 * std::f64::consts::PI * self.minor_radius,
+            )
+        };
+        // Tangent roots can appear once or twice, so check which parts between the roots are inside of the tube
+        let inside = |t: f64| {
+            let point = o + d * t;
+            let ring = point.x().hypot(point.y()) - self.major_radius;
+            ring * ring + point.z() * point.z() < self.minor_radius * self.minor_radius
+        };
+        let mut spans = Vec::new();
+        let mut enter = None;
+        for pair in roots.windows(2) {
+            if inside((pair[0] + pair[1]) / 2.0) {
+                enter.get_or_insert(pair[0]);
+            } else if let Some(enter) = enter.take() {
+                spans.push(Span {
+                    enter: hit(enter),
+                    exit: hit(pair[0]),
+                });
+            }
+        }
+        if let (Some(enter), Some(&exit)) = (enter, roots.last()) {
+            spans.push(Span {
+                enter: hit(enter),
+                exit: hit(exit),
+            });
+        }
+        Some(spans)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let extent = |a: f64| self.major_radius * (1.0 - a * a).max(0.0).sqrt() + self.minor_radius;
+        let axis = self.axis;
+        let extent = Direction::new(extent(axis.x()), extent(axis.y()), extent(axis.z()));
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+}
+
+mod test {
+
+    #[test]
+    fn quartic_roots() {
+        // (x - 1)(x + 2)(x - 3)(x - 0.5)
+        let roots = super::solve_quartic(-2.5, -4.0, 8.5, -3.0);
+        let expected = [-2.0, 0.5, 1.0, 3.0];
+        assert_eq!(roots.len(), 4);
+        for (root, expected) in roots.iter().zip(expected.iter()) {
+            assert!((root - expected).abs() < 1e-9);
+        }
+        // x⁴ + 1 has no real roots
+        assert!(super::solve_quartic(0.0, 0.0, 0.0, 1.0).is_empty());
+        // NaN coefficients give no roots instead of a panic
+        assert!(super::solve_quartic(f64::NAN, 1.0, f64::NAN, 1.0).is_empty());
+    }
+
+    #[test]
+    fn torus_spans() {
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::{Hittable, Ray};
+        use std::sync::Arc;
+
+        let torus = super::Torus {
+            center: Location::new(10.0, 0.0, 0.0),
+            axis: Direction::new(0.0, 0.0, 1.0),
+            major_radius: 2.0,
+            minor_radius: 0.5,
+            material: Arc::new(Lambertian::new(Color::white())),
+        };
+        // Through both sides of the ring
+        let ray = Ray {
+            origin: Location::origin(),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let spans = torus.get_spans(&ray).unwrap();
+        let distances: Vec<f64> = spans
+            .iter()
+            .flat_map(|span| vec![span.enter.distance, span.exit.distance])
+            .collect();
+        assert_eq!(distances.len(), 4);
+        for (distance, expected) in distances.iter().zip([7.5, 8.5, 11.5, 12.5].iter()) {
+            assert!((distance - expected).abs() < 1e-9);
+        }
+        assert!((spans[0].enter.normal - Direction::new(-1.0, 0.0, 0.0)).length() < 1e-9);
+        assert!((spans[0].exit.normal - Direction::new(1.0, 0.0, 0.0)).length() < 1e-9);
+        // Through the hole
+        let ray = Ray {
+            origin: Location::new(10.0, 0.0, 5.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        assert!(torus.get_hits(&ray, 0.001, f64::MAX).is_none());
+        // Onto the top of the tube
+        let ray = Ray {
+            origin: Location::new(12.0, 0.0, 5.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let hit = torus.get_hits(&ray, 0.001, f64::MAX).unwrap();
+        assert!((hit.distance - 4.5).abs() < 1e-9);
+        assert!((hit.normal - Direction::new(0.0, 0.0, 1.0)).length() < 1e-9);
+
+        // Grazing the top of the tube touches it on both sides of the hole without entering
+        let ray = Ray {
+            origin: Location::new(0.0, 0.0, 0.5),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let spans = torus.get_spans(&ray).unwrap();
+        assert!(spans
+            .iter()
+            .all(|span| span.exit.distance - span.enter.distance < 1e-3));
+
+        let bounds = torus.bounding_box().unwrap();
+        assert!((bounds.max.x() - 12.5).abs() < 1e-12 && (bounds.max.z() - 0.5).abs() < 1e-12);
+    }
+}
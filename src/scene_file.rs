@@ -0,0 +1,2708 @@
+//! Loader for scene description files in the toml format.
+//!
+//! A scene file describes the image, the camera, the background, named materials, the objects and the lights:
+//!
+//! ```toml
+//! [image]
+//! width = 800
+//! height = 600
+//! samples = 100
+//! max_depth = 50
+//! stereo_layout = "side-by-side"
+//!
+//! [camera]
+//! projection = "perspective"
+//! position = [0.0, 0.0, 0.0]
+//! look_at = [1.0, 0.0, 0.0]
+//! up = [0.0, 0.0, 1.0]
+//! vfov = 90.0
+//! roll = 0.0
+//! aperture = 0.0
+//! focus_distance = 1.0
+//! blades = 0
+//!
+//! shutter = [0.0, 0.0]
+//!
+//! [camera.stereo]
+//! interocular = 0.065
+//! convergence = 1.0
+//!
+//! [background]
+//! type = "sky"
+//!
+//! [materials.blue]
+//! type = "lambertian"
+//! color = [0.1, 0.2, 0.5]
+//!
+//! [materials.floor]
+//! type = "lambertian"
+//!
+//! [materials.floor.texture]
+//! type = "checker"
+//! even = [1.0, 1.0, 1.0]
+//! odd = [0.0, 0.0, 0.0]
+//! frequency = 10.0
+//!
+//! [[objects]]
+//! type = "sphere"
+//! center = [1.0, 0.0, 0.0]
+//! radius = 0.5
+//! material = "blue"
+//!
+//! [[objects]]
+//! type = "plane"
+//! point = [0.0, 0.0, -0.5]
+//! normal = [0.0, 0.0, 1.0]
+//! material = "blue"
+//!
+//! [[objects]]
+//! type = "csg"
+//! operation = "difference"
+//!
+//! [objects.left]
+//! type = "sphere"
+//! center = [1.0, 0.0, 1.0]
+//! radius = 0.5
+//! material = "blue"
+//!
+//! [objects.right]
+//! type = "sphere"
+//! center = [0.6, 0.0, 1.0]
+//! radius = 0.3
+//! material = "blue"
+//!
+//! [[groups]]
+//! name = "table"
+//! translate = [2.0, 0.0, 0.0]
+//! rotate = [0.0, 0.0, 45.0]
+//! scale = [1.0, 1.0, 1.0]
+//! material = "blue"
+//! visible = true
+//!
+//! [[groups.objects]]
+//! type = "obj"
+//! file = "table.obj"
+//!
+//! [[lights]]
+//! type = "sphere"
+//! center = [1.0, 0.0, 2.0]
+//! radius = 0.2
+//! color = [1.0, 1.0, 1.0]
+//! intensity = 10.0
+//! ```
+//!
+//! Spheres, moving spheres, triangles, disks and quads with `light = true` are sampled directly like the
+//! `[[lights]]`, which reduces the noise of small lights. Their material should be of type `light`.
+//!
+//! The other object types are `moving_sphere`, `triangle`, `disk`, `quad`, `box`, `cylinder`, `cone`, `torus`,
+//! `sdf`, `obj`, `medium` and `voxels`.
+//!
+//! A `medium` fills a closed `boundary` object with a constant `density`. The boundary can not be a medium. The material of the boundary is used
+//! inside, mostly of type `volume` with a `color` and an `anisotropy` between -1 and 1. Positive values scatter
+//! the light forward, negative values backward. A `[fog]` table with the same fields and a `density` fills the
+//! space in front of the objects, the background is not covered by it.
+//!
+//! `voxels` is a medium with the densities of a voxel grid `file` that fills the box from `min` to `max`. Files
+//! with a `resolution` are raw files of densities, others grid files that can contain temperatures as well, see
+//! the `voxel` module. `density` and `temperature_scale` scale the values of the file. The `emission` of a
+//! `volume` material is a texture, the `blackbody` texture turns the temperatures into the light of a fire.
+//!
+//! Groups can contain objects and further groups. The transformation, the material and the visibility of
+//! a group apply to everything in it, the material of an inner group wins. Lights and media keep their own
+//! materials. A `motion` table moves a group for
+//! motion blur. Its `translate`, `rotate` and `scale` are reached at the `end_time`, the group starts from its
+//! own values at the `start_time`. Missing values do not change.
+//!
+//! Textures are `constant`, `checker` or `image` with a `file`, a `wrap` mode (`repeat`, `mirrored-repeat`
+//! or `clamp`) and a `filter` (`bilinear` or `nearest`). A string instead of a table is the file of an image.
+//! The solid textures `noise`, `marble`, `wood` and `worley` ignore the texture coordinates and use the
+//! hitpoint in the world. They have a `seed` and a `scale` that sets the size of the pattern.
+//!
+//! A `conductor` is a physically based rough metal. Its complex index of refraction is a `preset` (`gold`,
+//! `copper`, `aluminium` or `silver`) or given as `eta` and `k` for red, green and blue. The `roughness` is
+//! between 0 and 1, the `anisotropy` between -1 and 1 stretches the highlights along the texture coordinates.
+//!
+//! Lambertian, metal, conductor and dielectric materials can have a `normal_map`. It is of type `normal` with a texture
+//! of tangent space normals or of type `bump` with a texture of heights, both with a `strength`. The strength of a
+//! bump map is the height of white in world units. A string is the file of a normal map image. Images of normal
+//! maps are not gamma corrected.
+//!
+//! Relative paths of textures and obj files are relative to the scene file.
+
+use crate::camera::{
+    Camera, EquirectangularCamera, FisheyeCamera, OrthographicCamera, PerspectiveCamera,
+    StereoCamera, StereoLayout,
+};
+use crate::color::Color;
+use crate::geometry::{Aabb, Direction, Location, Motion, Pose, Quaternion};
+use crate::material::{
+    Conductor, Dielectric, DiffuseLight, Lambertian, Material, Metal, Microfacet, NormalMapped,
+    PhaseFunction, Volume,
+};
+use crate::obj::load_obj;
+use crate::scene::graph::SceneNode;
+use crate::scene::objects::{
+    Cone, ConstantMedium, Csg, CsgOperation, Cuboid, Cylinder, Disk, DistanceField, GridMedium,
+    Mandelbulb, MovingSphere, Plane, Quad, SdfBlend, SdfBox, SdfCapsule, SdfObject, SdfSphere,
+    SdfTorus, Sphere, Torus, Triangle,
+};
+use crate::scene::{Background, Fog, Hittable, World};
+use crate::texture::{
+    BlackbodyTexture, CheckerTexture, Filter, ImageTexture, MarbleTexture, NoiseMode, NoiseTexture,
+    NormalMap, Perlin, Texture, WoodTexture, Worley, WorleyMode, WorleyTexture, WrapMode,
+};
+use crate::voxel::{load_raw_voxels, load_voxels};
+use serde::de::value::{MapAccessDeserializer, MapDeserializer};
+use serde::de::{Error, MapAccess, Unexpected, Visitor};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Error while loading a scene file
+#[derive(Debug)]
+pub enum SceneError {
+    /// The file could not be read
+    Io(PathBuf, std::io::Error),
+    /// The file is no valid toml or does not match the expected structure.
+    /// The message contains the line and the offending key.
+    Parse(toml::de::Error),
+    /// A value is not valid, for example an unknown material name
+    Invalid {
+        /// Path of the offending key, for example `objects[2].material`
+        key: String,
+        message: String,
+    },
+}
+
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SceneError::Io(path, error) => write!(f, "{}: {}", path.display(), error),
+            SceneError::Parse(error) => write!(f, "{}", error),
+            SceneError::Invalid { key, message } => write!(f, "{}: {}", key, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+/// Settings for rendering the image
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct RenderSettings {
+    /// Width of the image in pixel
+    pub width: u32,
+    /// Height of the image in pixel
+    pub height: u32,
+    /// Number of rays per pixel
+    pub samples: u32,
+    /// Maximum number of bounces of a ray
+    pub max_depth: u32,
+    /// How the images of a stereo camera are combined
+    pub stereo_layout: StereoLayout,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 600,
+            samples: 100,
+            max_depth: 50,
+            stereo_layout: StereoLayout::SideBySide,
+        }
+    }
+}
+
+/// Everything needed to render an image
+pub struct Scene {
+    pub world: World,
+    pub camera: Arc<dyn Camera + Send + Sync>,
+    /// Cameras for the eyes, if the scene is rendered in stereo
+    pub stereo: Option<StereoCamera>,
+    pub settings: RenderSettings,
+}
+
+/// Loads a scene file. The settings of the returned description can be changed before the scene is build.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<SceneDescription, SceneError> {
+    let path = path.as_ref();
+    let source =
+        std::fs::read_to_string(path).map_err(|e| SceneError::Io(path.to_path_buf(), e))?;
+    parse_scene(&source, path.parent().unwrap_or_else(|| Path::new("")))
+}
+
+/// Parses the content of a scene file. Relative paths in the file are relative to `base_dir`.
+pub fn parse_scene(source: &str, base_dir: &Path) -> Result<SceneDescription, SceneError> {
+    let mut description: SceneDescription = toml::from_str(source).map_err(SceneError::Parse)?;
+    description.base_dir = base_dir.to_path_buf();
+    Ok(description)
+}
+
+impl SceneDescription {
+    /// Creates the world and the camera
+    pub fn build(&self) -> Result<Scene, SceneError> {
+        build_scene(self)
+    }
+}
+
+/// Creates the world and the camera of a scene description
+fn build_scene(file: &SceneDescription) -> Result<Scene, SceneError> {
+    let base_dir = file.base_dir.as_path();
+
+    // Create the materials
+    let mut materials: BTreeMap<&str, Arc<dyn Material + Send + Sync>> = BTreeMap::new();
+    for (name, description) in &file.materials {
+        let material = description
+            .build(base_dir)
+            .map_err(|message| invalid(format!("materials.{}", name), message))?;
+        materials.insert(name, material);
+    }
+
+    // Create the objects from the scene graph
+    let mut builder = ObjectBuilder {
+        base_dir,
+        materials,
+        meshes: BTreeMap::new(),
+    };
+    let mut root = SceneNode::new("");
+    builder.add_objects(&mut root, &file.objects, "objects")?;
+    for (index, description) in file.groups.iter().enumerate() {
+        let child = builder.group(description, format!("groups[{}]", index))?;
+        root.children.push(child);
+    }
+    let objects = root.flatten();
+
+    // Create the lights, the objects marked as light are sampled as well
+    let mut lights = root.flatten_lights();
+    for description in &file.lights {
+        match description {
+            LightDescription::Sphere(l) => lights.push(Arc::new(Sphere {
+                origin: location(l.center),
+                radius: l.radius,
+                material: Arc::new(DiffuseLight::new(color(l.color), l.intensity)),
+            })),
+        }
+    }
+
+    let settings = file.settings;
+    if settings.width == 0 || settings.height == 0 {
+        return Err(invalid(
+            "image".into(),
+            "Width and height must be greater than zero".into(),
+        ));
+    }
+    if settings.samples == 0 {
+        return Err(invalid(
+            "image.samples".into(),
+            "Samples must be greater than zero".into(),
+        ));
+    }
+    let (camera, stereo) = file.camera.build(settings)?;
+
+    let fog = match &file.fog {
+        Some(fog) => {
+            positive(fog.density, "fog.density".into(), "Density")?;
+            let phase = phase_function(fog.anisotropy)
+                .map_err(|message| invalid("fog.anisotropy".into(), message))?;
+            Some(Fog {
+                density: fog.density,
+                material: Arc::new(Volume::new(color(fog.color), phase)),
+            })
+        }
+        None => None,
+    };
+
+    let mut world = World::new(objects, lights, file.background.build());
+    world.set_max_depth(settings.max_depth);
+    world.set_fog(fog);
+
+    Ok(Scene {
+        world,
+        camera,
+        stereo,
+        settings,
+    })
+}
+
+type Objects = Vec<Arc<dyn Hittable + Send + Sync>>;
+
+/// Creates the objects of the scene
+struct ObjectBuilder<'a> {
+    base_dir: &'a Path,
+    materials: BTreeMap<&'a str, Arc<dyn Material + Send + Sync>>,
+    /// Meshes of the loaded obj files by path and material, so a file used in several groups is loaded once
+    meshes: BTreeMap<(PathBuf, Option<String>), Objects>,
+}
+
+impl ObjectBuilder<'_> {
+    fn material(
+        &self,
+        name: &str,
+        key: String,
+    ) -> Result<Arc<dyn Material + Send + Sync>, SceneError> {
+        self.materials
+            .get(name)
+            .cloned()
+            .ok_or_else(|| invalid(key, format!("Unknown material \"{}\"", name)))
+    }
+
+    /// Creates a node of the scene graph for a group and its children
+    fn group(
+        &mut self,
+        description: &GroupDescription,
+        key: String,
+    ) -> Result<SceneNode, SceneError> {
+        if description.scale.contains(&0.0) {
+            return Err(invalid(key + ".scale", "Scale must not be zero".into()));
+        }
+        let pose = |translate: [f64; 3], rotate: [f64; 3], scale: [f64; 3]| {
+            Pose::new(
+                direction(translate),
+                Quaternion::from_euler(rotate[0], rotate[1], rotate[2]),
+                direction(scale),
+            )
+        };
+        let start = pose(description.translate, description.rotate, description.scale);
+        let mut node = SceneNode::new(description.name.clone()).with_transform(start.transform());
+        node.visible = description.visible;
+        if let Some(motion) = &description.motion {
+            let scale = motion.scale.unwrap_or(description.scale);
+            if (0..3).any(|axis| scale[axis] * description.scale[axis] <= 0.0) {
+                return Err(invalid(
+                    key + ".motion.scale",
+                    "Scale must not be zero or change its sign".into(),
+                ));
+            }
+            if motion.end_time < motion.start_time {
+                return Err(invalid(
+                    key + ".motion.end_time",
+                    "The motion has to start before it ends".into(),
+                ));
+            }
+            node.motion = Some(Motion {
+                start,
+                end: pose(
+                    motion.translate.unwrap_or(description.translate),
+                    motion.rotate.unwrap_or(description.rotate),
+                    scale,
+                ),
+                start_time: motion.start_time,
+                end_time: motion.end_time,
+            });
+        }
+        if let Some(material) = &description.material {
+            node.material = Some(self.material(material, key.clone() + ".material")?);
+        }
+        self.add_objects(&mut node, &description.objects, &(key.clone() + ".objects"))?;
+        for (index, child) in description.groups.iter().enumerate() {
+            let child = self.group(child, format!("{}.groups[{}]", key, index))?;
+            node.children.push(child);
+        }
+        Ok(node)
+    }
+
+    /// Creates the objects and adds them to the node. `key` names the list in error messages.
+    fn add_objects(
+        &mut self,
+        node: &mut SceneNode,
+        descriptions: &[ObjectDescription],
+        key: &str,
+    ) -> Result<(), SceneError> {
+        for (index, description) in descriptions.iter().enumerate() {
+            let objects = self.object(description, format!("{}[{}]", key, index))?;
+            if description.is_light() {
+                node.lights.extend(objects);
+            } else {
+                node.objects.extend(objects);
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates an object. Obj files can contain several meshes.
+    fn object(
+        &mut self,
+        description: &ObjectDescription,
+        key: String,
+    ) -> Result<Objects, SceneError> {
+        let object: Arc<dyn Hittable + Send + Sync> = match description {
+            ObjectDescription::Sphere(o) => Arc::new(Sphere {
+                origin: location(o.center),
+                radius: positive(o.radius, key.clone() + ".radius", "The radius")?,
+                material: self.material(&o.material, key + ".material")?,
+            }),
+            ObjectDescription::MovingSphere(o) => Arc::new(MovingSphere {
+                start: location(o.center),
+                end: location(o.end_center),
+                start_time: o.start_time,
+                end_time: o.end_time,
+                radius: positive(o.radius, key.clone() + ".radius", "The radius")?,
+                material: self.material(&o.material, key + ".material")?,
+            }),
+            ObjectDescription::Triangle(o) => Arc::new(Triangle {
+                vertices: [
+                    location(o.vertices[0]),
+                    location(o.vertices[1]),
+                    location(o.vertices[2]),
+                ],
+                material: self.material(&o.material, key + ".material")?,
+            }),
+            ObjectDescription::Plane(o) => Arc::new(Plane {
+                point: location(o.point),
+                normal: unit(o.normal, key.clone() + ".normal")?,
+                material: self.material(&o.material, key + ".material")?,
+            }),
+            ObjectDescription::Disk(o) => Arc::new(Disk {
+                center: location(o.center),
+                normal: unit(o.normal, key.clone() + ".normal")?,
+                radius: positive(o.radius, key.clone() + ".radius", "The radius")?,
+                material: self.material(&o.material, key + ".material")?,
+            }),
+            ObjectDescription::Quad(o) => {
+                let area = direction(o.u).cross(direction(o.v)).length();
+                if area == 0.0 || !area.is_finite() {
+                    return Err(invalid(
+                        key + ".v",
+                        "The edges u and v must not be zero or parallel".into(),
+                    ));
+                }
+                Arc::new(Quad {
+                    corner: location(o.corner),
+                    u: direction(o.u),
+                    v: direction(o.v),
+                    material: self.material(&o.material, key + ".material")?,
+                })
+            }
+            ObjectDescription::Box(o) => {
+                if !(0..3).all(|axis| o.min[axis] < o.max[axis]) {
+                    return Err(invalid(
+                        key + ".max",
+                        "Max must be larger than min on every axis".into(),
+                    ));
+                }
+                Arc::new(Cuboid {
+                    min: location(o.min),
+                    max: location(o.max),
+                    material: self.material(&o.material, key + ".material")?,
+                })
+            }
+            ObjectDescription::Cylinder(o) => {
+                if o.base == o.top {
+                    return Err(invalid(key + ".top", "Base and top must differ".into()));
+                }
+                Arc::new(Cylinder {
+                    base: location(o.base),
+                    top: location(o.top),
+                    radius: positive(o.radius, key.clone() + ".radius", "The radius")?,
+                    material: self.material(&o.material, key + ".material")?,
+                })
+            }
+            ObjectDescription::Cone(o) => {
+                if o.base == o.apex {
+                    return Err(invalid(key + ".apex", "Base and apex must differ".into()));
+                }
+                Arc::new(Cone {
+                    base: location(o.base),
+                    apex: location(o.apex),
+                    radius: positive(o.radius, key.clone() + ".radius", "The radius")?,
+                    material: self.material(&o.material, key + ".material")?,
+                })
+            }
+            ObjectDescription::Torus(o) => Arc::new(Torus {
+                center: location(o.center),
+                axis: unit(o.axis, key.clone() + ".axis")?,
+                major_radius: positive(
+                    o.major_radius,
+                    key.clone() + ".major_radius",
+                    "The radius",
+                )?,
+                minor_radius: positive(
+                    o.minor_radius,
+                    key.clone() + ".minor_radius",
+                    "The radius",
+                )?,
+                material: self.material(&o.material, key + ".material")?,
+            }),
+            ObjectDescription::Sdf(o) => Arc::new(
+                SdfObject::new(
+                    o.shape.build(),
+                    self.material(&o.material, key + ".material")?,
+                )
+                .with_precision(o.epsilon, o.max_steps),
+            ),
+            ObjectDescription::Obj(o) => return self.obj(o, key),
+            ObjectDescription::Csg(o) => Arc::new(Csg::new(
+                o.operation,
+                self.closed_object(&o.left, key.clone() + ".left", "CSG")?,
+                self.closed_object(&o.right, key + ".right", "CSG")?,
+            )),
+            ObjectDescription::Medium(o) => {
+                positive(o.density, key.clone() + ".density", "Density")?;
+                Arc::new(ConstantMedium::new(
+                    self.closed_object(&o.boundary, key + ".boundary", "A medium")?,
+                    o.density,
+                ))
+            }
+            ObjectDescription::Voxels(o) => {
+                if !(0..3).all(|axis| o.min[axis] < o.max[axis]) {
+                    return Err(invalid(
+                        key + ".max",
+                        "Max must be larger than min on every axis".into(),
+                    ));
+                }
+                positive(o.density, key.clone() + ".density", "Density")?;
+                let path = self.base_dir.join(&o.file);
+                let grid = match o.resolution {
+                    Some(resolution) => load_raw_voxels(path, resolution),
+                    None => load_voxels(path),
+                }
+                .map_err(|e| invalid(key.clone() + ".file", e.to_string()))?;
+                Arc::new(
+                    GridMedium::new(
+                        Arc::new(grid),
+                        Aabb::new(location(o.min), location(o.max)),
+                        o.density,
+                        self.material(&o.material, key + ".material")?,
+                    )
+                    .with_temperature_scale(o.temperature_scale),
+                )
+            }
+        };
+        Ok(vec![object])
+    }
+
+    /// Creates an object that has to be closed, like the operands of CSG nodes. `user` names what needs it.
+    fn closed_object(
+        &mut self,
+        description: &ObjectDescription,
+        key: String,
+        user: &str,
+    ) -> Result<Arc<dyn Hittable + Send + Sync>, SceneError> {
+        match description {
+            ObjectDescription::Triangle(_)
+            | ObjectDescription::Disk(_)
+            | ObjectDescription::Quad(_)
+            | ObjectDescription::Obj(_)
+            | ObjectDescription::Sdf(_)
+            | ObjectDescription::Medium(_)
+            | ObjectDescription::Voxels(_) => Err(invalid(
+                key + ".type",
+                format!(
+                    "{} needs closed objects, flat objects, meshes, sdf objects and media are not supported",
+                    user
+                ),
+            )),
+            _ if description.is_light() => Err(invalid(
+                key + ".light",
+                format!("{} can not use lights", user),
+            )),
+            _ => Ok(self.object(description, key)?.remove(0)),
+        }
+    }
+
+    /// Loads the meshes of an obj file or reuses them if the file was already loaded with the same material
+    fn obj(&mut self, description: &ObjDescription, key: String) -> Result<Objects, SceneError> {
+        let path = self.base_dir.join(&description.file);
+        let cache_key = (path, description.material.clone());
+        if let Some(meshes) = self.meshes.get(&cache_key) {
+            return Ok(meshes.clone());
+        }
+        // Check the name before the file is loaded
+        let material = match &description.material {
+            Some(material) => Some(self.material(material, key.clone() + ".material")?),
+            None => None,
+        };
+        let groups =
+            load_obj(&cache_key.0).map_err(|e| invalid(key.clone() + ".file", e.to_string()))?;
+        let meshes: Objects = groups
+            .into_iter()
+            .map(|group| -> Arc<dyn Hittable + Send + Sync> {
+                match &material {
+                    Some(material) => Arc::new(group.mesh.with_material(material.clone())),
+                    None => Arc::new(group.mesh),
+                }
+            })
+            .collect();
+        self.meshes.insert(cache_key, meshes.clone());
+        Ok(meshes)
+    }
+}
+
+/// Creates the error for an invalid value
+fn invalid(key: String, message: String) -> SceneError {
+    SceneError::Invalid { key, message }
+}
+
+/// Checks that a size or density is a positive number. `what` names it in the error message.
+fn positive(value: f64, key: String, what: &str) -> Result<f64, SceneError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(value)
+    } else {
+        Err(invalid(key, format!("{} must be greater than zero", what)))
+    }
+}
+
+/// Converts to a normalized direction, which must not be zero
+fn unit(v: [f64; 3], key: String) -> Result<Direction, SceneError> {
+    let v = direction(v);
+    if v.length() > 0.0 {
+        Ok(v.norm())
+    } else {
+        Err(invalid(key, "Direction must not be zero".into()))
+    }
+}
+
+fn location(v: [f64; 3]) -> Location {
+    Location::new(v[0], v[1], v[2])
+}
+
+fn direction(v: [f64; 3]) -> Direction {
+    Direction::new(v[0], v[1], v[2])
+}
+
+fn color(v: [f64; 3]) -> Color {
+    Color::new(v[0], v[1], v[2])
+}
+
+/// Content of a scene file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SceneDescription {
+    /// Settings of the `image` table
+    #[serde(default, rename = "image")]
+    pub settings: RenderSettings,
+    /// Directory relative paths are resolved from
+    #[serde(skip)]
+    base_dir: PathBuf,
+    camera: CameraDescription,
+    #[serde(default)]
+    background: BackgroundDescription,
+    #[serde(default)]
+    materials: BTreeMap<String, MaterialDescription>,
+    #[serde(default)]
+    objects: Vec<ObjectDescription>,
+    #[serde(default)]
+    groups: Vec<GroupDescription>,
+    #[serde(default)]
+    lights: Vec<LightDescription>,
+    fog: Option<FogDescription>,
+}
+
+/// Medium that fills the whole scene
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FogDescription {
+    density: f64,
+    #[serde(default = "default_light_color")]
+    color: [f64; 3],
+    #[serde(default)]
+    anisotropy: f64,
+}
+
+/// The camera looks either in a `direction` or at a point given by `look_at`.
+/// For the perspective projection the field of view is given by `vfov` in degrees or by the `focal_length` for an
+/// image plane of height 2. The orthographic projection shows an area of `view_height` units.
+/// The equirectangular projection shows all directions and the fisheye projection `vfov` degrees across the
+/// image circle.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    #[serde(default)]
+    projection: Projection,
+    position: [f64; 3],
+    direction: Option<[f64; 3]>,
+    look_at: Option<[f64; 3]>,
+    #[serde(default = "default_up")]
+    up: [f64; 3],
+    focal_length: Option<f64>,
+    vfov: Option<f64>,
+    /// Rotation around the viewing direction in degrees
+    #[serde(default)]
+    roll: f64,
+    /// Radius of the lens for depth of field
+    #[serde(default)]
+    aperture: f64,
+    /// Distance of the sharp plane. Defaults to the distance to `look_at` or the length of `direction`.
+    focus_distance: Option<f64>,
+    /// Number of blades of the aperture. Zero for a round aperture.
+    #[serde(default)]
+    blades: u32,
+    /// Rotation of the blades in degrees
+    #[serde(default)]
+    blade_rotation: f64,
+    /// Height of the visible area of the orthographic projection
+    view_height: Option<f64>,
+    /// Renders an image for each eye with the perspective projection
+    stereo: Option<StereoDescription>,
+    /// Time interval in which the shutter is open
+    #[serde(default)]
+    shutter: [f64; 2],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StereoDescription {
+    /// Distance between the eyes
+    interocular: f64,
+    /// Distance that appears on the screen. Defaults to the focus distance.
+    convergence: Option<f64>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Projection {
+    #[default]
+    Perspective,
+    Orthographic,
+    Equirectangular,
+    Fisheye,
+}
+
+fn default_up() -> [f64; 3] {
+    [0.0, 0.0, 1.0]
+}
+
+impl CameraDescription {
+    /// Creates the camera and the stereo camera if the `stereo` table is present
+    fn build(
+        &self,
+        settings: RenderSettings,
+    ) -> Result<(Arc<dyn Camera + Send + Sync>, Option<StereoCamera>), SceneError> {
+        let from = location(self.position);
+        let to = match (self.direction, self.look_at) {
+            (Some(d), None) => from + direction(d),
+            (None, Some(l)) => location(l),
+            _ => {
+                return Err(invalid(
+                    "camera".into(),
+                    "Either direction or look_at is required".into(),
+                ))
+            }
+        };
+        if (to - from).length() == 0.0 {
+            return Err(invalid(
+                "camera".into(),
+                "The camera has no viewing direction".into(),
+            ));
+        }
+        let aspect = settings.width as f64 / settings.height as f64;
+        let up = direction(self.up);
+        let [open, close] = self.shutter;
+        if close < open {
+            return Err(invalid(
+                "camera.shutter".into(),
+                "The shutter has to open before it closes".into(),
+            ));
+        }
+
+        match self.projection {
+            Projection::Perspective => {
+                self.reject("perspective", &["view_height"])?;
+                let vfov = match (self.vfov, self.focal_length) {
+                    (Some(vfov), None) => vfov,
+                    (None, Some(focal_length)) => 2.0 * (1.0 / focal_length).atan().to_degrees(),
+                    (None, None) => 90.0,
+                    (Some(_), Some(_)) => {
+                        return Err(invalid(
+                            "camera".into(),
+                            "Only one of vfov and focal_length can be set".into(),
+                        ))
+                    }
+                };
+                if !(vfov > 0.0 && vfov < 180.0) {
+                    return Err(invalid(
+                        "camera.vfov".into(),
+                        "The field of view must be between 0 and 180 degrees".into(),
+                    ));
+                }
+                let focus_distance = self.focus_distance.unwrap_or((to - from).length());
+                if self.aperture < 0.0 || focus_distance <= 0.0 {
+                    return Err(invalid(
+                        "camera".into(),
+                        "The aperture must not be negative and the focus distance must be positive"
+                            .into(),
+                    ));
+                }
+                let camera = PerspectiveCamera::look_at(from, to, up, vfov, aspect)
+                    .with_roll(self.roll)
+                    .with_shutter(open, close)
+                    .with_aperture(self.aperture, focus_distance)
+                    .with_blades(self.blades, self.blade_rotation);
+                let stereo = match &self.stereo {
+                    Some(stereo) => {
+                        let convergence = stereo.convergence.unwrap_or(focus_distance);
+                        if stereo.interocular < 0.0 || convergence <= 0.0 {
+                            return Err(invalid(
+                                "camera.stereo".into(),
+                                "The interocular distance must not be negative and the convergence must be positive"
+                                    .into(),
+                            ));
+                        }
+                        Some(StereoCamera::new(camera, stereo.interocular, convergence))
+                    }
+                    None => None,
+                };
+                Ok((Arc::new(camera), stereo))
+            }
+            Projection::Orthographic => {
+                self.reject(
+                    "orthographic",
+                    &["vfov", "focal_length", "aperture", "stereo"],
+                )?;
+                let view_height = self.view_height.unwrap_or(2.0);
+                if view_height <= 0.0 {
+                    return Err(invalid(
+                        "camera.view_height".into(),
+                        "The view height must be positive".into(),
+                    ));
+                }
+                Ok((
+                    Arc::new(
+                        OrthographicCamera::look_at(from, to, up, view_height, aspect)
+                            .with_roll(self.roll)
+                            .with_shutter(open, close),
+                    ),
+                    None,
+                ))
+            }
+            Projection::Equirectangular => {
+                self.reject(
+                    "equirectangular",
+                    &["vfov", "focal_length", "aperture", "view_height", "stereo"],
+                )?;
+                Ok((
+                    Arc::new(
+                        EquirectangularCamera::look_at(from, to, up)
+                            .with_roll(self.roll)
+                            .with_shutter(open, close),
+                    ),
+                    None,
+                ))
+            }
+            Projection::Fisheye => {
+                self.reject(
+                    "fisheye",
+                    &["focal_length", "aperture", "view_height", "stereo"],
+                )?;
+                let fov = self.vfov.unwrap_or(180.0);
+                if !(fov > 0.0 && fov <= 360.0) {
+                    return Err(invalid(
+                        "camera.vfov".into(),
+                        "The field of view must be between 0 and 360 degrees".into(),
+                    ));
+                }
+                Ok((
+                    Arc::new(
+                        FisheyeCamera::look_at(from, to, up, fov, aspect)
+                            .with_roll(self.roll)
+                            .with_shutter(open, close),
+                    ),
+                    None,
+                ))
+            }
+        }
+    }
+
+    /// Returns an error if one of the keys is set, because the projection does not support it
+    fn reject(&self, projection: &str, keys: &[&str]) -> Result<(), SceneError> {
+        for key in keys {
+            let set = match *key {
+                "vfov" => self.vfov.is_some(),
+                "focal_length" => self.focal_length.is_some(),
+                "aperture" => {
+                    self.aperture != 0.0 || self.focus_distance.is_some() || self.blades != 0
+                }
+                "view_height" => self.view_height.is_some(),
+                "stereo" => self.stereo.is_some(),
+                _ => false,
+            };
+            if set {
+                return Err(invalid(
+                    format!("camera.{}", key),
+                    format!("Not supported by the {} projection", projection),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Enums whose variant is selected with the `type` key of a table.
+///
+/// Serde buffers the content of internally tagged enums, which looses the position of the values in the file.
+/// If `type` is the first key of the table, the variant is deserialized directly from the file, so errors point to
+/// the offending key. Other tables are buffered to find the `type`, their errors only point to the table.
+trait Tagged: Sized {
+    /// Names of all variants
+    const VARIANTS: &'static [&'static str];
+
+    /// Deserializes the variant `tag` from the remaining entries of the table
+    fn from_map<'de, A: MapAccess<'de>>(tag: &str, map: A) -> Result<Self, A::Error>;
+
+    /// Creates the value from a string instead of a table. Only tables are supported by default.
+    fn from_str<E: Error>(value: &str) -> Result<Self, E> {
+        Err(E::invalid_type(Unexpected::Str(value), &"a table"))
+    }
+}
+
+/// Visitor that reads the `type` key and hands the rest of the table to the variant
+struct TaggedVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Tagged> Visitor<'de> for TaggedVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a table with one of the types {:?}", T::VARIANTS)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<T, A::Error> {
+        let first = match map.next_key::<String>()? {
+            Some(key) if key == "type" => {
+                let tag: String = map.next_value()?;
+                return T::from_map(&tag, map);
+            }
+            Some(key) => key,
+            None => return Err(A::Error::missing_field("type")),
+        };
+        let mut table = toml::Table::new();
+        table.insert(first, map.next_value()?);
+        while let Some((key, value)) = map.next_entry::<String, toml::Value>()? {
+            table.insert(key, value);
+        }
+        let tag = match table.remove("type") {
+            Some(toml::Value::String(tag)) => tag,
+            Some(value) => {
+                return Err(A::Error::invalid_type(
+                    Unexpected::Other(value.type_str()),
+                    &"a string",
+                ))
+            }
+            None => return Err(A::Error::missing_field("type")),
+        };
+        T::from_map(&tag, MapDeserializer::new(table.into_iter())).map_err(A::Error::custom)
+    }
+
+    fn visit_str<E: Error>(self, value: &str) -> Result<T, E> {
+        T::from_str(value)
+    }
+}
+
+/// Deserializes a variant struct from the remaining entries of the table
+fn variant<'de, V: Deserialize<'de>, A: MapAccess<'de>>(map: A) -> Result<V, A::Error> {
+    V::deserialize(MapAccessDeserializer::new(map))
+}
+
+/// Implements `Deserialize` for an enum that implements `Tagged`
+macro_rules! deserialize_tagged {
+    ($name:ident) => {
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                deserializer.deserialize_any(TaggedVisitor(PhantomData))
+            }
+        }
+    };
+}
+
+#[derive(Debug, Default)]
+enum BackgroundDescription {
+    #[default]
+    Sky,
+    Color(ColorBackground),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EmptyDescription {}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorBackground {
+    color: [f64; 3],
+}
+
+impl Tagged for BackgroundDescription {
+    const VARIANTS: &'static [&'static str] = &["sky", "color"];
+
+    fn from_map<'de, A: MapAccess<'de>>(tag: &str, map: A) -> Result<Self, A::Error> {
+        match tag {
+            "sky" => variant::<EmptyDescription, A>(map).map(|_| BackgroundDescription::Sky),
+            "color" => variant(map).map(BackgroundDescription::Color),
+            _ => Err(A::Error::unknown_variant(tag, Self::VARIANTS)),
+        }
+    }
+}
+deserialize_tagged!(BackgroundDescription);
+
+impl BackgroundDescription {
+    fn build(&self) -> Background {
+        match self {
+            BackgroundDescription::Sky => Background::Sky,
+            BackgroundDescription::Color(b) => Background::Color(color(b.color)),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum MaterialDescription {
+    Lambertian(LambertianDescription),
+    Metal(MetalDescription),
+    Conductor(ConductorDescription),
+    Dielectric(DielectricDescription),
+    Light(LightMaterialDescription),
+    Volume(VolumeDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LambertianDescription {
+    #[serde(default = "default_color")]
+    color: [f64; 3],
+    /// Texture used instead of the color
+    texture: Option<TextureDescription>,
+    normal_map: Option<NormalMapDescription>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MetalDescription {
+    #[serde(default = "default_color")]
+    color: [f64; 3],
+    #[serde(default)]
+    fuzz: f64,
+    normal_map: Option<NormalMapDescription>,
+}
+
+/// Rough metal, either a preset or the complex index of refraction
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConductorDescription {
+    preset: Option<String>,
+    eta: Option<[f64; 3]>,
+    k: Option<[f64; 3]>,
+    #[serde(default = "default_roughness")]
+    roughness: f64,
+    #[serde(default)]
+    anisotropy: f64,
+    normal_map: Option<NormalMapDescription>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DielectricDescription {
+    refraction_index: f64,
+    normal_map: Option<NormalMapDescription>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LightMaterialDescription {
+    #[serde(default = "default_light_color")]
+    color: [f64; 3],
+    #[serde(default = "default_intensity")]
+    intensity: f64,
+}
+
+/// Inside of a medium. The anisotropy is the mean cosine of the scattering angle.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VolumeDescription {
+    #[serde(default = "default_light_color")]
+    color: [f64; 3],
+    #[serde(default)]
+    anisotropy: f64,
+    /// Light emitted at every scattering point
+    emission: Option<TextureDescription>,
+}
+
+fn default_color() -> [f64; 3] {
+    [0.8, 0.8, 0.8]
+}
+
+fn default_light_color() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+fn default_roughness() -> f64 {
+    0.3
+}
+
+impl Tagged for MaterialDescription {
+    const VARIANTS: &'static [&'static str] = &[
+        "lambertian",
+        "metal",
+        "conductor",
+        "dielectric",
+        "light",
+        "volume",
+    ];
+
+    fn from_map<'de, A: MapAccess<'de>>(tag: &str, map: A) -> Result<Self, A::Error> {
+        match tag {
+            "lambertian" => variant(map).map(MaterialDescription::Lambertian),
+            "metal" => variant(map).map(MaterialDescription::Metal),
+            "conductor" => variant(map).map(MaterialDescription::Conductor),
+            "dielectric" => variant(map).map(MaterialDescription::Dielectric),
+            "light" => variant(map).map(MaterialDescription::Light),
+            "volume" => variant(map).map(MaterialDescription::Volume),
+            _ => Err(A::Error::unknown_variant(tag, Self::VARIANTS)),
+        }
+    }
+}
+deserialize_tagged!(MaterialDescription);
+
+impl MaterialDescription {
+    fn build(&self, base_dir: &Path) -> Result<Arc<dyn Material + Send + Sync>, String> {
+        let material: Arc<dyn Material + Send + Sync> = match self {
+            MaterialDescription::Lambertian(m) => match &m.texture {
+                Some(texture) => Arc::new(Lambertian::textured(texture.build(base_dir, false)?)),
+                None => Arc::new(Lambertian::new(color(m.color))),
+            },
+            MaterialDescription::Metal(m) => Arc::new(Metal::new(color(m.color), m.fuzz)),
+            MaterialDescription::Conductor(m) => Arc::new(m.build()?),
+            MaterialDescription::Dielectric(m) => Arc::new(Dielectric::new(m.refraction_index)),
+            MaterialDescription::Light(m) => {
+                Arc::new(DiffuseLight::new(color(m.color), m.intensity))
+            }
+            MaterialDescription::Volume(m) => {
+                let volume = Volume::new(color(m.color), phase_function(m.anisotropy)?);
+                match &m.emission {
+                    Some(emission) => {
+                        Arc::new(volume.with_emission(emission.build(base_dir, false)?))
+                    }
+                    None => Arc::new(volume),
+                }
+            }
+        };
+        let normal_map = match self {
+            MaterialDescription::Lambertian(m) => &m.normal_map,
+            MaterialDescription::Metal(m) => &m.normal_map,
+            MaterialDescription::Conductor(m) => &m.normal_map,
+            MaterialDescription::Dielectric(m) => &m.normal_map,
+            MaterialDescription::Light(_) | MaterialDescription::Volume(_) => &None,
+        };
+        Ok(match normal_map {
+            Some(map) => Arc::new(NormalMapped::new(material, map.build(base_dir)?)),
+            None => material,
+        })
+    }
+}
+
+impl ConductorDescription {
+    fn build(&self) -> Result<Microfacet, String> {
+        let conductor = match (&self.preset, self.eta, self.k) {
+            (Some(preset), None, None) => match preset.as_str() {
+                "gold" => Conductor::gold(),
+                "copper" => Conductor::copper(),
+                "aluminium" => Conductor::aluminium(),
+                "silver" => Conductor::silver(),
+                _ => {
+                    return Err(format!(
+                        "Unknown preset {}, expected gold, copper, aluminium or silver",
+                        preset
+                    ))
+                }
+            },
+            (None, Some(eta), Some(k)) => Conductor::new(color(eta), color(k)),
+            _ => return Err("Needs either a preset or eta and k".into()),
+        };
+        if !(0.0..=1.0).contains(&self.roughness) {
+            return Err("Roughness must be between 0 and 1".into());
+        }
+        if self.anisotropy.abs() > 1.0 {
+            return Err("Anisotropy must be between -1 and 1".into());
+        }
+        Ok(Microfacet::new(conductor, self.roughness, self.anisotropy))
+    }
+}
+
+fn phase_function(anisotropy: f64) -> Result<PhaseFunction, String> {
+    if anisotropy.abs() < 1.0 {
+        Ok(PhaseFunction::new(anisotropy))
+    } else {
+        Err("Anisotropy must be between -1 and 1".into())
+    }
+}
+
+#[derive(Debug)]
+enum NormalMapDescription {
+    Normal(NormalMapTextureDescription),
+    Bump(NormalMapTextureDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NormalMapTextureDescription {
+    texture: TextureDescription,
+    /// Tilt of the normals or height of the bumps in world units
+    #[serde(default = "default_strength")]
+    strength: f64,
+}
+
+fn default_strength() -> f64 {
+    1.0
+}
+
+impl Tagged for NormalMapDescription {
+    const VARIANTS: &'static [&'static str] = &["normal", "bump"];
+
+    fn from_map<'de, A: MapAccess<'de>>(tag: &str, map: A) -> Result<Self, A::Error> {
+        match tag {
+            "normal" => variant(map).map(NormalMapDescription::Normal),
+            "bump" => variant(map).map(NormalMapDescription::Bump),
+            _ => Err(A::Error::unknown_variant(tag, Self::VARIANTS)),
+        }
+    }
+
+    /// A string is the path of a normal map image
+    fn from_str<E: Error>(value: &str) -> Result<Self, E> {
+        Ok(NormalMapDescription::Normal(NormalMapTextureDescription {
+            texture: TextureDescription::from_str(value)?,
+            strength: default_strength(),
+        }))
+    }
+}
+deserialize_tagged!(NormalMapDescription);
+
+impl NormalMapDescription {
+    fn build(&self, base_dir: &Path) -> Result<NormalMap, String> {
+        Ok(match self {
+            NormalMapDescription::Normal(m) => NormalMap::Tangent {
+                texture: m.texture.build(base_dir, true)?,
+                strength: m.strength,
+            },
+            NormalMapDescription::Bump(m) => NormalMap::Bump {
+                texture: m.texture.build(base_dir, true)?,
+                strength: m.strength,
+            },
+        })
+    }
+}
+
+#[derive(Debug)]
+enum TextureDescription {
+    Constant(ConstantTextureDescription),
+    Checker(CheckerTextureDescription),
+    Image(ImageTextureDescription),
+    Noise(NoiseTextureDescription),
+    Marble(MarbleTextureDescription),
+    Wood(WoodTextureDescription),
+    Worley(WorleyTextureDescription),
+    Blackbody(BlackbodyTextureDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConstantTextureDescription {
+    color: [f64; 3],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckerTextureDescription {
+    #[serde(default = "default_light_color")]
+    even: [f64; 3],
+    #[serde(default)]
+    odd: [f64; 3],
+    /// Number of squares per unit of the texture coordinates
+    #[serde(default = "default_frequency")]
+    frequency: f64,
+}
+
+fn default_frequency() -> f64 {
+    10.0
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageTextureDescription {
+    file: String,
+    #[serde(default)]
+    wrap: WrapMode,
+    #[serde(default)]
+    filter: Filter,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoiseTextureDescription {
+    #[serde(default)]
+    seed: u64,
+    /// Frequency of the noise per unit in the world
+    #[serde(default = "default_texture_scale")]
+    scale: f64,
+    #[serde(default = "default_octaves")]
+    octaves: u32,
+    #[serde(default)]
+    mode: NoiseMode,
+    #[serde(default)]
+    low: [f64; 3],
+    #[serde(default = "default_light_color")]
+    high: [f64; 3],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MarbleTextureDescription {
+    #[serde(default)]
+    seed: u64,
+    #[serde(default = "default_texture_scale")]
+    scale: f64,
+    #[serde(default = "default_marble_turbulence")]
+    turbulence: f64,
+    #[serde(default = "default_octaves")]
+    octaves: u32,
+    #[serde(default = "default_light_color")]
+    base: [f64; 3],
+    #[serde(default = "default_vein_color")]
+    vein: [f64; 3],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WoodTextureDescription {
+    #[serde(default)]
+    seed: u64,
+    /// Number of rings per unit in the world
+    #[serde(default = "default_texture_scale")]
+    scale: f64,
+    #[serde(default = "default_wood_turbulence")]
+    turbulence: f64,
+    #[serde(default = "default_octaves")]
+    octaves: u32,
+    #[serde(default = "default_light_wood")]
+    light: [f64; 3],
+    #[serde(default = "default_dark_wood")]
+    dark: [f64; 3],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorleyTextureDescription {
+    #[serde(default)]
+    seed: u64,
+    /// Number of cells per unit in the world
+    #[serde(default = "default_texture_scale")]
+    scale: f64,
+    #[serde(default)]
+    mode: WorleyMode,
+    #[serde(default = "default_light_color")]
+    inner: [f64; 3],
+    #[serde(default)]
+    outer: [f64; 3],
+}
+
+/// Light of a black body at the temperature of a grid medium
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlackbodyTextureDescription {
+    /// Brightness at the reference temperature
+    #[serde(default = "default_intensity")]
+    intensity: f64,
+    #[serde(default = "default_reference_temperature")]
+    reference: f64,
+}
+
+fn default_reference_temperature() -> f64 {
+    1500.0
+}
+
+fn default_texture_scale() -> f64 {
+    1.0
+}
+
+fn default_octaves() -> u32 {
+    6
+}
+
+fn default_marble_turbulence() -> f64 {
+    5.0
+}
+
+fn default_vein_color() -> [f64; 3] {
+    [0.2, 0.2, 0.2]
+}
+
+fn default_wood_turbulence() -> f64 {
+    1.0
+}
+
+fn default_light_wood() -> [f64; 3] {
+    [0.76, 0.6, 0.42]
+}
+
+fn default_dark_wood() -> [f64; 3] {
+    [0.4, 0.26, 0.13]
+}
+
+impl Tagged for TextureDescription {
+    const VARIANTS: &'static [&'static str] = &[
+        "constant",
+        "checker",
+        "image",
+        "noise",
+        "marble",
+        "wood",
+        "worley",
+        "blackbody",
+    ];
+
+    fn from_map<'de, A: MapAccess<'de>>(tag: &str, map: A) -> Result<Self, A::Error> {
+        match tag {
+            "constant" => variant(map).map(TextureDescription::Constant),
+            "checker" => variant(map).map(TextureDescription::Checker),
+            "image" => variant(map).map(TextureDescription::Image),
+            "noise" => variant(map).map(TextureDescription::Noise),
+            "marble" => variant(map).map(TextureDescription::Marble),
+            "wood" => variant(map).map(TextureDescription::Wood),
+            "worley" => variant(map).map(TextureDescription::Worley),
+            "blackbody" => variant(map).map(TextureDescription::Blackbody),
+            _ => Err(A::Error::unknown_variant(tag, Self::VARIANTS)),
+        }
+    }
+
+    /// A string is the path of an image file
+    fn from_str<E: Error>(value: &str) -> Result<Self, E> {
+        Ok(TextureDescription::Image(ImageTextureDescription {
+            file: value.into(),
+            wrap: WrapMode::default(),
+            filter: Filter::default(),
+        }))
+    }
+}
+deserialize_tagged!(TextureDescription);
+
+impl TextureDescription {
+    /// Images are loaded without gamma correction if `linear` is set, for textures that store data like normals
+    fn build(
+        &self,
+        base_dir: &Path,
+        linear: bool,
+    ) -> Result<Arc<dyn Texture + Send + Sync>, String> {
+        Ok(match self {
+            TextureDescription::Constant(t) => Arc::new(color(t.color)),
+            TextureDescription::Checker(t) => Arc::new(CheckerTexture::new(
+                Arc::new(color(t.even)),
+                Arc::new(color(t.odd)),
+                t.frequency,
+            )),
+            TextureDescription::Image(t) => {
+                let path = base_dir.join(&t.file);
+                let image = if linear {
+                    ImageTexture::open_linear(path)?
+                } else {
+                    ImageTexture::open(path)?
+                };
+                Arc::new(image.with_wrap(t.wrap).with_filter(t.filter))
+            }
+            TextureDescription::Noise(t) => Arc::new(NoiseTexture {
+                noise: Perlin::new(t.seed),
+                scale: t.scale,
+                octaves: t.octaves,
+                mode: t.mode,
+                low: color(t.low),
+                high: color(t.high),
+            }),
+            TextureDescription::Marble(t) => Arc::new(MarbleTexture {
+                noise: Perlin::new(t.seed),
+                scale: t.scale,
+                turbulence: t.turbulence,
+                octaves: t.octaves,
+                base: color(t.base),
+                vein: color(t.vein),
+            }),
+            TextureDescription::Wood(t) => Arc::new(WoodTexture {
+                noise: Perlin::new(t.seed),
+                scale: t.scale,
+                turbulence: t.turbulence,
+                octaves: t.octaves,
+                light: color(t.light),
+                dark: color(t.dark),
+            }),
+            TextureDescription::Worley(t) => Arc::new(WorleyTexture {
+                noise: Worley::new(t.seed),
+                scale: t.scale,
+                mode: t.mode,
+                inner: color(t.inner),
+                outer: color(t.outer),
+            }),
+            TextureDescription::Blackbody(t) => {
+                Arc::new(BlackbodyTexture::new(t.intensity, t.reference))
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+enum ObjectDescription {
+    Sphere(SphereDescription),
+    MovingSphere(MovingSphereDescription),
+    Triangle(TriangleDescription),
+    Plane(PlaneDescription),
+    Disk(DiskDescription),
+    Quad(QuadDescription),
+    Box(BoxDescription),
+    Cylinder(CylinderDescription),
+    Cone(ConeDescription),
+    Torus(TorusDescription),
+    Sdf(SdfObjectDescription),
+    Obj(ObjDescription),
+    Csg(CsgDescription),
+    Medium(MediumDescription),
+    Voxels(VoxelsDescription),
+}
+
+impl ObjectDescription {
+    /// True for objects that are sampled as lights
+    fn is_light(&self) -> bool {
+        match self {
+            ObjectDescription::Sphere(o) => o.light,
+            ObjectDescription::MovingSphere(o) => o.light,
+            ObjectDescription::Triangle(o) => o.light,
+            ObjectDescription::Disk(o) => o.light,
+            ObjectDescription::Quad(o) => o.light,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereDescription {
+    center: [f64; 3],
+    radius: f64,
+    material: String,
+    /// Samples the object directly as a light
+    #[serde(default)]
+    light: bool,
+}
+
+/// Sphere that moves from `center` at `start_time` to `end_center` at `end_time`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MovingSphereDescription {
+    center: [f64; 3],
+    end_center: [f64; 3],
+    #[serde(default)]
+    start_time: f64,
+    #[serde(default = "default_end_time")]
+    end_time: f64,
+    radius: f64,
+    material: String,
+    /// Samples the object directly as a light
+    #[serde(default)]
+    light: bool,
+}
+
+fn default_end_time() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TriangleDescription {
+    vertices: [[f64; 3]; 3],
+    material: String,
+    /// Samples the object directly as a light
+    #[serde(default)]
+    light: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlaneDescription {
+    point: [f64; 3],
+    #[serde(default = "default_up")]
+    normal: [f64; 3],
+    material: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DiskDescription {
+    center: [f64; 3],
+    #[serde(default = "default_up")]
+    normal: [f64; 3],
+    radius: f64,
+    material: String,
+    /// Samples the object directly as a light
+    #[serde(default)]
+    light: bool,
+}
+
+/// Parallelogram spanned by the edges `u` and `v` from `corner`
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QuadDescription {
+    corner: [f64; 3],
+    u: [f64; 3],
+    v: [f64; 3],
+    material: String,
+    /// Samples the object directly as a light
+    #[serde(default)]
+    light: bool,
+}
+
+/// Axis aligned box
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BoxDescription {
+    min: [f64; 3],
+    max: [f64; 3],
+    material: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CylinderDescription {
+    base: [f64; 3],
+    top: [f64; 3],
+    radius: f64,
+    material: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConeDescription {
+    base: [f64; 3],
+    apex: [f64; 3],
+    radius: f64,
+    material: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TorusDescription {
+    center: [f64; 3],
+    #[serde(default = "default_up")]
+    axis: [f64; 3],
+    major_radius: f64,
+    minor_radius: f64,
+    material: String,
+}
+
+/// Surface of a distance field, rendered by sphere tracing
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SdfObjectDescription {
+    shape: SdfDescription,
+    material: String,
+    #[serde(default = "default_epsilon")]
+    epsilon: f64,
+    #[serde(default = "default_max_steps")]
+    max_steps: u32,
+}
+
+fn default_epsilon() -> f64 {
+    1e-4
+}
+
+fn default_max_steps() -> u32 {
+    512
+}
+
+#[derive(Debug)]
+enum SdfDescription {
+    Sphere(SdfSphereDescription),
+    Box(SdfBoxDescription),
+    Torus(SdfTorusDescription),
+    Capsule(SdfCapsuleDescription),
+    Mandelbulb(MandelbulbDescription),
+    Blend(SdfBlendDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SdfSphereDescription {
+    center: [f64; 3],
+    radius: f64,
+}
+
+/// Axis aligned box, optionally with rounded edges
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SdfBoxDescription {
+    center: [f64; 3],
+    half_size: [f64; 3],
+    #[serde(default)]
+    rounding: f64,
+}
+
+/// Torus around the z axis
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SdfTorusDescription {
+    center: [f64; 3],
+    major_radius: f64,
+    minor_radius: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SdfCapsuleDescription {
+    start: [f64; 3],
+    end: [f64; 3],
+    radius: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MandelbulbDescription {
+    center: [f64; 3],
+    #[serde(default = "default_mandelbulb_scale")]
+    scale: f64,
+    #[serde(default = "default_power")]
+    power: f64,
+    #[serde(default = "default_iterations")]
+    iterations: u32,
+}
+
+fn default_mandelbulb_scale() -> f64 {
+    1.0
+}
+
+fn default_power() -> f64 {
+    8.0
+}
+
+fn default_iterations() -> u32 {
+    10
+}
+
+/// Boolean combination of two distance fields, smoothly blended with a `smoothness` above zero
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SdfBlendDescription {
+    operation: CsgOperation,
+    #[serde(default)]
+    smoothness: f64,
+    a: Box<SdfDescription>,
+    b: Box<SdfDescription>,
+}
+
+impl Tagged for SdfDescription {
+    const VARIANTS: &'static [&'static str] =
+        &["sphere", "box", "torus", "capsule", "mandelbulb", "blend"];
+
+    fn from_map<'de, A: MapAccess<'de>>(tag: &str, map: A) -> Result<Self, A::Error> {
+        match tag {
+            "sphere" => variant(map).map(SdfDescription::Sphere),
+            "box" => variant(map).map(SdfDescription::Box),
+            "torus" => variant(map).map(SdfDescription::Torus),
+            "capsule" => variant(map).map(SdfDescription::Capsule),
+            "mandelbulb" => variant(map).map(SdfDescription::Mandelbulb),
+            "blend" => variant(map).map(SdfDescription::Blend),
+            _ => Err(A::Error::unknown_variant(tag, Self::VARIANTS)),
+        }
+    }
+}
+deserialize_tagged!(SdfDescription);
+
+impl SdfDescription {
+    fn build(&self) -> Arc<dyn DistanceField + Send + Sync> {
+        match self {
+            SdfDescription::Sphere(d) => Arc::new(SdfSphere {
+                center: location(d.center),
+                radius: d.radius,
+            }),
+            SdfDescription::Box(d) => Arc::new(SdfBox {
+                center: location(d.center),
+                half_size: direction(d.half_size),
+                rounding: d.rounding,
+            }),
+            SdfDescription::Torus(d) => Arc::new(SdfTorus {
+                center: location(d.center),
+                major_radius: d.major_radius,
+                minor_radius: d.minor_radius,
+            }),
+            SdfDescription::Capsule(d) => Arc::new(SdfCapsule {
+                start: location(d.start),
+                end: location(d.end),
+                radius: d.radius,
+            }),
+            SdfDescription::Mandelbulb(d) => Arc::new(Mandelbulb {
+                center: location(d.center),
+                scale: d.scale,
+                power: d.power,
+                iterations: d.iterations,
+            }),
+            SdfDescription::Blend(d) => Arc::new(SdfBlend {
+                operation: d.operation,
+                a: d.a.build(),
+                b: d.b.build(),
+                smoothness: d.smoothness,
+            }),
+        }
+    }
+}
+
+/// Wavefront obj file. The materials of the file are used if no material is given.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ObjDescription {
+    file: String,
+    material: Option<String>,
+}
+
+/// Boolean combination of two closed objects. CSG nodes can be nested.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CsgDescription {
+    operation: CsgOperation,
+    left: Box<ObjectDescription>,
+    right: Box<ObjectDescription>,
+}
+
+/// Medium of constant density inside of a closed object. The material of the boundary is used inside.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MediumDescription {
+    density: f64,
+    boundary: Box<ObjectDescription>,
+}
+
+/// Medium with the densities of a voxel grid file that fills the box from `min` to `max`.
+/// Files with a `resolution` are raw files.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VoxelsDescription {
+    file: String,
+    resolution: Option<[usize; 3]>,
+    min: [f64; 3],
+    max: [f64; 3],
+    /// Factor from the values of the file to the density
+    #[serde(default = "default_density_scale")]
+    density: f64,
+    #[serde(default = "default_temperature_scale")]
+    temperature_scale: f64,
+    material: String,
+}
+
+fn default_density_scale() -> f64 {
+    1.0
+}
+
+fn default_temperature_scale() -> f64 {
+    1.0
+}
+
+impl Tagged for ObjectDescription {
+    const VARIANTS: &'static [&'static str] = &[
+        "sphere",
+        "moving_sphere",
+        "triangle",
+        "plane",
+        "disk",
+        "quad",
+        "box",
+        "cylinder",
+        "cone",
+        "torus",
+        "sdf",
+        "obj",
+        "csg",
+        "medium",
+        "voxels",
+    ];
+
+    fn from_map<'de, A: MapAccess<'de>>(tag: &str, map: A) -> Result<Self, A::Error> {
+        match tag {
+            "sphere" => variant(map).map(ObjectDescription::Sphere),
+            "moving_sphere" => variant(map).map(ObjectDescription::MovingSphere),
+            "triangle" => variant(map).map(ObjectDescription::Triangle),
+            "plane" => variant(map).map(ObjectDescription::Plane),
+            "disk" => variant(map).map(ObjectDescription::Disk),
+            "quad" => variant(map).map(ObjectDescription::Quad),
+            "box" => variant(map).map(ObjectDescription::Box),
+            "cylinder" => variant(map).map(ObjectDescription::Cylinder),
+            "cone" => variant(map).map(ObjectDescription::Cone),
+            "torus" => variant(map).map(ObjectDescription::Torus),
+            "sdf" => variant(map).map(ObjectDescription::Sdf),
+            "obj" => variant(map).map(ObjectDescription::Obj),
+            "csg" => variant(map).map(ObjectDescription::Csg),
+            "medium" => variant(map).map(ObjectDescription::Medium),
+            "voxels" => variant(map).map(ObjectDescription::Voxels),
+            _ => Err(A::Error::unknown_variant(tag, Self::VARIANTS)),
+        }
+    }
+}
+deserialize_tagged!(ObjectDescription);
+
+/// Named group of objects and groups. The group is scaled, then rotated by the euler angles in degrees
+/// around x, y and z and then moved.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GroupDescription {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    translate: [f64; 3],
+    #[serde(default)]
+    rotate: [f64; 3],
+    #[serde(default = "default_scale")]
+    scale: [f64; 3],
+    /// Replaces the materials of all objects in the group except lights and media
+    material: Option<String>,
+    #[serde(default = "default_visible")]
+    visible: bool,
+    #[serde(default)]
+    objects: Vec<ObjectDescription>,
+    #[serde(default)]
+    groups: Vec<GroupDescription>,
+    motion: Option<MotionDescription>,
+}
+
+/// Pose of a group at the end of its motion
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MotionDescription {
+    translate: Option<[f64; 3]>,
+    rotate: Option<[f64; 3]>,
+    scale: Option<[f64; 3]>,
+    #[serde(default)]
+    start_time: f64,
+    #[serde(default = "default_end_time")]
+    end_time: f64,
+}
+
+fn default_scale() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+fn default_visible() -> bool {
+    true
+}
+
+#[derive(Debug)]
+enum LightDescription {
+    Sphere(SphereLightDescription),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SphereLightDescription {
+    center: [f64; 3],
+    radius: f64,
+    #[serde(default = "default_light_color")]
+    color: [f64; 3],
+    #[serde(default = "default_intensity")]
+    intensity: f64,
+}
+
+impl Tagged for LightDescription {
+    const VARIANTS: &'static [&'static str] = &["sphere"];
+
+    fn from_map<'de, A: MapAccess<'de>>(tag: &str, map: A) -> Result<Self, A::Error> {
+        match tag {
+            "sphere" => variant(map).map(LightDescription::Sphere),
+            _ => Err(A::Error::unknown_variant(tag, Self::VARIANTS)),
+        }
+    }
+}
+deserialize_tagged!(LightDescription);
+
+mod test {
+
+    #[test]
+    fn sample_scenes() {
+        let base_dir = std::path::Path::new("scenes");
+        for source in &[
+            include_str!("../scenes/sample.toml"),
+            include_str!("../scenes/light.toml"),
+            include_str!("../scenes/motion.toml"),
+            include_str!("../scenes/csg.toml"),
+            include_str!("../scenes/shapes.toml"),
+            include_str!("../scenes/sdf.toml"),
+            include_str!("../scenes/textures.toml"),
+            include_str!("../scenes/fog.toml"),
+            include_str!("../scenes/fire.toml"),
+            include_str!("../scenes/metals.toml"),
+            include_str!("../scenes/cornell.toml"),
+        ] {
+            let scene = super::parse_scene(source, base_dir)
+                .unwrap()
+                .build()
+                .unwrap();
+            assert_eq!(scene.settings.width, 800);
+        }
+    }
+
+    #[test]
+    fn image_settings() {
+        let source = r#"
+            [image]
+            width = 400
+            height = 300
+            samples = 0
+
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+        "#;
+        let build = |source: &str| {
+            super::parse_scene(source, std::path::Path::new(""))
+                .unwrap()
+                .build()
+                .err()
+                .map(|e| e.to_string())
+        };
+        assert_eq!(
+            build(source).unwrap(),
+            "image.samples: Samples must be greater than zero"
+        );
+        assert_eq!(
+            build(&source.replace("height = 300", "height = 0")).unwrap(),
+            "image: Width and height must be greater than zero"
+        );
+        assert!(build(&source.replace("samples = 0", "samples = 1")).is_none());
+    }
+
+    #[test]
+    fn orthographic_camera() {
+        let source = r#"
+            [camera]
+            projection = "orthographic"
+            position = [0.0, 0.0, 0.0]
+            look_at = [1.0, 0.0, 0.0]
+            view_height = 4.0
+        "#;
+        let scene = super::parse_scene(source, std::path::Path::new(""))
+            .unwrap()
+            .build()
+            .unwrap();
+        let ray = scene.camera.get_ray(0.5, 0.0).unwrap();
+        assert!((ray.origin.z() - 2.0).abs() < 1e-12);
+        assert!((ray.direction.x() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unknown_material() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [[objects]]
+            type = "sphere"
+            center = [1.0, 0.0, 0.0]
+            radius = 0.5
+            material = "missing"
+        "#;
+        let error = super::parse_scene(source, std::path::Path::new(""))
+            .unwrap()
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "objects[0].material: Unknown material \"missing\""
+        );
+    }
+
+    #[test]
+    fn parse_error_names_key() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [[objects]]
+            type = "sphere"
+            center = [1.0, 0.0, 0.0]
+            radius = "large"
+            material = "missing"
+        "#;
+        let error = super::parse_scene(source, std::path::Path::new(""))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("line 9"));
+        assert!(error.contains("radius"));
+    }
+
+    #[test]
+    fn type_key_anywhere() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.floor]
+            texture = { odd = [0.0, 0.0, 0.0], type = "checker" }
+            type = "lambertian"
+
+            [[objects]]
+            center = [2.0, 0.0, 0.0]
+            radius = 1.0
+            type = "sphere"
+            material = "floor"
+
+            [[objects]]
+            operation = "union"
+            type = "csg"
+            left = { radius = 1.0, type = "sphere", center = [5.0, 0.0, 0.0], material = "floor" }
+            right = { type = "sphere", center = [5.0, 1.0, 0.0], radius = 1.0, material = "floor" }
+        "#;
+        let scene = super::parse_scene(source, std::path::Path::new(""))
+            .unwrap()
+            .build()
+            .ok()
+            .unwrap();
+        assert!(scene
+            .world
+            .get_hit(crate::scene::Ray {
+                origin: crate::geometry::Location::new(0.0, 0.0, 0.0),
+                direction: crate::geometry::Direction::new(1.0, 0.0, 0.0),
+                time: 0.0,
+            })
+            .is_some());
+
+        // Errors of buffered tables point to the table
+        let error = super::parse_scene(
+            &source.replace("radius = 1.0, type", "radius = \"large\", type"),
+            std::path::Path::new(""),
+        )
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(error.contains("line 16"));
+        assert!(error.contains("\"large\", expected f64"));
+        let error = super::parse_scene(
+            &source.replace("type = \"csg\"", ""),
+            std::path::Path::new(""),
+        )
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(error.contains("missing field `type`"));
+    }
+
+    #[test]
+    fn nested_groups() {
+        use crate::geometry::{Direction, Location};
+        use crate::scene::Ray;
+
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.white]
+            type = "lambertian"
+            color = [1.0, 1.0, 1.0]
+
+            [[groups]]
+            name = "row"
+            translate = [10.0, 0.0, 0.0]
+
+            [[groups.groups]]
+            name = "big"
+            translate = [0.0, 0.0, 5.0]
+            scale = [2.0, 2.0, 2.0]
+
+            [[groups.groups.objects]]
+            type = "sphere"
+            center = [0.0, 0.0, 0.0]
+            radius = 1.0
+            material = "white"
+
+            [[groups.groups]]
+            name = "hidden"
+            visible = false
+
+            [[groups.groups.objects]]
+            type = "sphere"
+            center = [0.0, 0.0, 0.0]
+            radius = 1.0
+            material = "white"
+        "#;
+        let scene = super::parse_scene(source, std::path::Path::new(""))
+            .unwrap()
+            .build()
+            .unwrap();
+        let ray = |z: f64| Ray {
+            origin: Location::new(0.0, 0.0, z),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time: 0.0,
+        };
+        let hit = scene.world.get_hit(ray(5.0)).unwrap();
+        assert!((hit.distance - 8.0).abs() < 1e-9);
+        assert!(scene.world.get_hit(ray(0.0)).is_none());
+
+        let error = super::parse_scene(
+            &source.replace("scale = [2.0, 2.0, 2.0]", "scale = [2.0, 0.0, 2.0]"),
+            std::path::Path::new(""),
+        )
+        .unwrap()
+        .build()
+        .err()
+        .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "groups[0].groups[0].scale: Scale must not be zero"
+        );
+    }
+
+    #[test]
+    fn light_objects() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.lamp]
+            type = "light"
+            intensity = 4.0
+
+            [[objects]]
+            type = "quad"
+            corner = [-0.5, -0.5, 2.0]
+            u = [1.0, 0.0, 0.0]
+            v = [0.0, 1.0, 0.0]
+            material = "lamp"
+            light = true
+
+            [[groups]]
+            name = "lamps"
+            translate = [2.0, 0.0, 0.0]
+
+            [[groups.objects]]
+            type = "disk"
+            center = [0.0, 0.0, 2.0]
+            radius = 0.5
+            material = "lamp"
+            light = true
+
+            [[groups.objects]]
+            type = "box"
+            min = [0.0, 0.0, 0.0]
+            max = [1.0, 1.0, 1.0]
+            material = "lamp"
+
+            [[groups.objects]]
+            type = "triangle"
+            vertices = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+            material = "lamp"
+
+            [[lights]]
+            type = "sphere"
+            center = [0.0, 0.0, 3.0]
+            radius = 0.1
+        "#;
+        let build = |source: &str| {
+            super::parse_scene(source, std::path::Path::new(""))
+                .map_err(|e| e.to_string())?
+                .build()
+                .map_err(|e| e.to_string())
+        };
+        let scene = build(source).ok().unwrap();
+        assert_eq!(scene.world.lights().len(), 3);
+
+        // Objects without sampling can not be lights
+        let error = build(&source.replace("[1.0, 1.0, 1.0]\n", "[1.0, 1.0, 1.0]\nlight = true\n"))
+            .err()
+            .unwrap();
+        assert!(error.contains("unknown field `light`"));
+    }
+
+    #[test]
+    fn moving_group() {
+        use crate::geometry::{Direction, Location};
+        use crate::scene::Ray;
+
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.white]
+            type = "lambertian"
+
+            [[groups]]
+            translate = [10.0, 0.0, 0.0]
+
+            [groups.motion]
+            translate = [10.0, 4.0, 0.0]
+            end_time = 2.0
+
+            [[groups.objects]]
+            type = "sphere"
+            center = [0.0, 0.0, 0.0]
+            radius = 1.0
+            material = "white"
+        "#;
+        let build = |source: &str| {
+            super::parse_scene(source, std::path::Path::new(""))
+                .unwrap()
+                .build()
+                .map_err(|e| e.to_string())
+        };
+        let scene = build(source).ok().unwrap();
+        let ray = |y: f64, time: f64| Ray {
+            origin: Location::new(0.0, y, 0.0),
+            direction: Direction::new(1.0, 0.0, 0.0),
+            time,
+        };
+        assert!(scene.world.get_hit(ray(0.0, 0.0)).is_some());
+        assert!(scene.world.get_hit(ray(2.0, 0.0)).is_none());
+        assert!(scene.world.get_hit(ray(2.0, 1.0)).is_some());
+        assert!(scene.world.get_hit(ray(4.0, 2.0)).is_some());
+
+        let error = build(&source.replace("end_time = 2.0", "scale = [1.0, -1.0, 1.0]"))
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            "groups[0].motion.scale: Scale must not be zero or change its sign"
+        );
+    }
+
+    #[test]
+    fn object_sizes() {
+        let build = |object: &str| {
+            let source = format!(
+                r#"
+                [camera]
+                position = [0.0, 0.0, 0.0]
+                direction = [1.0, 0.0, 0.0]
+
+                [materials.white]
+                type = "lambertian"
+
+                [[objects]]
+                {}
+                "#,
+                object
+            );
+            super::parse_scene(&source, std::path::Path::new(""))
+                .unwrap()
+                .build()
+                .err()
+                .map(|error| error.to_string())
+        };
+        let error = |object: &str| build(&format!("{}\nmaterial = \"white\"", object)).unwrap();
+
+        assert!(build(
+            "type = \"sphere\"\ncenter = [5.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"white\""
+        )
+        .is_none());
+        assert_eq!(
+            error("type = \"sphere\"\ncenter = [5.0, 0.0, 0.0]\nradius = 0.0"),
+            "objects[0].radius: The radius must be greater than zero"
+        );
+        assert_eq!(
+            error("type = \"disk\"\ncenter = [5.0, 0.0, 0.0]\nnormal = [1.0, 0.0, 0.0]\nradius = -1.0"),
+            "objects[0].radius: The radius must be greater than zero"
+        );
+        assert_eq!(
+            error(
+                "type = \"cylinder\"\nbase = [5.0, 0.0, 0.0]\ntop = [5.0, 0.0, 1.0]\nradius = nan"
+            ),
+            "objects[0].radius: The radius must be greater than zero"
+        );
+        assert_eq!(
+            error("type = \"cone\"\nbase = [5.0, 0.0, 0.0]\napex = [5.0, 0.0, 1.0]\nradius = 0.0"),
+            "objects[0].radius: The radius must be greater than zero"
+        );
+        assert_eq!(
+            error("type = \"cone\"\nbase = [5.0, 0.0, 0.0]\napex = [5.0, 0.0, 0.0]\nradius = 1.0"),
+            "objects[0].apex: Base and apex must differ"
+        );
+        assert_eq!(
+            error("type = \"torus\"\ncenter = [5.0, 0.0, 0.0]\naxis = [0.0, 0.0, 1.0]\nmajor_radius = 1.0\nminor_radius = 0.0"),
+            "objects[0].minor_radius: The radius must be greater than zero"
+        );
+        assert_eq!(
+            error("type = \"quad\"\ncorner = [5.0, 0.0, 0.0]\nu = [0.0, 1.0, 0.0]\nv = [0.0, 2.0, 0.0]"),
+            "objects[0].v: The edges u and v must not be zero or parallel"
+        );
+        assert_eq!(
+            error("type = \"box\"\nmin = [5.0, 0.0, 0.0]\nmax = [6.0, -1.0, 1.0]"),
+            "objects[0].max: Max must be larger than min on every axis"
+        );
+        assert_eq!(
+            build("type = \"medium\"\ndensity = nan\n[objects.boundary]\ntype = \"sphere\"\ncenter = [5.0, 0.0, 0.0]\nradius = 1.0\nmaterial = \"white\"").unwrap(),
+            "objects[0].density: Density must be greater than zero"
+        );
+    }
+
+    #[test]
+    fn csg_needs_closed_objects() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.white]
+            type = "lambertian"
+
+            [[objects]]
+            type = "csg"
+            operation = "union"
+
+            [objects.left]
+            type = "sphere"
+            center = [1.0, 0.0, 0.0]
+            radius = 0.5
+            material = "white"
+
+            [objects.right]
+            type = "triangle"
+            vertices = [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [1.0, 0.0, 1.0]]
+            material = "white"
+        "#;
+        let error = super::parse_scene(source, std::path::Path::new(""))
+            .unwrap()
+            .build()
+            .err()
+            .unwrap();
+        assert!(error.to_string().starts_with("objects[0].right.type: "));
+
+        // Media have no surface
+        let medium = source.replace(
+            r#"type = "triangle"
+            vertices = [[1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [1.0, 0.0, 1.0]]
+            material = "white""#,
+            r#"type = "medium"
+            density = 1.0
+
+            [objects.right.boundary]
+            type = "sphere"
+            center = [1.0, 0.0, 0.0]
+            radius = 0.5
+            material = "white""#,
+        );
+        let error = super::parse_scene(&medium, std::path::Path::new(""))
+            .unwrap()
+            .build()
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("objects[0].right.type: CSG needs closed objects"));
+    }
+
+    #[test]
+    fn media() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [fog]
+            density = 0.01
+            anisotropy = 0.3
+
+            [materials.smoke]
+            type = "volume"
+            color = [0.5, 0.5, 0.5]
+            anisotropy = -0.2
+
+            [[objects]]
+            type = "medium"
+            density = 2.0
+
+            [objects.boundary]
+            type = "box"
+            min = [1.0, -1.0, -1.0]
+            max = [3.0, 1.0, 1.0]
+            material = "smoke"
+        "#;
+        let build = |source: &str| {
+            super::parse_scene(source, std::path::Path::new(""))
+                .unwrap()
+                .build()
+                .err()
+                .map(|error| error.to_string())
+        };
+        assert!(build(source).is_none());
+
+        let quad = source
+            .replace("type = \"box\"", "type = \"quad\"")
+            .replace("min = [1.0, -1.0, -1.0]", "corner = [1.0, -1.0, -1.0]")
+            .replace(
+                "max = [3.0, 1.0, 1.0]",
+                "u = [0.0, 2.0, 0.0]\nv = [0.0, 0.0, 2.0]",
+            );
+        let error = build(&quad).unwrap();
+        assert!(error.starts_with("objects[0].boundary.type: A medium needs closed objects"));
+        let voxels = source.replace(
+            "type = \"box\"",
+            "type = \"voxels\"\nfile = \"smoke.vox\"\nresolution = [2, 2, 2]",
+        );
+        let error = build(&voxels).unwrap();
+        assert!(error.starts_with("objects[0].boundary.type: A medium needs closed objects"));
+        let nested = source.replace(
+            "[objects.boundary]\n            type = \"box\"",
+            "[objects.boundary]\n            type = \"medium\"\n            density = 1.0\n\n            [objects.boundary.boundary]\n            type = \"box\"",
+        );
+        let error = build(&nested).unwrap();
+        assert!(error.starts_with("objects[0].boundary.type: A medium needs closed objects"));
+        let error = build(&source.replace("density = 2.0", "density = 0.0")).unwrap();
+        assert!(error.starts_with("objects[0].density: "));
+        let error = build(&source.replace("anisotropy = 0.3", "anisotropy = 1.0")).unwrap();
+        assert!(error.starts_with("fog.anisotropy: "));
+        let error = build(&source.replace("anisotropy = -0.2", "anisotropy = -1.5")).unwrap();
+        assert!(error.starts_with("materials.smoke: "));
+    }
+
+    #[test]
+    fn voxels() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.smoke]
+            type = "volume"
+            emission = { type = "blackbody", intensity = 2.0 }
+
+            [[objects]]
+            type = "voxels"
+            file = "missing.raw"
+            resolution = [8, 8, 8]
+            min = [1.0, -1.0, -1.0]
+            max = [3.0, 1.0, 1.0]
+            material = "smoke"
+        "#;
+        let build = |source: &str| {
+            super::parse_scene(source, std::path::Path::new(""))
+                .unwrap()
+                .build()
+                .err()
+                .unwrap()
+                .to_string()
+        };
+        assert!(build(source).starts_with("objects[0].file: missing.raw: "));
+        let error = build(&source.replace("max = [3.0, 1.0, 1.0]", "max = [3.0, -1.0, 1.0]"));
+        assert!(error.starts_with("objects[0].max: "));
+        let error = build(&source.replace(
+            "resolution = [8, 8, 8]",
+            "resolution = [8, 8, 8]\ndensity = -1.0",
+        ));
+        assert_eq!(
+            error,
+            "objects[0].density: Density must be greater than zero"
+        );
+    }
+
+    #[test]
+    fn normal_maps() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.rough]
+            type = "metal"
+
+            [materials.rough.normal_map]
+            type = "bump"
+            strength = 0.05
+            texture = { type = "noise", scale = 8.0 }
+
+            [materials.bricks]
+            type = "lambertian"
+            normal_map = "bricks_normal.png"
+        "#;
+        let error = super::parse_scene(source, std::path::Path::new(""))
+            .unwrap()
+            .build()
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.starts_with("materials.bricks: Unable to load image bricks_normal.png"));
+
+        let error = super::parse_scene(
+            &source.replace("type = \"bump\"", "type = \"height\""),
+            std::path::Path::new(""),
+        )
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(error.contains("height"));
+    }
+
+    #[test]
+    fn conductors() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.gold]
+            type = "conductor"
+            preset = "gold"
+            roughness = 0.4
+            anisotropy = 0.5
+
+            [materials.custom]
+            type = "conductor"
+            eta = [1.0, 1.0, 1.0]
+            k = [5.0, 5.0, 5.0]
+        "#;
+        let parse = |source: &str| {
+            super::parse_scene(source, std::path::Path::new(""))
+                .unwrap()
+                .build()
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        };
+        assert!(parse(source).is_ok());
+
+        let error = parse(&source.replace("\"gold\"\n", "\"brass\"\n")).unwrap_err();
+        assert!(error.starts_with("materials.gold: Unknown preset brass"));
+        let error = parse(&source.replace("k = [5.0, 5.0, 5.0]", "")).unwrap_err();
+        assert_eq!(
+            error,
+            "materials.custom: Needs either a preset or eta and k"
+        );
+        let error = parse(&source.replace("roughness = 0.4", "roughness = 1.5")).unwrap_err();
+        assert_eq!(error, "materials.gold: Roughness must be between 0 and 1");
+    }
+
+    #[test]
+    fn textures() {
+        let source = r#"
+            [camera]
+            position = [0.0, 0.0, 0.0]
+            direction = [1.0, 0.0, 0.0]
+
+            [materials.floor]
+            type = "lambertian"
+
+            [materials.floor.texture]
+            type = "checker"
+            frequency = 4.0
+
+            [materials.stone]
+            type = "lambertian"
+            texture = { type = "worley", mode = "edges", scale = 2.0 }
+
+            [materials.wall]
+            type = "lambertian"
+            texture = "missing.png"
+        "#;
+        let error = super::parse_scene(source, std::path::Path::new(""))
+            .unwrap()
+            .build()
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.starts_with("materials.wall: Unable to load image missing.png"));
+
+        let error = super::parse_scene(
+            &source.replace("texture = \"missing.png\"", "texture = 3"),
+            std::path::Path::new(""),
+        )
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(error.contains("texture"));
+    }
+}
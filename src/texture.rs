@@ -0,0 +1,276 @@
+//! Textures describe how a property of a material, mostly its color, changes over the surface.
+
+mod blackbody;
+mod noise;
+mod normal_map;
+mod procedural;
+
+pub use blackbody::BlackbodyTexture;
+pub use noise::{Perlin, Worley};
+pub use normal_map::NormalMap;
+pub use procedural::{
+    MarbleTexture, NoiseMode, NoiseTexture, WoodTexture, WorleyMode, WorleyTexture,
+};
+
+use crate::color::Color;
+use crate::geometry::Location;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+
+/// All textures must implement this trait
+pub trait Texture: std::fmt::Debug {
+    /// Returns the value of the texture at the given texture coordinates and location of the hit
+    fn value(&self, uv: (f64, f64), point: Location) -> Color;
+}
+
+/// A color is a texture that is the same everywhere
+impl Texture for Color {
+    fn value(&self, _uv: (f64, f64), _point: Location) -> Color {
+        *self
+    }
+}
+
+/// Alternating squares of two textures
+#[derive(Debug, Clone)]
+pub struct CheckerTexture {
+    pub even: Arc<dyn Texture + Send + Sync>,
+    pub odd: Arc<dyn Texture + Send + Sync>,
+    /// Number of squares per unit of the texture coordinates
+    pub frequency: f64,
+}
+
+impl CheckerTexture {
+    pub fn new(
+        even: Arc<dyn Texture + Send + Sync>,
+        odd: Arc<dyn Texture + Send + Sync>,
+        frequency: f64,
+    ) -> Self {
+        Self {
+            even,
+            odd,
+            frequency,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, uv: (f64, f64), point: Location) -> Color {
+        let u = (uv.0 * self.frequency).floor() as i64;
+        let v = (uv.1 * self.frequency).floor() as i64;
+        if (u + v).rem_euclid(2) == 0 {
+            self.even.value(uv, point)
+        } else {
+            self.odd.value(uv, point)
+        }
+    }
+}
+
+/// How texture coordinates outside of the range 0.0 to 1.0 are mapped to the image
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WrapMode {
+    /// The image is repeated
+    #[default]
+    Repeat,
+    /// The image is repeated, every second copy is mirrored
+    MirroredRepeat,
+    /// The pixels on the edge are continued
+    Clamp,
+}
+
+impl WrapMode {
+    /// Maps a pixel index to the image of the given size
+    fn wrap(self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let index = match self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::MirroredRepeat => {
+                let index = index.rem_euclid(2 * size);
+                if index < size {
+                    index
+                } else {
+                    2 * size - 1 - index
+                }
+            }
+            WrapMode::Clamp => index.clamp(0, size - 1),
+        };
+        index as usize
+    }
+}
+
+/// How the color between the pixel centers is computed
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Filter {
+    /// Color of the nearest pixel
+    Nearest,
+    /// Linear interpolation between the four nearest pixels
+    #[default]
+    Bilinear,
+}
+
+/// Texture from an image file
+#[derive(Debug, Clone)]
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Linear colors of the pixels, row by row starting at the top
+    pixels: Vec<Color>,
+    wrap: WrapMode,
+    filter: Filter,
+}
+
+impl ImageTexture {
+    /// Loads an image file. All formats of the image crate are supported.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let image = image::open(path.as_ref())
+            .map_err(|e| format!("Unable to load image {}: {}", path.as_ref().display(), e))?;
+        Ok(Self::from_image(&image.to_rgb8()))
+    }
+
+    /// Loads an image that stores data like normals or heights instead of colors.
+    /// The values of the pixels are used without gamma correction.
+    pub fn open_linear<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let image = image::open(path.as_ref())
+            .map_err(|e| format!("Unable to load image {}: {}", path.as_ref().display(), e))?;
+        Ok(Self::from_linear_image(&image.to_rgb8()))
+    }
+
+    /// Creates a texture from an image. The gamma correction of the output is undone for the pixel colors.
+    pub fn from_image(image: &image::RgbImage) -> Self {
+        Self::from_pixels(image, |value| value.powi(2))
+    }
+
+    /// Creates a texture from an image whose pixels are used without gamma correction
+    pub fn from_linear_image(image: &image::RgbImage) -> Self {
+        Self::from_pixels(image, |value| value)
+    }
+
+    fn from_pixels(image: &image::RgbImage, decode: impl Fn(f64) -> f64) -> Self {
+        let pixels = image
+            .pixels()
+            .map(|pixel| {
+                Color::new(
+                    decode(pixel.0[0] as f64 / 255.0),
+                    decode(pixel.0[1] as f64 / 255.0),
+                    decode(pixel.0[2] as f64 / 255.0),
+                )
+            })
+            .collect();
+        Self {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            pixels,
+            wrap: WrapMode::default(),
+            filter: Filter::default(),
+        }
+    }
+
+    /// Sets how texture coordinates outside of the image are handled
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Sets how the colors between the pixels are computed
+    pub fn with_filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    fn pixel(&self, x: i64, y: i64) -> Color {
+        let x = self.wrap.wrap(x, self.width);
+        let y = self.wrap.wrap(y, self.height);
+        self.pixels[y * self.width + x]
+    }
+}
+
+impl Texture for ImageTexture {
+    /// v goes from the bottom to the top of the image
+    fn value(&self, uv: (f64, f64), _point: Location) -> Color {
+        if self.pixels.is_empty() {
+            return Color::black();
+        }
+        // Position in pixels from the top left corner
+        let x = uv.0 * self.width as f64;
+        let y = (1.0 - uv.1) * self.height as f64;
+        match self.filter {
+            Filter::Nearest => self.pixel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                // Relative to the centers of the pixels
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let top = self.pixel(x0, y0) * (1.0 - fx) + self.pixel(x0 + 1, y0) * fx;
+                let bottom = self.pixel(x0, y0 + 1) * (1.0 - fx) + self.pixel(x0 + 1, y0 + 1) * fx;
+                top * (1.0 - fy) + bottom * fy
+            }
+        }
+    }
+}
+
+mod test {
+
+    #[test]
+    fn image_lookup() {
+        use super::Texture;
+        use crate::color::Color;
+        use crate::geometry::Location;
+
+        // Left pixel black, right pixel white, both rows the same
+        let mut image = image::RgbImage::new(2, 2);
+        image.put_pixel(1, 0, image::Rgb([255, 255, 255]));
+        image.put_pixel(1, 1, image::Rgb([255, 255, 255]));
+        let texture = super::ImageTexture::from_image(&image);
+
+        let p = Location::origin();
+        assert_eq!(texture.value((0.25, 0.5), p), Color::black());
+        assert_eq!(texture.value((0.75, 0.5), p), Color::white());
+        // Repeated outside of the unit square
+        assert_eq!(texture.value((1.75, -0.5), p), Color::white());
+    }
+
+    #[test]
+    fn filter_and_wrap() {
+        use super::{Filter, Texture, WrapMode};
+        use crate::color::Color;
+        use crate::geometry::Location;
+
+        let mut image = image::RgbImage::new(2, 1);
+        image.put_pixel(1, 0, image::Rgb([255, 255, 255]));
+        let p = Location::origin();
+
+        // Half way between the pixel centers
+        let texture = super::ImageTexture::from_image(&image);
+        assert_eq!(texture.value((0.5, 0.5), p), Color::white() * 0.5);
+        // Between the right pixel and the repeated left pixel
+        assert_eq!(texture.value((1.0, 0.5), p), Color::white() * 0.5);
+        let clamped = texture.clone().with_wrap(WrapMode::Clamp);
+        assert_eq!(clamped.value((1.0, 0.5), p), Color::white());
+        assert_eq!(clamped.value((-3.0, 0.5), p), Color::black());
+        let mirrored = texture.clone().with_wrap(WrapMode::MirroredRepeat);
+        assert_eq!(mirrored.value((1.25, 0.5), p), Color::white());
+
+        let nearest = texture.with_filter(Filter::Nearest);
+        assert_eq!(nearest.value((0.49, 0.5), p), Color::black());
+        assert_eq!(nearest.value((0.51, 0.5), p), Color::white());
+    }
+
+    #[test]
+    fn checker() {
+        use super::Texture;
+        use crate::color::Color;
+        use crate::geometry::Location;
+        use std::sync::Arc;
+
+        let checker =
+            super::CheckerTexture::new(Arc::new(Color::white()), Arc::new(Color::black()), 4.0);
+        let p = Location::origin();
+        assert_eq!(checker.value((0.1, 0.1), p), Color::white());
+        assert_eq!(checker.value((0.3, 0.1), p), Color::black());
+        assert_eq!(checker.value((0.3, 0.3), p), Color::white());
+        assert_eq!(checker.value((-0.1, 0.1), p), Color::black());
+    }
+}
@@ -0,0 +1,111 @@
+//! Colors of the light emitted by hot bodies like flames, from Planck's law.
+
+use super::Texture;
+use crate::color::Color;
+use crate::geometry::Location;
+
+/// Second radiation constant h * c / k in nanometer kelvin
+const C2: f64 = 1.4388e7;
+
+/// Multi lobe gaussian, wider on the side of the second width
+fn lobe(wavelength: f64, center: f64, low_width: f64, high_width: f64) -> f64 {
+    let width = if wavelength < center {
+        low_width
+    } else {
+        high_width
+    };
+    (-0.5 * ((wavelength - center) / width).powi(2)).exp()
+}
+
+/// CIE 1931 color matching functions, fitted by Wyman, Sloan and Shirley
+fn color_matching(wavelength: f64) -> [f64; 3] {
+    [
+        1.056 * lobe(wavelength, 599.8, 37.9, 31.0) + 0.362 * lobe(wavelength, 442.0, 16.0, 26.7)
+            - 0.065 * lobe(wavelength, 501.1, 20.4, 26.2),
+        0.821 * lobe(wavelength, 568.8, 46.9, 40.5) + 0.286 * lobe(wavelength, 530.9, 16.3, 31.1),
+        1.217 * lobe(wavelength, 437.0, 11.8, 36.0) + 0.681 * lobe(wavelength, 459.0, 26.0, 13.8),
+    ]
+}
+
+/// CIE XYZ of the spectrum of a black body, in arbitrary units
+fn blackbody_xyz(temperature: f64) -> [f64; 3] {
+    let mut xyz = [0.0; 3];
+    if temperature <= 0.0 {
+        return xyz;
+    }
+    for step in 0..=80 {
+        let wavelength = 380.0 + 5.0 * step as f64;
+        // Planck's law without the constant factors
+        let radiance = 1e15 / wavelength.powi(5) / ((C2 / (wavelength * temperature)).exp() - 1.0);
+        let matching = color_matching(wavelength);
+        for axis in 0..3 {
+            xyz[axis] += radiance * matching[axis];
+        }
+    }
+    xyz
+}
+
+/// Light of a black body whose temperature in kelvin is the texture coordinate v, like in the hits of grid
+/// media. The brightness is relative to a black body at the reference temperature, so hotter parts are much
+/// brighter and parts below about 800 kelvin are nearly black.
+#[derive(Debug, Clone)]
+pub struct BlackbodyTexture {
+    /// Factor from the xyz values to the returned color
+    scale: f64,
+}
+
+impl BlackbodyTexture {
+    /// `intensity` is the luminance at the reference temperature
+    pub fn new(intensity: f64, reference: f64) -> Self {
+        let luminance = blackbody_xyz(reference)[1];
+        Self {
+            scale: if luminance > 0.0 {
+                intensity / luminance
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// Linear rgb color of a black body at the temperature
+    pub fn color(&self, temperature: f64) -> Color {
+        let [x, y, z] = blackbody_xyz(temperature);
+        // XYZ to linear sRGB, colors outside of the gamut are clamped
+        Color::new(
+            (3.2406 * x - 1.5372 * y - 0.4986 * z).max(0.0),
+            (-0.9689 * x + 1.8758 * y + 0.0415 * z).max(0.0),
+            (0.0557 * x - 0.2040 * y + 1.0570 * z).max(0.0),
+        ) * self.scale
+    }
+}
+
+impl Texture for BlackbodyTexture {
+    fn value(&self, uv: (f64, f64), _point: Location) -> Color {
+        self.color(uv.1)
+    }
+}
+
+mod test {
+
+    #[test]
+    fn blackbody() {
+        use super::BlackbodyTexture;
+
+        let texture = BlackbodyTexture::new(1.0, 1500.0);
+        let luminance = |c: crate::color::Color| 0.2126 * c.r() + 0.7152 * c.g() + 0.0722 * c.b();
+
+        // Close to the requested luminance at the reference temperature
+        assert!((luminance(texture.color(1500.0)) - 1.0).abs() < 0.05);
+        // Embers are red, flames yellow and hot stars blue
+        let ember = texture.color(1000.0);
+        assert!(ember.r() > 5.0 * ember.g() && ember.b() < 1e-3 * ember.r());
+        let flame = texture.color(2500.0);
+        assert!(flame.r() > flame.g() && flame.g() > flame.b());
+        let star = texture.color(20000.0);
+        assert!(star.b() > star.r());
+        // Hotter is brighter, cold is black
+        assert!(luminance(flame) > 10.0 * luminance(texture.color(1500.0)));
+        assert!(luminance(texture.color(300.0)) < 1e-12);
+        assert_eq!(texture.color(0.0), crate::color::Color::black());
+    }
+}
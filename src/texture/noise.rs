@@ -0,0 +1,230 @@
+//! Noise functions for procedural textures. Both generators are deterministic for a seed.
+
+use crate::geometry::Location;
+use rand::prelude::*;
+use rand::rngs::SmallRng;
+
+/// Improved Perlin noise from "Improving Noise" by Ken Perlin
+#[derive(Debug, Clone)]
+pub struct Perlin {
+    /// Random permutation of 0 to 255, repeated once to avoid wrapping the indices
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut permutation: Vec<usize> = (0..256).collect();
+        permutation.shuffle(&mut SmallRng::seed_from_u64(seed));
+        permutation.extend_from_within(..);
+        Self { permutation }
+    }
+
+    /// Smooth noise in the range -1.0 to 1.0. It is zero on all integer coordinates.
+    pub fn noise(&self, point: Location) -> f64 {
+        let p = &self.permutation;
+        let (x, y, z) = (point.x(), point.y(), point.z());
+        // Cell of the point and position in the cell
+        let (xi, yi, zi) = (
+            (x.floor() as i64 & 255) as usize,
+            (y.floor() as i64 & 255) as usize,
+            (z.floor() as i64 & 255) as usize,
+        );
+        let (x, y, z) = (x - x.floor(), y - y.floor(), z - z.floor());
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        // Hashes of the eight corners
+        let a = p[xi] + yi;
+        let aa = p[a] + zi;
+        let ab = p[a + 1] + zi;
+        let b = p[xi + 1] + yi;
+        let ba = p[b] + zi;
+        let bb = p[b + 1] + zi;
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, grad(p[aa], x, y, z), grad(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    grad(p[ab], x, y - 1.0, z),
+                    grad(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    grad(p[aa + 1], x, y, z - 1.0),
+                    grad(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    grad(p[ab + 1], x, y - 1.0, z - 1.0),
+                    grad(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Fractal Brownian motion: sum of `octaves` layers of noise, each with double frequency and half amplitude.
+    /// The result is in the range -1.0 to 1.0.
+    pub fn fbm(&self, point: Location, octaves: u32) -> f64 {
+        self.octaves(point, octaves, |noise| noise)
+    }
+
+    /// Like `fbm` but sums the absolute values, which gives sharp creases. The result is in the range 0.0 to 1.0.
+    pub fn turbulence(&self, point: Location, octaves: u32) -> f64 {
+        self.octaves(point, octaves, f64::abs)
+    }
+
+    fn octaves<F: Fn(f64) -> f64>(&self, point: Location, octaves: u32, layer: F) -> f64 {
+        let mut sum = 0.0;
+        let mut total = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for _ in 0..octaves.max(1) {
+            let p = Location::new(
+                point.x() * frequency,
+                point.y() * frequency,
+                point.z() * frequency,
+            );
+            sum += layer(self.noise(p)) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / total
+    }
+}
+
+/// Smoothstep of the improved noise with zero first and second derivative at 0 and 1
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Dot product of the offset with one of twelve gradient directions selected by the hash
+fn grad(hash: usize, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+/// Worley noise, also called cellular noise. Every unit cell contains one random feature point,
+/// the noise is the distance to the nearest points.
+#[derive(Debug, Copy, Clone)]
+pub struct Worley {
+    seed: u64,
+}
+
+impl Worley {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    /// Distances to the nearest and the second nearest feature point
+    pub fn distances(&self, point: Location) -> (f64, f64) {
+        let cell = [point.x().floor(), point.y().floor(), point.z().floor()];
+        let mut nearest = f64::MAX;
+        let mut second = f64::MAX;
+        // The nearest points are in the neighbouring cells
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbour = [
+                        cell[0] + dx as f64,
+                        cell[1] + dy as f64,
+                        cell[2] + dz as f64,
+                    ];
+                    let feature = self.feature_point(neighbour);
+                    let distance = (feature - point).length();
+                    if distance < nearest {
+                        second = nearest;
+                        nearest = distance;
+                    } else if distance < second {
+                        second = distance;
+                    }
+                }
+            }
+        }
+        (nearest, second)
+    }
+
+    /// Random point in the cell with the given lower corner
+    fn feature_point(&self, cell: [f64; 3]) -> Location {
+        let mut hash = self.seed;
+        for c in cell.iter() {
+            hash = mix(hash ^ (*c as i64 as u64));
+        }
+        let mut offset = [0.0; 3];
+        for o in offset.iter_mut() {
+            hash = mix(hash);
+            // 53 random bits in the range 0.0 to 1.0
+            *o = (hash >> 11) as f64 / (1u64 << 53) as f64;
+        }
+        Location::new(
+            cell[0] + offset[0],
+            cell[1] + offset[1],
+            cell[2] + offset[2],
+        )
+    }
+}
+
+/// Hash function of splitmix64
+fn mix(z: u64) -> u64 {
+    let z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+mod test {
+
+    #[test]
+    fn perlin_noise() {
+        use crate::geometry::Location;
+
+        let perlin = super::Perlin::new(1);
+        assert_eq!(perlin.noise(Location::new(3.0, -2.0, 7.0)), 0.0);
+        let p = Location::new(0.3, 1.7, -4.2);
+        // Deterministic for the seed, different for other seeds
+        assert_eq!(perlin.noise(p), super::Perlin::new(1).noise(p));
+        assert_ne!(perlin.noise(p), super::Perlin::new(2).noise(p));
+
+        for i in 0..1000 {
+            let p = Location::new(i as f64 * 0.137, i as f64 * 0.071, i as f64 * -0.093);
+            assert!(perlin.noise(p).abs() <= 1.0);
+            assert!(perlin.fbm(p, 6).abs() <= 1.0);
+            let turbulence = perlin.turbulence(p, 6);
+            assert!((0.0..=1.0).contains(&turbulence));
+        }
+    }
+
+    #[test]
+    fn worley_noise() {
+        use crate::geometry::Location;
+
+        let worley = super::Worley::new(3);
+        for i in 0..1000 {
+            let p = Location::new(i as f64 * 0.137, i as f64 * -0.071, i as f64 * 0.093);
+            let (nearest, second) = worley.distances(p);
+            assert!(nearest <= second);
+            // There is a feature point in the cell of the point
+            assert!(nearest < 3.0_f64.sqrt());
+        }
+        // A feature point has the distance zero
+        let feature = worley.feature_point([2.0, -5.0, 1.0]);
+        assert_eq!(worley.distances(feature).0, 0.0);
+    }
+}
@@ -0,0 +1,142 @@
+//! Normal maps change the normal used for shading, so flat surfaces look like they have small details.
+
+use super::Texture;
+use crate::geometry::Direction;
+use crate::scene::Hit;
+use std::sync::Arc;
+
+/// Step of the finite differences of bump maps in texture coordinates
+const BUMP_STEP: f64 = 1e-3;
+
+/// Source of the shading normal of a surface
+#[derive(Debug, Clone)]
+pub enum NormalMap {
+    /// Normals in tangent space stored as colors like in most normal map images. Red points along the
+    /// tangent, green along the bitangent and blue along the normal. The texture must not be gamma corrected.
+    Tangent {
+        texture: Arc<dyn Texture + Send + Sync>,
+        /// Scales the tilt of the normals, 1.0 keeps the normals of the texture
+        strength: f64,
+    },
+    /// Heights given by the brightness of the texture. The normal is tilted along the slope of the heights.
+    Bump {
+        texture: Arc<dyn Texture + Send + Sync>,
+        /// Height of the brightest value in world units
+        strength: f64,
+    },
+}
+
+impl NormalMap {
+    /// Returns the shading normal at the hit. It points away from the surface on the same side as the normal
+    /// of the hit, normals below the surface are replaced by the normal of the hit.
+    pub fn normal(&self, hit: &Hit) -> Direction {
+        let (tangent, bitangent) = (hit.tangent, hit.bitangent());
+        let normal = match self {
+            NormalMap::Tangent { texture, strength } => {
+                let value = texture.value(hit.uv, hit.point);
+                tangent * ((2.0 * value.r() - 1.0) * strength)
+                    + bitangent * ((2.0 * value.g() - 1.0) * strength)
+                    + hit.normal * (2.0 * value.b() - 1.0)
+            }
+            NormalMap::Bump { texture, strength } => {
+                let height = |du: f64, dv: f64| {
+                    let point = hit.point
+                        + tangent * (du * hit.uv_scale.0)
+                        + bitangent * (dv * hit.uv_scale.1);
+                    let value = texture.value((hit.uv.0 + du, hit.uv.1 + dv), point);
+                    (value.r() + value.g() + value.b()) / 3.0 * strength
+                };
+                // Slopes per world unit, the texture coordinates of most objects do not follow the world size
+                let center = height(0.0, 0.0);
+                let slope = |step: f64, scale: f64| {
+                    if scale > 1e-12 {
+                        (step - center) / (BUMP_STEP * scale)
+                    } else {
+                        0.0
+                    }
+                };
+                let slope_u = slope(height(BUMP_STEP, 0.0), hit.uv_scale.0);
+                let slope_v = slope(height(0.0, BUMP_STEP), hit.uv_scale.1);
+                hit.normal - tangent * slope_u - bitangent * slope_v
+            }
+        };
+        let length = normal.length();
+        if length > 1e-12 && normal.dot(hit.normal) > 0.0 {
+            normal / length
+        } else {
+            hit.normal
+        }
+    }
+}
+
+mod test {
+
+    #[test]
+    fn normal_maps() {
+        use super::NormalMap;
+        use crate::color::Color;
+        use crate::geometry::{Direction, Location};
+        use crate::material::Lambertian;
+        use crate::scene::{Hit, Ray};
+        use crate::texture::Texture;
+        use std::sync::Arc;
+
+        // Heights rise along x, which is the direction of u
+        #[derive(Debug)]
+        struct Ramp;
+        impl Texture for Ramp {
+            fn value(&self, uv: (f64, f64), _point: Location) -> Color {
+                Color::white() * uv.0
+            }
+        }
+
+        let material = Lambertian::new(Color::white());
+        let ray = Ray {
+            origin: Location::new(0.0, 0.0, 1.0),
+            direction: Direction::new(0.0, 0.0, -1.0),
+            time: 0.0,
+        };
+        let hit = Hit::new(
+            &ray,
+            1.0,
+            Direction::new(0.0, 0.0, 1.0),
+            (0.5, 0.5),
+            &material,
+        )
+        .with_tangent(Direction::new(1.0, 0.0, 0.0));
+        assert!((hit.bitangent() - Direction::new(0.0, 1.0, 0.0)).length() < 1e-12);
+
+        // A flat normal map keeps the normal
+        let flat = NormalMap::Tangent {
+            texture: Arc::new(Color::new(0.5, 0.5, 1.0)),
+            strength: 1.0,
+        };
+        assert!((flat.normal(&hit) - hit.normal).length() < 1e-12);
+
+        // Tilted towards the tangent and the bitangent
+        let tilted = NormalMap::Tangent {
+            texture: Arc::new(Color::new(1.0, 0.0, 1.0)),
+            strength: 1.0,
+        };
+        let expected = Direction::new(1.0, -1.0, 1.0).norm();
+        assert!((tilted.normal(&hit) - expected).length() < 1e-12);
+
+        // The normal of a slope rising along u tilts backwards
+        let bump = NormalMap::Bump {
+            texture: Arc::new(Ramp),
+            strength: 1.0,
+        };
+        let expected = Direction::new(-1.0, 0.0, 1.0).norm();
+        assert!((bump.normal(&hit) - expected).length() < 1e-9);
+        // The same heights spread over twice the length are half as steep
+        let expected = Direction::new(-0.5, 0.0, 1.0).norm();
+        assert!((bump.normal(&hit.with_uv_scale(2.0, 1.0)) - expected).length() < 1e-9);
+
+        // Normals below the surface are ignored
+        let below = NormalMap::Tangent {
+            texture: Arc::new(Color::new(0.5, 0.5, 0.0)),
+            strength: 1.0,
+        };
+        assert!((below.normal(&hit) - hit.normal).length() < 1e-12);
+    }
+}
@@ -0,0 +1,193 @@
+//! Solid textures computed from noise at the location of the hit. They need no texture coordinates and do not
+//! repeat, so they work on large surfaces.
+
+use super::noise::{Perlin, Worley};
+use super::Texture;
+use crate::color::Color;
+use crate::geometry::Location;
+use serde::Deserialize;
+
+/// Color between `a` at 0.0 and `b` at 1.0
+fn mix(a: Color, b: Color, t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    a * (1.0 - t) + b * t
+}
+
+fn scaled(point: Location, scale: f64) -> Location {
+    Location::new(point.x() * scale, point.y() * scale, point.z() * scale)
+}
+
+/// Which sum of the noise layers is used
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NoiseMode {
+    /// Fractal Brownian motion, soft clouds
+    #[default]
+    Fbm,
+    /// Sum of the absolute values, clouds with sharp creases
+    Turbulence,
+}
+
+/// Perlin noise between two colors
+#[derive(Debug, Clone)]
+pub struct NoiseTexture {
+    pub noise: Perlin,
+    /// Frequency of the noise per unit in the world
+    pub scale: f64,
+    pub octaves: u32,
+    pub mode: NoiseMode,
+    pub low: Color,
+    pub high: Color,
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _uv: (f64, f64), point: Location) -> Color {
+        let p = scaled(point, self.scale);
+        let t = match self.mode {
+            NoiseMode::Fbm => 0.5 * (self.noise.fbm(p, self.octaves) + 1.0),
+            NoiseMode::Turbulence => self.noise.turbulence(p, self.octaves),
+        };
+        mix(self.low, self.high, t)
+    }
+}
+
+/// Layers of veins stacked along the z axis, distorted by turbulence
+#[derive(Debug, Clone)]
+pub struct MarbleTexture {
+    pub noise: Perlin,
+    /// Number of veins per unit in the world, divided by 2π
+    pub scale: f64,
+    /// Strength of the distortion of the veins
+    pub turbulence: f64,
+    pub octaves: u32,
+    pub base: Color,
+    pub vein: Color,
+}
+
+impl Texture for MarbleTexture {
+    fn value(&self, _uv: (f64, f64), point: Location) -> Color {
+        let p = scaled(point, self.scale);
+        let phase = p.z() + self.turbulence * self.noise.turbulence(p, self.octaves);
+        // Thin veins where the sine is near its minimum
+        let t = (0.5 * (1.0 + phase.sin())).sqrt();
+        mix(self.vein, self.base, t)
+    }
+}
+
+/// Growth rings around the z axis, distorted by noise
+#[derive(Debug, Clone)]
+pub struct WoodTexture {
+    pub noise: Perlin,
+    /// Number of rings per unit in the world
+    pub scale: f64,
+    /// Strength of the distortion of the rings
+    pub turbulence: f64,
+    pub octaves: u32,
+    pub light: Color,
+    pub dark: Color,
+}
+
+impl Texture for WoodTexture {
+    fn value(&self, _uv: (f64, f64), point: Location) -> Color {
+        let p = scaled(point, self.scale);
+        let rings = p.x().hypot(p.y()) + self.turbulence * self.noise.fbm(p, self.octaves);
+        // Light early wood fading into the dark late wood of each ring
+        mix(self.light, self.dark, rings.rem_euclid(1.0).powi(2))
+    }
+}
+
+/// Which distances of the Worley noise are used
+#[derive(Debug, Copy, Clone, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WorleyMode {
+    /// Distance to the nearest feature point, round spots
+    #[default]
+    Nearest,
+    /// Difference of the two nearest distances, which is zero on the borders of the cells
+    Edges,
+}
+
+/// Cellular pattern from Worley noise
+#[derive(Debug, Clone)]
+pub struct WorleyTexture {
+    pub noise: Worley,
+    /// Number of cells per unit in the world
+    pub scale: f64,
+    pub mode: WorleyMode,
+    /// Color at the feature points or at the borders of the cells
+    pub inner: Color,
+    pub outer: Color,
+}
+
+impl Texture for WorleyTexture {
+    fn value(&self, _uv: (f64, f64), point: Location) -> Color {
+        let (nearest, second) = self.noise.distances(scaled(point, self.scale));
+        let t = match self.mode {
+            WorleyMode::Nearest => nearest,
+            WorleyMode::Edges => second - nearest,
+        };
+        mix(self.inner, self.outer, t)
+    }
+}
+
+mod test {
+
+    #[test]
+    fn procedural_textures() {
+        use super::{
+            MarbleTexture, NoiseMode, NoiseTexture, WoodTexture, WorleyMode, WorleyTexture,
+        };
+        use crate::color::Color;
+        use crate::geometry::Location;
+        use crate::texture::{Perlin, Texture, Worley};
+
+        let textures: Vec<Box<dyn Texture>> = vec![
+            Box::new(NoiseTexture {
+                noise: Perlin::new(0),
+                scale: 4.0,
+                octaves: 6,
+                mode: NoiseMode::Turbulence,
+                low: Color::black(),
+                high: Color::white(),
+            }),
+            Box::new(MarbleTexture {
+                noise: Perlin::new(0),
+                scale: 4.0,
+                turbulence: 5.0,
+                octaves: 6,
+                base: Color::white(),
+                vein: Color::black(),
+            }),
+            Box::new(WoodTexture {
+                noise: Perlin::new(0),
+                scale: 4.0,
+                turbulence: 0.5,
+                octaves: 4,
+                light: Color::white(),
+                dark: Color::black(),
+            }),
+            Box::new(WorleyTexture {
+                noise: Worley::new(0),
+                scale: 4.0,
+                mode: WorleyMode::Edges,
+                inner: Color::black(),
+                outer: Color::white(),
+            }),
+        ];
+        for texture in &textures {
+            let mut values = vec![];
+            for i in 0..200 {
+                let p = Location::new(i as f64 * 0.0137, i as f64 * 0.031, i as f64 * -0.023);
+                let value = texture.value((0.0, 0.0), p);
+                // Gray between the two colors, independent of the texture coordinates
+                assert!((0.0..=1.0).contains(&value.r()));
+                assert_eq!(value, texture.value((0.7, 0.2), p));
+                values.push(value.r());
+            }
+            // The pattern varies over the surface
+            let min = values.iter().cloned().fold(f64::MAX, f64::min);
+            let max = values.iter().cloned().fold(f64::MIN, f64::max);
+            assert!(max - min > 0.3);
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Loader for dense voxel grids, used for volumes like smoke or fire from simulations.
+//!
+//! Two formats are supported, both store little endian 32 bit floats with x changing fastest, then y, then z:
+//! - raw files contain only the densities, the resolution has to be known
+//! - grid files start with the magic bytes `VXG1`, the resolution in x, y and z and the number of channels as
+//!   unsigned 32 bit integers. The first channel holds the densities, an optional second one the temperatures
+//!   in kelvin. Each channel is stored completely before the next one.
+
+use std::path::{Path, PathBuf};
+
+/// Magic bytes at the start of a grid file
+const MAGIC: &[u8; 4] = b"VXG1";
+
+/// Error while loading a voxel file
+#[derive(Debug, Clone)]
+pub struct VoxelError {
+    pub file: PathBuf,
+    pub message: String,
+}
+
+impl std::fmt::Display for VoxelError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: {}", self.file.display(), self.message)
+    }
+}
+
+impl std::error::Error for VoxelError {}
+
+/// Densities and optional temperatures at the centers of the cells of a regular grid
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    /// Number of cells in x, y and z
+    resolution: [usize; 3],
+    density: Vec<f32>,
+    temperature: Option<Vec<f32>>,
+    /// Largest density of the grid
+    max_density: f64,
+}
+
+impl VoxelGrid {
+    /// Creates a grid from the densities with x changing fastest. Returns None if the number of values does not
+    /// match the resolution or a density is negative or not finite.
+    pub fn new(resolution: [usize; 3], density: Vec<f32>) -> Option<Self> {
+        if resolution.contains(&0)
+            || Some(density.len()) != cell_count(resolution)
+            || !valid_values(&density)
+        {
+            return None;
+        }
+        let max_density = density.iter().fold(0.0f64, |max, &d| max.max(d as f64));
+        Some(Self {
+            resolution,
+            density,
+            temperature: None,
+            max_density,
+        })
+    }
+
+    /// Adds temperatures in kelvin in the same order as the densities. Returns None if the number does not match
+    /// or a temperature is negative or not finite.
+    pub fn with_temperature(mut self, temperature: Vec<f32>) -> Option<Self> {
+        if temperature.len() != self.density.len() || !valid_values(&temperature) {
+            return None;
+        }
+        self.temperature = Some(temperature);
+        Some(self)
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    pub fn max_density(&self) -> f64 {
+        self.max_density
+    }
+
+    /// Interpolated density at a position given relative to the grid, 0.0 to 1.0 on each axis
+    pub fn density(&self, position: [f64; 3]) -> f64 {
+        self.interpolate(&self.density, position)
+    }
+
+    /// Interpolated temperature at a position relative to the grid. Zero if the grid has no temperatures.
+    pub fn temperature(&self, position: [f64; 3]) -> f64 {
+        match &self.temperature {
+            Some(temperature) => self.interpolate(temperature, position),
+            None => 0.0,
+        }
+    }
+
+    /// Trilinear interpolation between the cell centers. The cells on the border are continued to the edges.
+    fn interpolate(&self, values: &[f32], position: [f64; 3]) -> f64 {
+        let mut low = [0; 3];
+        let mut high = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let size = self.resolution[axis];
+            let x = (position[axis] * size as f64 - 0.5).clamp(0.0, (size - 1) as f64);
+            low[axis] = x.floor() as usize;
+            high[axis] = (low[axis] + 1).min(size - 1);
+            fraction[axis] = x - low[axis] as f64;
+        }
+        let value = |x: usize, y: usize, z: usize| {
+            values[(z * self.resolution[1] + y) * self.resolution[0] + x] as f64
+        };
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let [fx, fy, fz] = fraction;
+        let plane = |z: usize| {
+            lerp(
+                lerp(value(low[0], low[1], z), value(high[0], low[1], z), fx),
+                lerp(value(low[0], high[1], z), value(high[0], high[1], z), fx),
+                fy,
+            )
+        };
+        lerp(plane(low[2]), plane(high[2]), fz)
+    }
+}
+
+/// Loads a grid file
+pub fn load_voxels<P: AsRef<Path>>(path: P) -> Result<VoxelGrid, VoxelError> {
+    let path = path.as_ref();
+    parse_voxels(&read_file(path)?, path)
+}
+
+/// Loads a raw file of densities with the given resolution
+pub fn load_raw_voxels<P: AsRef<Path>>(
+    path: P,
+    resolution: [usize; 3],
+) -> Result<VoxelGrid, VoxelError> {
+    let path = path.as_ref();
+    parse_raw_voxels(&read_file(path)?, resolution, path)
+}
+
+/// Parses the content of a grid file. `file` is used for error messages.
+pub fn parse_voxels(data: &[u8], file: &Path) -> Result<VoxelGrid, VoxelError> {
+    let error = |message: &str| VoxelError {
+        file: file.to_path_buf(),
+        message: message.into(),
+    };
+    if data.len() < 20 || &data[..4] != MAGIC {
+        return Err(error("Not a voxel grid file"));
+    }
+    let header: Vec<usize> = data[4..20]
+        .chunks_exact(4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+        .collect();
+    let resolution = [header[0], header[1], header[2]];
+    let channels = header[3];
+    if channels != 1 && channels != 2 {
+        return Err(error("Only one or two channels are supported"));
+    }
+    let count = cell_count(resolution).ok_or_else(|| error("Resolution too large"))?;
+    let size = count
+        .checked_mul(channels)
+        .ok_or_else(|| error("Resolution too large"))?;
+    let mut values = floats(&data[20..]);
+    if values.len() != size {
+        return Err(error("Size of the data does not match the resolution"));
+    }
+    if !valid_values(&values) {
+        return Err(error("Values must be finite and not negative"));
+    }
+    let temperature = values.split_off(count);
+    let grid = VoxelGrid::new(resolution, values).ok_or_else(|| error("Empty grid"))?;
+    Ok(if channels == 2 {
+        grid.with_temperature(temperature).unwrap()
+    } else {
+        grid
+    })
+}
+
+/// Parses the content of a raw file. `file` is used for error messages.
+pub fn parse_raw_voxels(
+    data: &[u8],
+    resolution: [usize; 3],
+    file: &Path,
+) -> Result<VoxelGrid, VoxelError> {
+    let error = |message: &str| VoxelError {
+        file: file.to_path_buf(),
+        message: message.into(),
+    };
+    let values = if data.len().is_multiple_of(4) {
+        floats(data)
+    } else {
+        Vec::new()
+    };
+    if !valid_values(&values) {
+        return Err(error("Values must be finite and not negative"));
+    }
+    VoxelGrid::new(resolution, values)
+        .ok_or_else(|| error("Size of the data does not match the resolution"))
+}
+
+/// Densities and temperatures can not be negative, NaN or infinite
+fn valid_values(values: &[f32]) -> bool {
+    values
+        .iter()
+        .all(|value| value.is_finite() && *value >= 0.0)
+}
+
+/// Number of cells of the resolution, None if it does not fit into usize
+fn cell_count(resolution: [usize; 3]) -> Option<usize> {
+    resolution
+        .iter()
+        .try_fold(1usize, |count, &size| count.checked_mul(size))
+}
+
+/// Little endian floats of the data, remaining bytes are ignored
+fn floats(data: &[u8]) -> Vec<f32> {
+    data.chunks_exact(4)
+        .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, VoxelError> {
+    std::fs::read(path).map_err(|e| VoxelError {
+        file: path.to_path_buf(),
+        message: e.to_string(),
+    })
+}
+
+mod test {
+
+    #[test]
+    fn parse_grids() {
+        use std::path::Path;
+
+        // 2x1x1 grid with density and temperature
+        let mut data = b"VXG1".to_vec();
+        for value in &[2u32, 1, 1, 2] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in &[0.0f32, 1.0, 1000.0, 2000.0] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        let grid = super::parse_voxels(&data, Path::new("fire.vxg")).unwrap();
+        assert_eq!(grid.resolution(), [2, 1, 1]);
+        assert_eq!(grid.max_density(), 1.0);
+        // Values at the cell centers, interpolated between them and continued to the edges
+        assert_eq!(grid.density([0.25, 0.5, 0.5]), 0.0);
+        assert_eq!(grid.density([0.5, 0.5, 0.5]), 0.5);
+        assert_eq!(grid.density([1.0, 0.0, 1.0]), 1.0);
+        assert_eq!(grid.temperature([0.5, 0.5, 0.5]), 1500.0);
+
+        let error =
+            super::parse_voxels(&data[..data.len() - 4], Path::new("fire.vxg")).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "fire.vxg: Size of the data does not match the resolution"
+        );
+
+        // Raw densities without header
+        let grid =
+            super::parse_raw_voxels(&data[20..36], [2, 2, 1], Path::new("smoke.raw")).unwrap();
+        assert_eq!(grid.temperature([0.5, 0.5, 0.5]), 0.0);
+        assert_eq!(grid.max_density(), 2000.0);
+        assert!(super::parse_raw_voxels(&data[20..36], [2, 1, 1], Path::new("smoke.raw")).is_err());
+
+        // Negative and NaN values
+        for value in &[-1.0f32, f32::NAN, f32::INFINITY] {
+            let mut data = data.clone();
+            data[24..28].copy_from_slice(&value.to_le_bytes());
+            let error = super::parse_voxels(&data, Path::new("fire.vxg")).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "fire.vxg: Values must be finite and not negative"
+            );
+            assert!(
+                super::parse_raw_voxels(&data[20..28], [2, 1, 1], Path::new("smoke.raw")).is_err()
+            );
+            assert!(super::VoxelGrid::new([1, 1, 1], vec![*value]).is_none());
+        }
+
+        // Resolutions with more cells than fit into usize
+        assert!(super::VoxelGrid::new([usize::MAX, 2, 1], vec![0.0]).is_none());
+        let mut data = b"VXG1".to_vec();
+        for value in &[u32::MAX, u32::MAX, u32::MAX, 1] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        let error = super::parse_voxels(&data, Path::new("huge.vxg")).unwrap_err();
+        assert_eq!(error.to_string(), "huge.vxg: Resolution too large");
+    }
+}